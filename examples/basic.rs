@@ -50,11 +50,11 @@ fn main() -> Result<(), Box<dyn Error>> {
     //           |-------- Child 1
     //                     |-------- Grandchild
 
-    print_tree::<Tree>(&frame, root);
+    print!("{}", format_tree::<Tree, _, _>(&frame, root, label));
 
-    frame.despawn_all::<Tree>(child2);
+    frame.despawn_all::<Tree>(child2).unwrap();
 
-    print_tree::<Tree>(&frame, root);
+    print!("{}", format_tree::<Tree, _, _>(&frame, root, label));
 
     frame
         .iter()
@@ -63,23 +63,6 @@ fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-fn print_tree<T: 'static + Send + Sync>(frame: &Frame, root: Entity) {
-    fn internal<T: 'static + Send + Sync>(frame: &Frame, parent: Entity, depth: usize) {
-        for child in frame.children::<T>(parent) {
-            let name = frame.get::<&&str>(child).unwrap();
-            println!(
-                "{}|-------- {}",
-                std::iter::repeat(" ")
-                    .take((depth - 1) * 10)
-                    .collect::<String>(),
-                *name,
-            );
-
-            internal::<T>(frame, child, depth + 1)
-        }
-    }
-
-    let name = frame.get::<&&str>(root).unwrap();
-    println!("{}", *name);
-    internal::<T>(frame, root, 1)
+fn label(frame: &Frame, entity: Entity) -> String {
+    frame.get::<&&str>(entity).unwrap().to_string()
 }
@@ -1,10 +1,14 @@
-use std::marker::PhantomData;
+use std::{collections::HashMap, marker::PhantomData};
 
 use moss_hecs::{Component, DynamicBundle, Entity, EntityBuilder, Frame};
 use moss_hecs_schedule::{CommandBuffer, GenericWorld};
 use once_cell::sync::OnceCell;
 
-use crate::{HierarchyMut, TreeBuilderClone};
+use crate::{
+    hierarchy::attach_at,
+    policy::{check_policy_all, num_children},
+    Hierarchy, HierarchyError, HierarchyMut, HierarchyResult, Role, TreeBuilderClone,
+};
 
 /// Ergonomically construct trees without knowledge of frame.
 ///
@@ -42,6 +46,7 @@ pub struct TreeBuilder<T> {
     builder: EntityBuilder,
     marker: PhantomData<T>,
     reserved: OnceCell<Entity>,
+    label: Option<String>,
 }
 
 impl<T: Component> TreeBuilder<T> {
@@ -52,6 +57,7 @@ impl<T: Component> TreeBuilder<T> {
             builder: EntityBuilder::new(),
             marker: PhantomData,
             reserved: OnceCell::new(),
+            label: None,
         }
     }
 
@@ -60,29 +66,151 @@ impl<T: Component> TreeBuilder<T> {
         *self.reserved.get_or_init(|| frame.reserve())
     }
 
-    /// Spawn the whole tree into the frame
+    /// Spawn the whole tree into the frame, returning the root. Use [Self::spawn_labeled] to
+    /// retrieve the entities of labeled nodes as well.
     pub fn spawn(&mut self, frame: &mut Frame) -> Entity {
+        self.spawn_labeled(frame).root()
+    }
+
+    /// Spawn the whole tree into the frame, returning the root along with the entities of all
+    /// labeled nodes.
+    pub fn spawn_labeled(&mut self, frame: &mut Frame) -> SpawnedTree {
+        let mut labels = HashMap::new();
+        let root = self.spawn_inner(frame, &mut labels);
+        SpawnedTree { root, labels }
+    }
+
+    fn spawn_inner(&mut self, frame: &mut Frame, labels: &mut HashMap<String, Entity>) -> Entity {
         let parent = self.reserve(frame);
         let builder = self.builder.build();
         frame.insert(parent, builder).unwrap();
 
+        if let Some(label) = self.label.take() {
+            labels.insert(label, parent);
+        }
+
         for mut child in self.children.drain(..) {
-            let child = child.spawn(frame);
+            let child = child.spawn_inner(frame, labels);
             frame.attach::<T>(child, parent).unwrap();
         }
 
         parent
     }
 
-    /// Spawn the whole tree into a commandbuffer.
+    /// Graft the tree onto an existing entity. Instead of spawning a new root, the components of
+    /// the root are added to `parent` and the children are spawned and attached as the last
+    /// children of `parent`. A reserved root entity is left unused.
+    ///
+    /// Fails with the error of the enabled [HierarchyPolicy](crate::HierarchyPolicy) of `T` if a
+    /// child may not be attached to `parent`. The spawned children are despawned again and the
+    /// children of `parent` are left as they were, while the components of the root stay on
+    /// `parent`.
+    pub fn graft(&mut self, frame: &mut Frame, parent: Entity) -> HierarchyResult<SpawnedTree> {
+        self.graft_inner(frame, parent, None)
+    }
+
+    /// Same as [Self::graft], but attaches the children starting at `index` among the existing
+    /// children of `parent`, or last if `parent` has fewer children.
+    pub fn graft_at(
+        &mut self,
+        frame: &mut Frame,
+        parent: Entity,
+        index: usize,
+    ) -> HierarchyResult<SpawnedTree> {
+        self.graft_inner(frame, parent, Some(index))
+    }
+
+    fn graft_inner(
+        &mut self,
+        frame: &mut Frame,
+        parent: Entity,
+        index: Option<usize>,
+    ) -> HierarchyResult<SpawnedTree> {
+        let builder = self.builder.build();
+        frame
+            .insert(parent, builder)
+            .map_err(|_| HierarchyError::NoSuchEntity {
+                entity: parent,
+                which: Role::Parent,
+            })?;
+
+        let mut labels = HashMap::new();
+        if let Some(label) = self.label.take() {
+            labels.insert(label, parent);
+        }
+
+        let children = self
+            .children
+            .drain(..)
+            .map(|mut child| child.spawn_inner(frame, &mut labels))
+            .collect::<Vec<_>>();
+
+        // The children are checked as a whole before attaching any of them, as the components of
+        // the spawned subtrees may be what the policy inspects
+        let num_children = num_children::<T>(frame, parent) + children.len();
+        if let Err(err) = check_policy_all::<T>(frame, &children, parent, num_children) {
+            for &child in &children {
+                let subtree = std::iter::once(child)
+                    .chain(frame.descendants_depth_first::<T>(child))
+                    .collect::<Vec<_>>();
+                for entity in subtree {
+                    let _ = frame.despawn(entity);
+                }
+            }
+            return Err(err);
+        }
+
+        for (i, child) in children.into_iter().enumerate() {
+            match index {
+                Some(index) => attach_at::<T>(frame, child, parent, index + i)?,
+                None => {
+                    frame.attach::<T>(child, parent)?;
+                }
+            }
+        }
+
+        Ok(SpawnedTree {
+            root: parent,
+            labels,
+        })
+    }
+
+    /// Spawn the whole tree into a commandbuffer, returning the root. Use
+    /// [Self::spawn_labeled_deferred] to retrieve the entities of labeled nodes as well.
     /// The frame is required for reserving entities.
     pub fn spawn_deferred(&mut self, frame: &impl GenericWorld, cmd: &mut CommandBuffer) -> Entity {
+        self.spawn_labeled_deferred(frame, cmd).root()
+    }
+
+    /// Spawn the whole tree into a commandbuffer, returning the root along with the entities
+    /// of all labeled nodes.
+    /// The frame is required for reserving entities.
+    pub fn spawn_labeled_deferred(
+        &mut self,
+        frame: &impl GenericWorld,
+        cmd: &mut CommandBuffer,
+    ) -> SpawnedTree {
+        let mut labels = HashMap::new();
+        let root = self.spawn_deferred_inner(frame, cmd, &mut labels);
+        SpawnedTree { root, labels }
+    }
+
+    fn spawn_deferred_inner(
+        &mut self,
+        frame: &impl GenericWorld,
+        cmd: &mut CommandBuffer,
+        labels: &mut HashMap<String, Entity>,
+    ) -> Entity {
         let parent = self.reserve(frame);
         let builder = self.builder.build();
         cmd.insert(parent, builder);
 
+        if let Some(label) = self.label.take() {
+            labels.insert(label, parent);
+        }
+
         for mut child in self.children.drain(..) {
-            let child = child.spawn_deferred(frame, cmd);
+            let child = child.spawn_deferred_inner(frame, cmd, labels);
             cmd.write(move |w: &mut Frame| {
                 w.attach::<T>(child, parent).unwrap();
             });
@@ -90,6 +218,19 @@ impl<T: Component> TreeBuilder<T> {
         parent
     }
 
+    /// Label the root so that its entity can be retrieved from the [SpawnedTree] returned by
+    /// [Self::spawn_labeled].
+    pub fn label(&mut self, label: impl Into<String>) -> &mut Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Consuming variant of [Self::label].
+    pub fn label_move(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
     /// Add a component to the root
     pub fn add(&mut self, component: impl Component) -> &mut Self {
         self.builder.add(component);
@@ -159,6 +300,31 @@ impl<T: Component> TreeBuilder<T> {
     pub fn children_mut(&mut self) -> &mut Vec<TreeBuilder<T>> {
         &mut self.children
     }
+
+    /// Visit every node in the tree depth first, starting with the root, giving mutable access
+    /// to each node's [EntityBuilder](moss_hecs::EntityBuilder).
+    ///
+    /// The closure receives the path of child indices leading to the node. The root has an empty
+    /// path, and the depth of a node is the length of its path.
+    pub fn walk_mut<F: FnMut(&[usize], &mut EntityBuilder)>(&mut self, mut f: F) -> &mut Self {
+        let mut path = Vec::new();
+        self.walk_mut_inner(&mut path, &mut f);
+        self
+    }
+
+    fn walk_mut_inner<F: FnMut(&[usize], &mut EntityBuilder)>(
+        &mut self,
+        path: &mut Vec<usize>,
+        f: &mut F,
+    ) {
+        f(path, &mut self.builder);
+
+        for (i, child) in self.children.iter_mut().enumerate() {
+            path.push(i);
+            child.walk_mut_inner(path, f);
+            path.pop();
+        }
+    }
 }
 
 impl<B: DynamicBundle, T: Component> From<B> for TreeBuilder<T> {
@@ -171,6 +337,7 @@ impl<B: DynamicBundle, T: Component> From<B> for TreeBuilder<T> {
             builder,
             marker: PhantomData,
             reserved: OnceCell::new(),
+            label: None,
         }
     }
 }
@@ -191,6 +358,32 @@ impl<T: Component> From<TreeBuilderClone<T>> for TreeBuilder<T> {
             builder,
             marker: PhantomData,
             reserved: tree.reserved,
+            label: tree.label,
         }
     }
 }
+
+/// The entities of a spawned tree, along with the entities of all labeled nodes.
+#[derive(Debug, Clone)]
+pub struct SpawnedTree {
+    pub(crate) root: Entity,
+    pub(crate) labels: HashMap<String, Entity>,
+}
+
+impl SpawnedTree {
+    /// Returns the root of the spawned tree.
+    pub fn root(&self) -> Entity {
+        self.root
+    }
+
+    /// Returns the entity spawned for the node with `label`. If several nodes share a label, the
+    /// last spawned node is returned.
+    pub fn get(&self, label: &str) -> Option<Entity> {
+        self.labels.get(label).copied()
+    }
+
+    /// Get a reference to all labeled entities.
+    pub fn labels(&self) -> &HashMap<String, Entity> {
+        &self.labels
+    }
+}
@@ -1,10 +1,77 @@
-use std::marker::PhantomData;
+use std::{
+    any::TypeId,
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    marker::PhantomData,
+};
 
-use moss_hecs::{Component, DynamicBundleClone, Entity, EntityBuilderClone, Frame};
+use moss_hecs::{
+    BuiltEntityClone, Component, DynamicBundleClone, Entity, EntityBuilderClone, Frame,
+};
 use moss_hecs_schedule::{CommandBuffer, GenericWorld};
 use once_cell::sync::OnceCell;
 
-use crate::HierarchyMut;
+use crate::{
+    hierarchy::ensure_alive, Hierarchy, HierarchyError, HierarchyMut, HierarchyResult, Role,
+    SpawnedTree,
+};
+
+/// Removes a component of a known type from an entity. Recorded for the components added to a
+/// node, so that [TreeBuilderClone::reconcile] can remove them once the node no longer carries
+/// them.
+pub(crate) type Remover = (TypeId, fn(&mut Frame, Entity));
+
+pub(crate) fn remover<C: Component>() -> Remover {
+    (TypeId::of::<C>(), remove_component::<C>)
+}
+
+fn remove_component<C: Component>(frame: &mut Frame, entity: Entity) {
+    let _ = frame.remove_one::<C>(entity);
+}
+
+/// Records `remover` in `removers` unless its component type is already recorded.
+pub(crate) fn track(removers: &mut Vec<Remover>, remover: Remover) {
+    if removers.iter().all(|(id, _)| *id != remover.0) {
+        removers.push(remover);
+    }
+}
+
+/// A bundle whose component types are known statically, which lets
+/// [TreeBuilderClone::reconcile] remove its components once a node no longer carries them, when
+/// added through [TreeBuilderClone::add_tracked_bundle]. Implemented for tuples of up to eight
+/// cloneable components.
+pub trait StaticBundle: DynamicBundleClone {
+    #[doc(hidden)]
+    fn track(removers: &mut Vec<Remover>);
+}
+
+macro_rules! impl_static_bundle {
+    ($($component:ident)*) => {
+        impl<$($component: Component + Clone),*> StaticBundle for ($($component,)*) {
+            fn track(removers: &mut Vec<Remover>) {
+                $(track(removers, remover::<$component>());)*
+            }
+        }
+    };
+}
+
+impl_static_bundle!(A);
+impl_static_bundle!(A B);
+impl_static_bundle!(A B C);
+impl_static_bundle!(A B C D);
+impl_static_bundle!(A B C D E);
+impl_static_bundle!(A B C D E F);
+impl_static_bundle!(A B C D E F G);
+impl_static_bundle!(A B C D E F G H);
+
+/// What [TreeBuilderClone::reconcile] applied to an entity last, stored on the entity to match
+/// it by label and to detect changes on the next reconcile.
+struct Reconciled<T> {
+    label: Option<String>,
+    revision: Option<u64>,
+    components: Vec<Remover>,
+    marker: PhantomData<T>,
+}
 
 /// Cloneable version of the [crate::TreeBuilder]
 pub struct TreeBuilderClone<T> {
@@ -12,6 +79,10 @@ pub struct TreeBuilderClone<T> {
     pub(crate) builder: EntityBuilderClone,
     pub(crate) marker: PhantomData<T>,
     pub(crate) reserved: OnceCell<Entity>,
+    pub(crate) label: Option<String>,
+    pub(crate) revision: Option<u64>,
+    /// The component types added to `builder`
+    pub(crate) components: Vec<Remover>,
 }
 
 impl<T: Component> TreeBuilderClone<T> {
@@ -22,6 +93,9 @@ impl<T: Component> TreeBuilderClone<T> {
             builder: EntityBuilderClone::new(),
             marker: PhantomData,
             reserved: OnceCell::new(),
+            label: None,
+            revision: None,
+            components: Vec::new(),
         }
     }
 
@@ -30,29 +104,73 @@ impl<T: Component> TreeBuilderClone<T> {
         *self.reserved.get_or_init(|| frame.reserve())
     }
 
-    /// Spawn the whole tree into the world
+    /// Spawn the whole tree into the world, returning the root. Use [Self::spawn_labeled] to
+    /// retrieve the entities of labeled nodes as well.
     pub fn spawn(self, frame: &mut Frame) -> Entity {
+        self.spawn_labeled(frame).root()
+    }
+
+    /// Spawn the whole tree into the world, returning the root along with the entities of all
+    /// labeled nodes.
+    pub fn spawn_labeled(self, frame: &mut Frame) -> SpawnedTree {
+        let mut labels = HashMap::new();
+        let root = self.spawn_inner(frame, &mut labels);
+        SpawnedTree { root, labels }
+    }
+
+    fn spawn_inner(self, frame: &mut Frame, labels: &mut HashMap<String, Entity>) -> Entity {
         let parent = self.reserve(frame);
         let builder = self.builder.build();
         frame.insert(parent, &builder).unwrap();
 
+        if let Some(label) = self.label {
+            labels.insert(label, parent);
+        }
+
         for child in self.children {
-            let child = child.spawn(frame);
+            let child = child.spawn_inner(frame, labels);
             frame.attach::<T>(child, parent).unwrap();
         }
 
         parent
     }
 
-    /// Spawn the whole tree into a commandbuffer.
+    /// Spawn the whole tree into a commandbuffer, returning the root. Use
+    /// [Self::spawn_labeled_deferred] to retrieve the entities of labeled nodes as well.
     /// The world is required for reserving entities.
     pub fn spawn_deferred(self, frame: &impl GenericWorld, cmd: &mut CommandBuffer) -> Entity {
+        self.spawn_labeled_deferred(frame, cmd).root()
+    }
+
+    /// Spawn the whole tree into a commandbuffer, returning the root along with the entities
+    /// of all labeled nodes.
+    /// The world is required for reserving entities.
+    pub fn spawn_labeled_deferred(
+        self,
+        frame: &impl GenericWorld,
+        cmd: &mut CommandBuffer,
+    ) -> SpawnedTree {
+        let mut labels = HashMap::new();
+        let root = self.spawn_deferred_inner(frame, cmd, &mut labels);
+        SpawnedTree { root, labels }
+    }
+
+    fn spawn_deferred_inner(
+        self,
+        frame: &impl GenericWorld,
+        cmd: &mut CommandBuffer,
+        labels: &mut HashMap<String, Entity>,
+    ) -> Entity {
         let parent = self.reserve(frame);
         let builder = self.builder.build();
         cmd.insert(parent, &builder);
 
+        if let Some(label) = self.label {
+            labels.insert(label, parent);
+        }
+
         for child in self.children {
-            let child = child.spawn_deferred(frame, cmd);
+            let child = child.spawn_deferred_inner(frame, cmd, labels);
             cmd.write(move |w: &mut Frame| {
                 w.attach::<T>(child, parent).unwrap();
             });
@@ -60,19 +178,227 @@ impl<T: Component> TreeBuilderClone<T> {
         parent
     }
 
+    /// Spawn `count` copies of the tree into the world, returning the root of each copy.
+    ///
+    /// Every node is built only once and all entities are reserved up front, which is
+    /// considerably cheaper than cloning and spawning the builder once per copy.
+    pub fn spawn_n(&self, frame: &mut Frame, count: usize) -> Vec<Entity> {
+        let tree = self.build_tree();
+        let mut entities = reserve_batch(frame, tree.len(), count).into_iter();
+
+        (0..count)
+            .map(|_| tree.spawn::<T>(frame, &mut entities))
+            .collect()
+    }
+
+    /// Spawn `count` copies of the tree into a commandbuffer, returning the root of each copy.
+    /// The world is required for reserving entities, which are reserved one at a time like in
+    /// [Self::spawn_deferred], so that a subworld can spawn copies as well.
+    pub fn spawn_n_deferred(
+        &self,
+        frame: &impl GenericWorld,
+        cmd: &mut CommandBuffer,
+        count: usize,
+    ) -> Vec<Entity> {
+        let tree = self.build_tree();
+        let mut entities = std::iter::repeat_with(|| frame.reserve());
+
+        (0..count)
+            .map(|_| tree.spawn_deferred::<T>(cmd, &mut entities))
+            .collect()
+    }
+
+    /// Update the existing subtree at `root` to match this tree, rather than spawning a new one,
+    /// touching only what changed.
+    ///
+    /// The root of the tree is applied to `root`. Every other node is matched with the entity of
+    /// the subtree which was reconciled with the same [label](Self::label) last time, wherever it
+    /// is in the subtree, and otherwise with the unlabeled entity at the same position among the
+    /// children of its parent. Matched entities are moved to the parent and index of their node,
+    /// nodes without a match are spawned, and entities without a node are despawned along with
+    /// their remaining descendants.
+    ///
+    /// Nodes with the same [revision](Self::revision) as the one applied to their entity last
+    /// time are skipped. Otherwise the components of the node are inserted, overwriting previous
+    /// values, and the components of the previously applied node which the node no longer
+    /// carries are removed. Only components added through [Self::add], [Self::add_all] and
+    /// [Self::add_tracked_bundle] are removed, not those added through [Self::add_bundle], `From`
+    /// or the [EntityBuilderClone] directly, and components which were never part of a node are
+    /// left as is.
+    ///
+    /// Fails with the error of the enabled [HierarchyPolicy](crate::HierarchyPolicy) of `T` if
+    /// the children of a node violate it, leaving the nodes before it reconciled.
+    pub fn reconcile(&self, frame: &mut Frame, root: Entity) -> HierarchyResult<()> {
+        ensure_alive(frame, root, Role::Parent)?;
+
+        let existing = frame.descendants_depth_first::<T>(root).collect::<Vec<_>>();
+        let mut labeled = existing
+            .iter()
+            .filter_map(|&entity| {
+                let record = frame.get::<&Reconciled<T>>(entity).ok()?;
+                Some((record.label.clone()?, entity))
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut matched = HashSet::new();
+        matched.insert(root);
+
+        self.apply(frame, root)?;
+        self.reconcile_children(frame, root, &mut labeled, &mut matched)?;
+
+        // Unmatched entities are roots or below other unmatched entities by now
+        for entity in existing {
+            if !matched.contains(&entity) && frame.contains(entity) {
+                frame.despawn_all::<T>(entity)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Matches the children of this node with entities, makes them the children of `entity` and
+    /// reconciles them in turn.
+    fn reconcile_children(
+        &self,
+        frame: &mut Frame,
+        entity: Entity,
+        labeled: &mut HashMap<String, Entity>,
+        matched: &mut HashSet<Entity>,
+    ) -> HierarchyResult<()> {
+        let current = frame.children_vec::<T>(entity);
+
+        let mut children = Vec::with_capacity(self.children.len());
+        for (i, node) in self.children.iter().enumerate() {
+            let by_label = node.label.as_ref().and_then(|label| labeled.remove(label));
+            let by_position = || {
+                current.get(i).copied().filter(|&child| {
+                    !matched.contains(&child)
+                        && frame
+                            .get::<&Reconciled<T>>(child)
+                            .map_or(true, |record| record.label.is_none())
+                })
+            };
+
+            let child = match by_label.or_else(by_position) {
+                Some(child) => child,
+                None => frame.spawn(()),
+            };
+
+            node.apply(frame, child)?;
+            matched.insert(child);
+            children.push(child);
+        }
+
+        // Matched entities are never ancestors of `entity`, since the tree is matched from the
+        // root down
+        if children != current {
+            frame.set_children::<T>(entity, &children)?;
+        }
+
+        for (node, &child) in self.children.iter().zip(&children) {
+            node.reconcile_children(frame, child, labeled, matched)?;
+        }
+
+        Ok(())
+    }
+
+    /// Applies the components of this node to `entity`, unless they were applied last time.
+    fn apply(&self, frame: &mut Frame, entity: Entity) -> HierarchyResult<()> {
+        let unchanged = frame
+            .get::<&Reconciled<T>>(entity)
+            .map(|record| {
+                self.revision.is_some()
+                    && record.revision == self.revision
+                    && record.label == self.label
+            })
+            .unwrap_or_default();
+
+        if unchanged {
+            return Ok(());
+        }
+
+        let stale = frame
+            .remove_one::<Reconciled<T>>(entity)
+            .map(|record| record.components)
+            .unwrap_or_default();
+
+        for (id, remove) in stale {
+            if self
+                .components
+                .iter()
+                .all(|(component, _)| *component != id)
+            {
+                remove(frame, entity);
+            }
+        }
+
+        let record = Reconciled::<T> {
+            label: self.label.clone(),
+            revision: self.revision,
+            components: self.components.clone(),
+            marker: PhantomData,
+        };
+
+        frame
+            .insert(entity, &self.builder.build())
+            .and_then(|_| frame.insert_one(entity, record))
+            .map_err(|_| HierarchyError::NoSuchEntity {
+                entity,
+                which: Role::Child,
+            })
+    }
+
+    fn build_tree(&self) -> BuiltTree {
+        BuiltTree {
+            builder: self.builder.build(),
+            children: self
+                .children
+                .iter()
+                .map(|child| child.build_tree())
+                .collect(),
+        }
+    }
+
+    /// Label the root so that its entity can be retrieved from the [SpawnedTree] returned by
+    /// [Self::spawn_labeled].
+    pub fn label(&mut self, label: impl Into<String>) -> &mut Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Consuming variant of [Self::label].
+    pub fn label_move(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Set the revision of the root, which [Self::reconcile] compares with the revision it
+    /// applied to the entity of the root last time to skip unchanged nodes. Change it whenever
+    /// the components of the root change. Nodes without a revision are always applied.
+    pub fn revision(&mut self, revision: u64) -> &mut Self {
+        self.revision = Some(revision);
+        self
+    }
+
+    /// Consuming variant of [Self::revision].
+    pub fn revision_move(mut self, revision: u64) -> Self {
+        self.revision = Some(revision);
+        self
+    }
+
     /// Add a component to the root
-    pub fn add(&mut self, component: impl Component + Clone) -> &mut Self {
+    pub fn add<C: Component + Clone>(&mut self, component: C) -> &mut Self {
+        track(&mut self.components, remover::<C>());
         self.builder.add(component);
         self
     }
 
     // Adds a component to all nodes
-    pub fn add_all(&mut self, component: impl Component + Clone) -> &mut Self {
+    pub fn add_all<C: Component + Clone>(&mut self, component: C) -> &mut Self {
         for child in &mut self.children {
             child.add_all(component.clone());
         }
-        self.builder.add(component);
-        self
+        self.add(component)
     }
 
     /// Add a bundle to the root
@@ -81,6 +407,20 @@ impl<T: Component> TreeBuilderClone<T> {
         self
     }
 
+    /// Same as [Self::add_bundle], but records the component types of the bundle so that
+    /// [Self::reconcile] removes them once the node no longer carries them.
+    pub fn add_tracked_bundle<B: StaticBundle>(&mut self, bundle: B) -> &mut Self {
+        B::track(&mut self.components);
+        self.builder.add_bundle(bundle);
+        self
+    }
+
+    /// Same as [Self::add_tracked_bundle], but takes and returns the builder.
+    pub fn add_tracked_bundle_move<B: StaticBundle>(mut self, bundle: B) -> Self {
+        self.add_tracked_bundle(bundle);
+        self
+    }
+
     /// Atttach a new subtree
     pub fn attach_tree(&mut self, child: Self) -> &mut Self {
         self.children.push(child);
@@ -129,6 +469,98 @@ impl<T: Component> TreeBuilderClone<T> {
     pub fn children_mut(&mut self) -> &mut Vec<TreeBuilderClone<T>> {
         &mut self.children
     }
+
+    /// Visit every node in the tree depth first, starting with the root, giving mutable access
+    /// to each node's [EntityBuilderClone](moss_hecs::EntityBuilderClone).
+    ///
+    /// The closure receives the path of child indices leading to the node. The root has an empty
+    /// path, and the depth of a node is the length of its path.
+    pub fn walk_mut<F: FnMut(&[usize], &mut EntityBuilderClone)>(&mut self, mut f: F) -> &mut Self {
+        let mut path = Vec::new();
+        self.walk_mut_inner(&mut path, &mut f);
+        self
+    }
+
+    fn walk_mut_inner<F: FnMut(&[usize], &mut EntityBuilderClone)>(
+        &mut self,
+        path: &mut Vec<usize>,
+        f: &mut F,
+    ) {
+        f(path, &mut self.builder);
+
+        for (i, child) in self.children.iter_mut().enumerate() {
+            path.push(i);
+            child.walk_mut_inner(path, f);
+            path.pop();
+        }
+    }
+}
+
+/// A tree where every node has already been built, used to spawn the same tree several times.
+struct BuiltTree {
+    builder: BuiltEntityClone,
+    children: Vec<BuiltTree>,
+}
+
+impl BuiltTree {
+    /// Returns the number of nodes in the tree
+    fn len(&self) -> usize {
+        1 + self.children.iter().map(BuiltTree::len).sum::<usize>()
+    }
+
+    fn spawn<T: Component>(
+        &self,
+        frame: &mut Frame,
+        entities: &mut impl Iterator<Item = Entity>,
+    ) -> Entity {
+        let parent = entities
+            .next()
+            .expect("Not enough entities reserved for tree");
+        frame.insert(parent, &self.builder).unwrap();
+
+        for child in &self.children {
+            let child = child.spawn::<T>(frame, entities);
+            frame.attach::<T>(child, parent).unwrap();
+        }
+
+        parent
+    }
+
+    fn spawn_deferred<T: Component>(
+        &self,
+        cmd: &mut CommandBuffer,
+        entities: &mut impl Iterator<Item = Entity>,
+    ) -> Entity {
+        let parent = entities
+            .next()
+            .expect("Not enough entities reserved for tree");
+        cmd.insert(parent, &self.builder);
+
+        for child in &self.children {
+            let child = child.spawn_deferred::<T>(cmd, entities);
+            cmd.write(move |w: &mut Frame| {
+                w.attach::<T>(child, parent).unwrap();
+            });
+        }
+
+        parent
+    }
+}
+
+/// Reserves the entities of `copies` copies of a tree of `len` nodes at once. A single
+/// reservation is limited to `u32::MAX` entities, so larger batches are reserved in chunks.
+fn reserve_batch(frame: &Frame, len: usize, copies: usize) -> Vec<Entity> {
+    let total = len
+        .checked_mul(copies)
+        .expect("Number of entities to reserve overflows usize");
+
+    let mut entities = Vec::with_capacity(total);
+    while entities.len() < total {
+        let chunk = u32::try_from(total - entities.len()).unwrap_or(u32::MAX);
+        entities.extend(frame.reserve_entities(chunk));
+    }
+
+    entities
 }
 
 impl<T> Clone for TreeBuilderClone<T> {
@@ -138,20 +570,17 @@ impl<T> Clone for TreeBuilderClone<T> {
             builder: self.builder.clone(),
             marker: PhantomData,
             reserved: OnceCell::new(),
+            label: self.label.clone(),
+            revision: self.revision,
+            components: self.components.clone(),
         }
     }
 }
 
 impl<B: DynamicBundleClone, T: Component> From<B> for TreeBuilderClone<T> {
     fn from(bundle: B) -> Self {
-        let mut builder = EntityBuilderClone::new();
-        builder.add_bundle(bundle);
-
-        Self {
-            children: Vec::new(),
-            builder,
-            marker: PhantomData,
-            reserved: OnceCell::new(),
-        }
+        let mut tree = Self::new();
+        tree.add_bundle(bundle);
+        tree
     }
 }
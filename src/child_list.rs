@@ -0,0 +1,205 @@
+use std::marker::PhantomData;
+
+use moss_hecs::{Component, Entity, Frame};
+
+use crate::{hierarchy::Position, Child, Parent};
+
+/// Declares how hierarchy `Self` stores the children of its parents, applied with
+/// [HierarchyMut::enable_child_storage](crate::HierarchyMut::enable_child_storage). The storage of
+/// a hierarchy is declared by its marker type, and applied to each frame separately.
+///
+/// ```rust
+/// use moss_hecs_hierarchy::*;
+///
+/// // A list view whose rows are iterated far more often than they change
+/// struct Rows;
+///
+/// impl ChildStorage for Rows {
+///     const CHILD_LISTS: bool = true;
+/// }
+///
+/// let mut frame = moss_hecs::Frame::default();
+/// frame.enable_child_storage::<Rows>().unwrap();
+///
+/// let list = frame.spawn(("List",));
+/// let row = frame.attach_new::<Rows, _>(list, ("Row",)).unwrap();
+/// assert_eq!(frame.get::<&ChildList<Rows>>(list).unwrap().as_slice(), [row]);
+/// ```
+pub trait ChildStorage: Component {
+    /// Whether every parent carries a [ChildList] alongside its [Parent] component. Disabled by
+    /// default, leaving the sibling ring as the only storage.
+    const CHILD_LISTS: bool = false;
+}
+
+/// The children of a parent in hierarchy `T` as a contiguous list, in sibling order. Once enabled
+/// through a [ChildStorage] of `T`, every parent of `T` carries a `ChildList<T>` alongside its
+/// [Parent] component, which the structural operations of [HierarchyMut](crate::HierarchyMut)
+/// keep in sync with the sibling ring, and which [Hierarchy::children](crate::Hierarchy::children)
+/// iterates instead of following the ring.
+///
+/// Scanning the list is much friendlier to the cache than following the sibling links, at the
+/// cost of shifting the list of a parent whenever a child is inserted or removed before its end.
+/// It pays off for parents with many children which are iterated more often than they are
+/// modified.
+pub struct ChildList<T> {
+    children: Vec<Entity>,
+    marker: PhantomData<T>,
+}
+
+impl<T: Component> ChildList<T> {
+    fn new(children: Vec<Entity>) -> Self {
+        Self {
+            children,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the children in sibling order.
+    pub fn as_slice(&self) -> &[Entity] {
+        &self.children
+    }
+
+    /// Returns the number of children.
+    pub fn len(&self) -> usize {
+        self.children.len()
+    }
+
+    /// Returns true if there are no children.
+    pub fn is_empty(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    /// Returns an iterator over the children in sibling order.
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.children.iter().copied()
+    }
+
+    pub(crate) fn remap_entities<F: FnMut(Entity) -> Entity>(&mut self, mut map: F) {
+        for child in &mut self.children {
+            *child = map(*child);
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for ChildList<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ChildList").field(&self.children).finish()
+    }
+}
+
+/// Marks child lists of hierarchy `T` as enabled in a frame, stored on a singleton entity.
+pub(crate) struct ChildListsEnabled<T>(PhantomData<T>);
+
+/// Enables child lists of hierarchy `T` in `frame`, building a list for every current parent.
+/// Does nothing if they are already enabled.
+pub(crate) fn enable_child_lists<T: Component>(frame: &mut Frame) {
+    if child_lists_enabled::<T>(frame) {
+        return;
+    }
+
+    frame.spawn((ChildListsEnabled::<T>(PhantomData),));
+
+    let parents = frame
+        .query::<&Parent<T>>()
+        .iter()
+        .map(|(parent, _)| parent)
+        .collect::<Vec<_>>();
+    for parent in parents {
+        if let Some(children) = ring_children::<T>(frame, parent) {
+            let _ = frame.insert_one(parent, ChildList::<T>::new(children));
+        }
+    }
+}
+
+/// Returns true if child lists of hierarchy `T` are enabled in `frame`.
+fn child_lists_enabled<T: Component>(frame: &Frame) -> bool {
+    frame
+        .query::<&ChildListsEnabled<T>>()
+        .iter()
+        .next()
+        .is_some()
+}
+
+/// Inserts `child` into the [ChildList] of `parent` at `position`, after it was linked there.
+/// Only the first child of a parent looks up whether child lists of `T` are enabled, later ones
+/// update the existing list in place.
+pub(crate) fn list_linked<T: Component>(
+    frame: &mut Frame,
+    child: Entity,
+    parent: Entity,
+    position: Position,
+) {
+    if let Ok(mut list) = frame.get::<&mut ChildList<T>>(parent) {
+        let children = &mut list.children;
+        match position {
+            Position::Front => children.insert(0, child),
+            Position::Back => children.push(child),
+            Position::After(sibling) => match children.iter().position(|&e| e == sibling) {
+                Some(index) => children.insert(index + 1, child),
+                None => children.push(child),
+            },
+        }
+        return;
+    }
+
+    if child_lists_enabled::<T>(frame) {
+        let _ = frame.insert_one(parent, ChildList::<T>::new(vec![child]));
+    }
+}
+
+/// Removes `child` from the [ChildList] of `parent` after it was unlinked, removing the list
+/// along with the last child.
+pub(crate) fn list_unlinked<T: Component>(frame: &mut Frame, child: Entity, parent: Entity) {
+    let empty = match frame.get::<&mut ChildList<T>>(parent) {
+        Ok(mut list) => {
+            list.children.retain(|&e| e != child);
+            list.children.is_empty()
+        }
+        Err(_) => return,
+    };
+
+    if empty {
+        let _ = frame.remove_one::<ChildList<T>>(parent);
+    }
+}
+
+/// Replaces the [ChildList] of `parent` with `children` after its ring was relinked, removing it
+/// if `children` is empty.
+pub(crate) fn list_relinked<T: Component>(frame: &mut Frame, parent: Entity, children: &[Entity]) {
+    if children.is_empty() {
+        let _ = frame.remove_one::<ChildList<T>>(parent);
+        return;
+    }
+
+    if let Ok(mut list) = frame.get::<&mut ChildList<T>>(parent) {
+        list.children.clear();
+        list.children.extend_from_slice(children);
+        return;
+    }
+
+    if child_lists_enabled::<T>(frame) {
+        let _ = frame.insert_one(parent, ChildList::<T>::new(children.to_vec()));
+    }
+}
+
+/// Follows the sibling ring of `parent`, or returns `None` if it has no `Parent` component.
+fn ring_children<T: Component>(frame: &Frame, parent: Entity) -> Option<Vec<Entity>> {
+    let (num_children, last_child) = {
+        let data = frame.get::<&Parent<T>>(parent).ok()?;
+        (data.num_children, data.last_child)
+    };
+
+    let mut children = Vec::with_capacity(num_children);
+    let mut cur = frame.get::<&Child<T>>(last_child).ok()?.next;
+    while children.len() < num_children {
+        match frame.get::<&Child<T>>(cur) {
+            Ok(data) => {
+                children.push(cur);
+                cur = data.next;
+            }
+            Err(_) => break,
+        }
+    }
+
+    Some(children)
+}
@@ -21,27 +21,40 @@ impl<T: 'static + Send + Sync> Parent<T> {
         }
     }
 
-    /// Return the parent's num children.
+    /// Return the parent's num children. A parent always has at least one child, since the
+    /// `Parent` component is removed along with the last child.
     pub fn num_children(&self) -> usize {
         self.num_children
     }
 
-    /// Query the parent's first child.
+    /// Query the parent's first child. Only the last child is stored in the parent, and the first
+    /// child is the next sibling of the last child, which is why the `Child` component of the last
+    /// child has to be looked up.
     pub fn first_child<W: GenericWorld>(&self, frame: &W) -> Result<Entity> {
         Ok(frame.try_get::<Child<T>>(self.last_child)?.next)
     }
 
-    /// Query the parent's first child.
+    /// Query the parent's first child through an existing view of the `Child` components, which
+    /// avoids borrowing them again for every parent when iterating a query of parents. See
+    /// [Self::first_child].
     pub fn view_first_child(&self, view: &View<&Child<T>>) -> Result<Entity> {
         Ok(view
             .get(self.last_child)
             .ok_or_else(|| moss_hecs_schedule::Error::NoSuchEntity(self.last_child))?
             .next)
     }
+
     /// Return the parent's last child.
     pub fn last_child(&self) -> Entity {
         self.last_child
     }
+
+    /// Replace the entity reference of the parent using `map`. This is the hook for remapping a
+    /// parent which has been deserialized or copied into a frame where its children have other
+    /// ids.
+    pub fn remap_entities<F: FnMut(Entity) -> Entity>(&mut self, mut map: F) {
+        self.last_child = map(self.last_child);
+    }
 }
 
 impl<T> std::fmt::Debug for Parent<T> {
@@ -72,6 +85,33 @@ impl<T> Child<T> {
             marker: PhantomData,
         }
     }
+
+    /// Return the child's parent.
+    pub fn parent(&self) -> Entity {
+        self.parent
+    }
+
+    /// Return the child's next sibling. Since the children form a circular list, the next sibling
+    /// of the last child is the first child, and an only child is its own next sibling.
+    pub fn next_sibling(&self) -> Entity {
+        self.next
+    }
+
+    /// Return the child's previous sibling. Since the children form a circular list, the previous
+    /// sibling of the first child is the last child, and an only child is its own previous
+    /// sibling.
+    pub fn prev_sibling(&self) -> Entity {
+        self.prev
+    }
+
+    /// Replace the entity references of the child using `map`. This is the hook for remapping a
+    /// child which has been deserialized or copied into a frame where its parent and siblings
+    /// have other ids.
+    pub fn remap_entities<F: FnMut(Entity) -> Entity>(&mut self, mut map: F) {
+        self.parent = map(self.parent);
+        self.next = map(self.next);
+        self.prev = map(self.prev);
+    }
 }
 
 impl<T> std::fmt::Debug for Child<T> {
@@ -83,3 +123,34 @@ impl<T> std::fmt::Debug for Child<T> {
             .finish()
     }
 }
+
+/// Position of a child among its siblings in hierarchy tree `T`, where the first child has index
+/// 0. Kept up to date by attaching, detaching and reordering when the `child-index` feature is
+/// enabled.
+#[cfg(feature = "child-index")]
+pub struct ChildIndex<T> {
+    index: u32,
+    marker: PhantomData<T>,
+}
+
+#[cfg(feature = "child-index")]
+impl<T> ChildIndex<T> {
+    pub(crate) fn new(index: u32) -> Self {
+        Self {
+            index,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the position of the child among its siblings.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+}
+
+#[cfg(feature = "child-index")]
+impl<T> std::fmt::Debug for ChildIndex<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ChildIndex").field(&self.index).finish()
+    }
+}
@@ -0,0 +1,101 @@
+use moss_hecs::{Component, Entity};
+use moss_hecs_schedule::GenericWorld;
+
+use crate::{Child, Parent};
+
+/// Reusable buckets for grouping a tree by depth, so that repeated grouping of large trees, such as
+/// once per frame, does not allocate. See
+/// [Hierarchy::entities_by_depth_in](crate::Hierarchy::entities_by_depth_in).
+#[derive(Debug, Default, Clone)]
+pub struct DepthBuckets {
+    layers: Vec<Vec<Entity>>,
+    len: usize,
+}
+
+impl DepthBuckets {
+    /// Creates empty buckets.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the buckets filled by the last grouping, starting with the root at depth 0.
+    pub fn as_slice(&self) -> &[Vec<Entity>] {
+        &self.layers[..self.len]
+    }
+
+    /// Returns the groups filled by the last grouping, dropping the buffers of the unused ones.
+    pub(crate) fn into_vec(mut self) -> Vec<Vec<Entity>> {
+        self.layers.truncate(self.len);
+        self.layers
+    }
+
+    /// Group the tree below `root` into the buckets.
+    pub(crate) fn fill<T: Component, W: GenericWorld>(&mut self, frame: &W, root: Entity) {
+        self.len = fill_layers::<T, _>(frame, root, &mut self.layers);
+    }
+}
+
+/// Fills `layers` with the tree below `root`, one depth level per layer starting with `root`
+/// itself, in breadth first order. Walks the sibling rings through a single borrow of the
+/// components instead of borrowing them once per entity, and reuses the allocations of the
+/// layers already in `layers`. Returns the number of filled layers; the layers after them are
+/// left empty.
+pub(crate) fn fill_layers<T: Component, W: GenericWorld>(
+    frame: &W,
+    root: Entity,
+    layers: &mut Vec<Vec<Entity>>,
+) -> usize {
+    layers.iter_mut().for_each(Vec::clear);
+    if layers.is_empty() {
+        layers.push(Vec::new());
+    }
+    layers[0].push(root);
+
+    let (mut parents, mut children) = match (
+        frame.try_query::<&Parent<T>>(),
+        frame.try_query::<&Child<T>>(),
+    ) {
+        (Ok(parents), Ok(children)) => (parents, children),
+        _ => return 1,
+    };
+
+    let parents = parents.view();
+    let children = children.view();
+
+    let mut depth = 0;
+    loop {
+        if layers.len() == depth + 1 {
+            layers.push(Vec::new());
+        }
+
+        let (done, rest) = layers.split_at_mut(depth + 1);
+        let next = &mut rest[0];
+        for &entity in &done[depth] {
+            let (num_children, last_child) = match parents.get(entity) {
+                Some(parent) => (parent.num_children, parent.last_child),
+                None => continue,
+            };
+
+            let mut cur = match children.get(last_child) {
+                Some(last) => last.next,
+                None => continue,
+            };
+
+            for _ in 0..num_children {
+                next.push(cur);
+                cur = match children.get(cur) {
+                    Some(child) => child.next,
+                    None => break,
+                };
+            }
+        }
+
+        if next.is_empty() {
+            break;
+        }
+
+        depth += 1;
+    }
+
+    depth + 1
+}
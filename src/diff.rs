@@ -0,0 +1,159 @@
+use std::{collections::HashMap, hash::Hash};
+
+use moss_hecs::{Component, Entity};
+use moss_hecs_schedule::GenericWorld;
+
+use crate::Hierarchy;
+
+/// A change turning one tree into another, as reported by [diff]. Entities are identified by
+/// their key component `K`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TreeEdit<K> {
+    /// `key` only exists in the new tree.
+    Added {
+        /// The key of the added entity
+        key: K,
+        /// The key of its parent, or `None` for the root
+        parent: Option<K>,
+        /// Its index among the keyed children of its parent
+        index: usize,
+    },
+    /// `key` only exists in the old tree.
+    Removed {
+        /// The key of the removed entity
+        key: K,
+    },
+    /// `key` exists in both trees, but has another parent or has been reordered relative to the
+    /// siblings it has in both trees.
+    Moved {
+        /// The key of the moved entity
+        key: K,
+        /// The key of its new parent, or `None` for the root
+        parent: Option<K>,
+        /// Its new index among the keyed children of its parent
+        index: usize,
+    },
+}
+
+struct KeyedTree<K> {
+    /// Keys in depth first order, starting at the root
+    order: Vec<K>,
+    parents: HashMap<K, Option<K>>,
+    children: HashMap<Option<K>, Vec<K>>,
+}
+
+impl<K: Component + Clone + Eq + Hash> KeyedTree<K> {
+    fn new<T: Component, W: GenericWorld + Hierarchy>(frame: &W, root: Entity) -> Self {
+        let mut tree = Self {
+            order: Vec::new(),
+            parents: HashMap::new(),
+            children: HashMap::new(),
+        };
+
+        let mut stack = vec![(root, None)];
+        while let Some((entity, parent)) = stack.pop() {
+            let key = match frame.try_get::<K>(entity) {
+                Ok(key) => (*key).clone(),
+                Err(_) => continue,
+            };
+
+            if tree.parents.contains_key(&key) {
+                continue;
+            }
+
+            tree.order.push(key.clone());
+            tree.parents.insert(key.clone(), parent.clone());
+            tree.children
+                .entry(parent)
+                .or_insert_with(Vec::new)
+                .push(key.clone());
+
+            let children = frame.children_vec::<T>(entity);
+            stack.extend(
+                children
+                    .into_iter()
+                    .rev()
+                    .map(|child| (child, Some(key.clone()))),
+            );
+        }
+
+        tree
+    }
+
+    fn index(&self, key: &K) -> usize {
+        self.siblings(key)
+            .iter()
+            .position(|sibling| sibling == key)
+            .unwrap_or_default()
+    }
+
+    fn siblings(&self, key: &K) -> &[K] {
+        self.parents
+            .get(key)
+            .and_then(|parent| self.children.get(parent))
+            .map(|siblings| &siblings[..])
+            .unwrap_or_default()
+    }
+}
+
+/// Compares the tree below `old` with the tree below `new` in hierarchy `T`, matching entities by
+/// their key component `K`, and returns the edits turning the old tree into the new one. Removals
+/// come first in depth first order of the old tree, followed by additions and moves in depth
+/// first order of the new tree.
+///
+/// Entities without `K` are skipped along with their descendants, and of several entities with
+/// the same key only the first in depth first order is considered.
+pub fn diff<T, K, W>(frame: &W, old: Entity, new: Entity) -> Vec<TreeEdit<K>>
+where
+    T: Component,
+    K: Component + Clone + Eq + Hash,
+    W: GenericWorld + Hierarchy,
+{
+    let old = KeyedTree::<K>::new::<T, _>(frame, old);
+    let new = KeyedTree::<K>::new::<T, _>(frame, new);
+
+    let mut edits = old
+        .order
+        .iter()
+        .filter(|key| !new.parents.contains_key(key))
+        .map(|key| TreeEdit::Removed { key: key.clone() })
+        .collect::<Vec<_>>();
+
+    for key in &new.order {
+        let parent = new.parents[key].clone();
+        let index = new.index(key);
+
+        let old_parent = match old.parents.get(key) {
+            Some(old_parent) => old_parent,
+            None => {
+                edits.push(TreeEdit::Added {
+                    key: key.clone(),
+                    parent,
+                    index,
+                });
+                continue;
+            }
+        };
+
+        // Compare the order among the siblings which stayed under the same parent
+        let stable = |tree: &KeyedTree<K>| {
+            tree.siblings(key)
+                .iter()
+                .filter(|sibling| {
+                    old.parents.get(*sibling) == Some(&parent)
+                        && new.parents.get(*sibling) == Some(&parent)
+                })
+                .position(|sibling| sibling == key)
+        };
+
+        if *old_parent != parent || stable(&old) != stable(&new) {
+            edits.push(TreeEdit::Moved {
+                key: key.clone(),
+                parent,
+                index,
+            });
+        }
+    }
+
+    edits
+}
@@ -0,0 +1,212 @@
+use moss_hecs::{Entity, Frame};
+use moss_hecs_schedule::error::Result;
+
+use crate::{Hierarchy, HierarchyError, HierarchyMut, HierarchyResult};
+
+/// The number of runtime hierarchies which can be in use at the same time. Each is backed by its
+/// own [DynMarker] type, instantiated at compile time, so the limit can not grow at runtime.
+pub const MAX_DYN_HIERARCHIES: usize = 32;
+
+/// Marker type backing the runtime hierarchy with index `N`. Usually used through a
+/// [HierarchyId], but can be named directly to use the generic API on a runtime hierarchy, e.g.
+/// `frame.descendants_depth_first::<DynMarker<0>>(root)`.
+pub struct DynMarker<const N: usize>;
+
+/// Identifies a hierarchy chosen at runtime rather than through a marker type, such as one per
+/// loaded document. Every id is backed by one of [MAX_DYN_HIERARCHIES] [DynMarker] types, and
+/// offers the core operations of [Hierarchy] and [HierarchyMut] dispatched to it. Operations
+/// beyond these are available through the generic API on the [DynMarker] of the id.
+///
+/// At most [MAX_DYN_HIERARCHIES] ids exist, and thereby at most as many runtime hierarchies can
+/// be in use at the same time. Ids can be allocated and recycled using [HierarchyIds], which fails
+/// with [HierarchyError::IdsExhausted] once every id is in use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HierarchyId(usize);
+
+impl HierarchyId {
+    /// Returns the id with `index`, or `None` if it is not less than [MAX_DYN_HIERARCHIES].
+    pub fn new(index: usize) -> Option<Self> {
+        if index < MAX_DYN_HIERARCHIES {
+            Some(Self(index))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the index of the id, which is the `N` of its [DynMarker].
+    pub fn index(self) -> usize {
+        self.0
+    }
+
+    fn vtable(self) -> &'static VTable {
+        &VTABLES[self.0]
+    }
+
+    /// See [HierarchyMut::attach].
+    pub fn attach(
+        self,
+        frame: &mut Frame,
+        child: Entity,
+        parent: Entity,
+    ) -> HierarchyResult<Entity> {
+        (self.vtable().attach)(frame, child, parent)
+    }
+
+    /// See [HierarchyMut::detach].
+    pub fn detach(self, frame: &mut Frame, child: Entity) -> HierarchyResult<Entity> {
+        (self.vtable().detach)(frame, child)
+    }
+
+    /// See [HierarchyMut::despawn_all].
+    pub fn despawn_all(self, frame: &mut Frame, parent: Entity) -> HierarchyResult<usize> {
+        (self.vtable().despawn_all)(frame, parent)
+    }
+
+    /// See [Hierarchy::parent].
+    pub fn parent(self, frame: &Frame, child: Entity) -> Result<Entity> {
+        (self.vtable().parent)(frame, child)
+    }
+
+    /// Returns the immediate children of `parent` in order. See [Hierarchy::children_vec].
+    pub fn children(self, frame: &Frame, parent: Entity) -> Vec<Entity> {
+        (self.vtable().children)(frame, parent)
+    }
+
+    /// Returns the descendants of `root` in depth first order. See
+    /// [Hierarchy::descendants_depth_first].
+    pub fn descendants_depth_first(self, frame: &Frame, root: Entity) -> Vec<Entity> {
+        (self.vtable().descendants)(frame, root)
+    }
+
+    /// Returns the descendants of `root` in breadth first order. See
+    /// [Hierarchy::descendants_breadth_first].
+    pub fn descendants_breadth_first(self, frame: &Frame, root: Entity) -> Vec<Entity> {
+        (self.vtable().descendants_breadth_first)(frame, root)
+    }
+
+    /// Returns the ancestors of `child`, starting with its parent. See [Hierarchy::ancestors].
+    pub fn ancestors(self, frame: &Frame, child: Entity) -> Vec<Entity> {
+        (self.vtable().ancestors)(frame, child)
+    }
+
+    /// Returns the roots of the hierarchy in no particular order. See [Hierarchy::roots].
+    pub fn roots(self, frame: &Frame) -> Result<Vec<Entity>> {
+        (self.vtable().roots)(frame)
+    }
+}
+
+/// Hands out unused [HierarchyId]s and takes them back once their hierarchy is no longer needed.
+#[derive(Debug, Default, Clone)]
+pub struct HierarchyIds {
+    used: u64,
+}
+
+impl HierarchyIds {
+    /// Creates an allocator with every id available.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the lowest unused id, or fails with [HierarchyError::IdsExhausted] if all
+    /// [MAX_DYN_HIERARCHIES] are in use.
+    pub fn allocate(&mut self) -> HierarchyResult<HierarchyId> {
+        let index = (0..MAX_DYN_HIERARCHIES)
+            .find(|&index| self.used & (1 << index) == 0)
+            .ok_or(HierarchyError::IdsExhausted {
+                max: MAX_DYN_HIERARCHIES,
+            })?;
+        self.used |= 1 << index;
+        Ok(HierarchyId(index))
+    }
+
+    /// Makes `id` available again. The hierarchy should be emptied first, as a later allocation
+    /// reuses its components.
+    pub fn release(&mut self, id: HierarchyId) {
+        self.used &= !(1 << id.0);
+    }
+
+    /// Returns true if `id` is allocated.
+    pub fn is_allocated(&self, id: HierarchyId) -> bool {
+        self.used & (1 << id.0) != 0
+    }
+}
+
+struct VTable {
+    attach: fn(&mut Frame, Entity, Entity) -> HierarchyResult<Entity>,
+    detach: fn(&mut Frame, Entity) -> HierarchyResult<Entity>,
+    despawn_all: fn(&mut Frame, Entity) -> HierarchyResult<usize>,
+    parent: fn(&Frame, Entity) -> Result<Entity>,
+    children: fn(&Frame, Entity) -> Vec<Entity>,
+    descendants: fn(&Frame, Entity) -> Vec<Entity>,
+    descendants_breadth_first: fn(&Frame, Entity) -> Vec<Entity>,
+    ancestors: fn(&Frame, Entity) -> Vec<Entity>,
+    roots: fn(&Frame) -> Result<Vec<Entity>>,
+}
+
+fn attach<const N: usize>(
+    frame: &mut Frame,
+    child: Entity,
+    parent: Entity,
+) -> HierarchyResult<Entity> {
+    frame.attach::<DynMarker<N>>(child, parent)
+}
+
+fn detach<const N: usize>(frame: &mut Frame, child: Entity) -> HierarchyResult<Entity> {
+    frame.detach::<DynMarker<N>>(child)
+}
+
+fn despawn_all<const N: usize>(frame: &mut Frame, parent: Entity) -> HierarchyResult<usize> {
+    frame.despawn_all::<DynMarker<N>>(parent)
+}
+
+fn parent<const N: usize>(frame: &Frame, child: Entity) -> Result<Entity> {
+    frame.parent::<DynMarker<N>>(child)
+}
+
+fn children<const N: usize>(frame: &Frame, parent: Entity) -> Vec<Entity> {
+    frame.children_vec::<DynMarker<N>>(parent)
+}
+
+fn descendants<const N: usize>(frame: &Frame, root: Entity) -> Vec<Entity> {
+    frame
+        .descendants_depth_first::<DynMarker<N>>(root)
+        .collect()
+}
+
+fn descendants_breadth_first<const N: usize>(frame: &Frame, root: Entity) -> Vec<Entity> {
+    frame
+        .descendants_breadth_first::<DynMarker<N>>(root)
+        .collect()
+}
+
+fn ancestors<const N: usize>(frame: &Frame, child: Entity) -> Vec<Entity> {
+    frame.ancestors::<DynMarker<N>>(child).collect()
+}
+
+fn roots<const N: usize>(frame: &Frame) -> Result<Vec<Entity>> {
+    Ok(frame
+        .roots::<DynMarker<N>>()?
+        .iter()
+        .map(|(root, _)| root)
+        .collect())
+}
+
+macro_rules! vtables {
+    ($($n:literal)*) => {
+        [$(VTable {
+            attach: attach::<$n>,
+            detach: detach::<$n>,
+            despawn_all: despawn_all::<$n>,
+            parent: parent::<$n>,
+            children: children::<$n>,
+            descendants: descendants::<$n>,
+            descendants_breadth_first: descendants_breadth_first::<$n>,
+            ancestors: ancestors::<$n>,
+            roots: roots::<$n>,
+        }),*]
+    };
+}
+
+static VTABLES: [VTable; MAX_DYN_HIERARCHIES] = vtables!(
+    0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31
+);
@@ -0,0 +1,182 @@
+use std::{
+    cmp::Ordering,
+    iter::FusedIterator,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+use moss_hecs::{Component, Entity, Ref};
+use moss_hecs_schedule::GenericWorld;
+
+use crate::ChildrenIter;
+
+/// Data `D` of the edge between a child and its parent in hierarchy `T`, such as a split ratio or
+/// a priority, stored on the child by [HierarchyMut::set_edge_data](crate::HierarchyMut::set_edge_data).
+///
+/// The data belongs to the parent it was set for. Once the child is detached or moved to another
+/// parent the data is stale and ignored by [Hierarchy::children_with_edge_data](crate::Hierarchy::children_with_edge_data),
+/// until it is set again.
+pub struct EdgeData<T, D> {
+    parent: Entity,
+    data: D,
+    marker: PhantomData<T>,
+}
+
+impl<T: Component, D: Component> EdgeData<T, D> {
+    pub(crate) fn new(parent: Entity, data: D) -> Self {
+        Self {
+            parent,
+            data,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the parent the data was set for.
+    pub fn parent(&self) -> Entity {
+        self.parent
+    }
+
+    /// Consumes the edge, returning the data.
+    pub fn into_inner(self) -> D {
+        self.data
+    }
+
+    /// Replace the entity reference of the parent using `map`. This is the hook for remapping
+    /// edge data which has been deserialized or copied into a frame where its parent has another
+    /// id.
+    pub fn remap_entities<F: FnMut(Entity) -> Entity>(&mut self, mut map: F) {
+        self.parent = map(self.parent);
+    }
+}
+
+impl<T, D> Deref for EdgeData<T, D> {
+    type Target = D;
+
+    fn deref(&self) -> &D {
+        &self.data
+    }
+}
+
+impl<T, D> DerefMut for EdgeData<T, D> {
+    fn deref_mut(&mut self) -> &mut D {
+        &mut self.data
+    }
+}
+
+impl<T, D: std::fmt::Debug> std::fmt::Debug for EdgeData<T, D> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EdgeData")
+            .field("parent", &self.parent)
+            .field("data", &self.data)
+            .finish()
+    }
+}
+
+/// Returns the data of the edge between `child` and `parent`, unless it is missing or stale.
+pub(crate) fn edge_data<'a, T: Component, D: Component, W: GenericWorld>(
+    frame: &'a W,
+    child: Entity,
+    parent: Entity,
+) -> Option<Ref<'a, EdgeData<T, D>>> {
+    frame
+        .try_get::<EdgeData<T, D>>(child)
+        .ok()
+        .filter(|edge| edge.parent == parent)
+}
+
+/// Iterates the children of a parent which have data `D` on their edge to it, along with the
+/// data. See [Hierarchy::children_with_edge_data](crate::Hierarchy::children_with_edge_data).
+pub struct ChildrenWithEdgeDataIter<'a, W, T: Component, D> {
+    frame: &'a W,
+    parent: Entity,
+    children: ChildrenIter<'a, T>,
+    marker: PhantomData<D>,
+}
+
+impl<'a, W: GenericWorld, T: Component, D: Component> ChildrenWithEdgeDataIter<'a, W, T, D> {
+    pub(crate) fn new(frame: &'a W, parent: Entity, children: ChildrenIter<'a, T>) -> Self {
+        Self {
+            frame,
+            parent,
+            children,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, W: GenericWorld, T: Component, D: Component> Iterator
+    for ChildrenWithEdgeDataIter<'a, W, T, D>
+{
+    type Item = (Entity, Ref<'a, EdgeData<T, D>>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (frame, parent) = (self.frame, self.parent);
+        self.children
+            .find_map(|child| Some((child, edge_data::<T, D, _>(frame, child, parent)?)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.children.size_hint().1)
+    }
+}
+
+impl<'a, W: GenericWorld, T: Component, D: Component> FusedIterator
+    for ChildrenWithEdgeDataIter<'a, W, T, D>
+{
+}
+
+/// Iterates the children of a parent ordered by the data on their edges to it. See
+/// [Hierarchy::children_by_edge_key](crate::Hierarchy::children_by_edge_key).
+pub struct ChildrenByEdgeKeyIter {
+    children: std::vec::IntoIter<Entity>,
+}
+
+impl ChildrenByEdgeKeyIter {
+    pub(crate) fn new<T: Component, D: Component + Ord, W: GenericWorld>(
+        frame: &W,
+        parent: Entity,
+        children: ChildrenIter<T>,
+    ) -> Self {
+        let mut keyed = children
+            .map(|child| (edge_data::<T, D, _>(frame, child, parent), child))
+            .collect::<Vec<_>>();
+
+        // Stable, so that children with equal keys keep their sibling order
+        keyed.sort_by(|(a, _), (b, _)| match (a, b) {
+            (Some(a), Some(b)) => (***a).cmp(&***b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        });
+
+        Self {
+            children: keyed
+                .into_iter()
+                .map(|(_, child)| child)
+                .collect::<Vec<_>>()
+                .into_iter(),
+        }
+    }
+}
+
+impl Iterator for ChildrenByEdgeKeyIter {
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.children.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.children.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for ChildrenByEdgeKeyIter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.children.next_back()
+    }
+}
+
+impl ExactSizeIterator for ChildrenByEdgeKeyIter {}
+
+impl FusedIterator for ChildrenByEdgeKeyIter {}
@@ -0,0 +1,154 @@
+use moss_hecs::Entity;
+use moss_hecs_schedule::Error;
+
+/// Result type of the hierarchy operations which can fail for reasons beyond the world itself.
+pub type HierarchyResult<T> = std::result::Result<T, HierarchyError>;
+
+/// The role of an entity passed to a hierarchy operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// The entity is attached, detached or moved
+    Child,
+    /// The entity has children attached to, detached from or moved under it
+    Parent,
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Role::Child => write!(f, "child"),
+            Role::Parent => write!(f, "parent"),
+        }
+    }
+}
+
+/// Errors of hierarchy operations which can fail for reasons beyond the world itself.
+#[derive(Debug)]
+pub enum HierarchyError {
+    /// An entity passed to the operation does not exist.
+    NoSuchEntity {
+        /// The dead entity
+        entity: Entity,
+        /// Which argument of the operation the entity was passed as
+        which: Role,
+    },
+    /// `child` is already attached to `parent`.
+    AlreadyAttached {
+        /// The attached child
+        child: Entity,
+        /// The current parent of `child`
+        parent: Entity,
+    },
+    /// The slot of `parent` which `child` is attached into already holds `occupant`.
+    SlotOccupied {
+        /// The entity which was attached
+        child: Entity,
+        /// The parent of the slot
+        parent: Entity,
+        /// The child already in the slot
+        occupant: Entity,
+    },
+    /// Attaching another child to `parent` would exceed the maximum number of children of the
+    /// [HierarchyPolicy](crate::HierarchyPolicy) of the hierarchy.
+    TooManyChildren {
+        /// The parent which has the maximum number of children
+        parent: Entity,
+        /// The maximum number of children
+        max: usize,
+    },
+    /// Attaching `child` would place it or one of its descendants deeper than the maximum depth of
+    /// the [HierarchyPolicy](crate::HierarchyPolicy) of the hierarchy.
+    TooDeep {
+        /// The entity which was attached
+        child: Entity,
+        /// The maximum depth
+        max: usize,
+    },
+    /// The [HierarchyPolicy](crate::HierarchyPolicy) of the hierarchy does not allow attaching
+    /// `child` to `parent`.
+    NotAllowed {
+        /// The entity which was attached
+        child: Entity,
+        /// The parent it was attached to
+        parent: Entity,
+    },
+    /// The operation would make `descendant` an ancestor of `ancestor`, creating a cycle.
+    Cycle {
+        /// The entity which is an ancestor of `descendant`
+        ancestor: Entity,
+        /// The entity which is a descendant of `ancestor`
+        descendant: Entity,
+    },
+    /// Every runtime [HierarchyId](crate::HierarchyId) is in use, since at most `max` can be
+    /// allocated at the same time.
+    IdsExhausted {
+        /// The number of ids, [MAX_DYN_HIERARCHIES](crate::MAX_DYN_HIERARCHIES)
+        max: usize,
+    },
+    /// Accessing the world failed.
+    World(Error),
+}
+
+impl std::fmt::Display for HierarchyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HierarchyError::NoSuchEntity { entity, which } => {
+                write!(f, "The {} {:?} does not exist", which, entity)
+            }
+            HierarchyError::AlreadyAttached { child, parent } => {
+                write!(f, "Entity {:?} is already attached to {:?}", child, parent)
+            }
+            HierarchyError::SlotOccupied {
+                child,
+                parent,
+                occupant,
+            } => write!(
+                f,
+                "Entity {:?} can not be attached to {:?}, since its slot is occupied by {:?}",
+                child, parent, occupant
+            ),
+            HierarchyError::TooManyChildren { parent, max } => write!(
+                f,
+                "Entity {:?} already has the maximum of {} children",
+                parent, max
+            ),
+            HierarchyError::TooDeep { child, max } => write!(
+                f,
+                "Attaching entity {:?} would exceed the maximum depth of {}",
+                child, max
+            ),
+            HierarchyError::NotAllowed { child, parent } => write!(
+                f,
+                "Entity {:?} is not allowed to be attached to {:?}",
+                child, parent
+            ),
+            HierarchyError::Cycle {
+                ancestor,
+                descendant,
+            } => write!(
+                f,
+                "Entity {:?} is an ancestor of {:?} which would create a cycle",
+                ancestor, descendant
+            ),
+            HierarchyError::IdsExhausted { max } => {
+                write!(f, "All {} runtime hierarchy ids are in use", max)
+            }
+            HierarchyError::World(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for HierarchyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HierarchyError::World(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for HierarchyError {
+    fn from(err: Error) -> Self {
+        HierarchyError::World(err)
+    }
+}
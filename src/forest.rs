@@ -0,0 +1,92 @@
+use std::marker::PhantomData;
+
+use moss_hecs::{Component, Entity, Frame};
+use moss_hecs_schedule::{error::Result, GenericWorld};
+
+use crate::{Hierarchy, HierarchyMut, HierarchyResult};
+
+/// The roots of every tree in hierarchy `T`, allowing operations over all trees at once.
+///
+/// The roots are collected when the forest is created, and can be brought up to date using
+/// [Forest::refresh].
+pub struct Forest<T> {
+    roots: Vec<Entity>,
+    marker: PhantomData<T>,
+}
+
+impl<T: Component> Forest<T> {
+    /// Collect the roots of every tree in hierarchy `T`
+    pub fn new<W: GenericWorld>(frame: &W) -> Result<Self> {
+        let mut forest = Self {
+            roots: Vec::new(),
+            marker: PhantomData,
+        };
+
+        forest.refresh(frame)?;
+        Ok(forest)
+    }
+
+    /// Recollect the roots of every tree in hierarchy `T`
+    pub fn refresh<W: GenericWorld>(&mut self, frame: &W) -> Result<()> {
+        self.roots.clear();
+        self.roots
+            .extend(frame.roots::<T>()?.iter().map(|(root, _)| root));
+
+        Ok(())
+    }
+
+    /// Get a reference to the roots of the forest.
+    pub fn roots(&self) -> &[Entity] {
+        &self.roots
+    }
+
+    /// Returns the number of trees in the forest.
+    pub fn len(&self) -> usize {
+        self.roots.len()
+    }
+
+    /// Returns true if the forest contains no trees.
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// Traverse every tree depth first. Unlike [Hierarchy::descendants_depth_first], each root is
+    /// included before its descendants.
+    pub fn iter_all_depth_first<'a, W: GenericWorld>(
+        &'a self,
+        frame: &'a W,
+    ) -> impl Iterator<Item = Entity> + 'a {
+        self.roots.iter().flat_map(move |&root| {
+            std::iter::once(root).chain(frame.descendants_depth_first::<T>(root))
+        })
+    }
+
+    /// Returns the total number of entities in all trees, including the roots.
+    pub fn count_nodes<W: GenericWorld>(&self, frame: &W) -> usize {
+        self.iter_all_depth_first(frame).count()
+    }
+
+    /// Returns the number of entities in each tree, including the root, in the same order as
+    /// [Forest::roots].
+    pub fn tree_sizes<W: GenericWorld>(&self, frame: &W) -> Vec<usize> {
+        self.roots
+            .iter()
+            .map(|&root| 1 + frame.descendants_depth_first::<T>(root).count())
+            .collect()
+    }
+
+    /// Despawn every tree in the forest. Returns the number of despawned entities.
+    pub fn despawn(self, frame: &mut Frame) -> HierarchyResult<usize> {
+        self.roots
+            .iter()
+            .try_fold(0, |count, &root| Ok(count + frame.despawn_all::<T>(root)?))
+    }
+}
+
+impl<T> std::fmt::Debug for Forest<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Forest")
+            .field("roots", &self.roots)
+            .finish()
+    }
+}
@@ -0,0 +1,98 @@
+use std::{collections::HashSet, fmt, marker::PhantomData};
+
+use moss_hecs::{Component, Entity};
+use moss_hecs_schedule::GenericWorld;
+
+use crate::Hierarchy;
+
+/// Formats the tree below `root` with one labelled entity per line, indenting every level below
+/// the root. See [TreeDisplay] for configuring the indentation.
+///
+/// ```text
+/// Root
+/// |-------- Child 1
+/// |-------- Child 2
+///           |-------- Grandchild
+/// ```
+pub fn format_tree<T, W, F>(frame: &W, root: Entity, labeler: F) -> String
+where
+    T: Component,
+    W: GenericWorld + Hierarchy,
+    F: Fn(&W, Entity) -> String,
+{
+    TreeDisplay::<T, W, F>::new(frame, root, labeler).to_string()
+}
+
+/// Displays the tree below `root` in hierarchy `T`, labelling every entity using `labeler`. An
+/// entity reached a second time, which means the hierarchy contains a cycle, is labelled once
+/// more but not descended into.
+pub struct TreeDisplay<'a, T, W, F> {
+    frame: &'a W,
+    root: Entity,
+    labeler: F,
+    indent: usize,
+    branch: &'a str,
+    marker: PhantomData<T>,
+}
+
+impl<'a, T, W, F> TreeDisplay<'a, T, W, F>
+where
+    T: Component,
+    W: GenericWorld + Hierarchy,
+    F: Fn(&W, Entity) -> String,
+{
+    /// Creates a display of the tree below `root` with the default indentation.
+    pub fn new(frame: &'a W, root: Entity, labeler: F) -> Self {
+        Self {
+            frame,
+            root,
+            labeler,
+            indent: 10,
+            branch: "|-------- ",
+            marker: PhantomData,
+        }
+    }
+
+    /// Set the number of spaces each level is indented by.
+    pub fn indent(mut self, indent: usize) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    /// Set the prefix written before the label of every entity below the root.
+    pub fn branch(mut self, branch: &'a str) -> Self {
+        self.branch = branch;
+        self
+    }
+}
+
+impl<'a, T, W, F> fmt::Display for TreeDisplay<'a, T, W, F>
+where
+    T: Component,
+    W: GenericWorld + Hierarchy,
+    F: Fn(&W, Entity) -> String,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut visited = HashSet::new();
+        let mut stack = vec![(self.root, 0)];
+
+        while let Some((entity, depth)) = stack.pop() {
+            let label = (self.labeler)(self.frame, entity);
+            if depth == 0 {
+                writeln!(f, "{}", label)?;
+            } else {
+                let indent = (depth - 1) * self.indent;
+                writeln!(f, "{:indent$}{}{}", "", self.branch, label, indent = indent)?;
+            }
+
+            if !visited.insert(entity) {
+                continue;
+            }
+
+            let children = self.frame.children_vec::<T>(entity);
+            stack.extend(children.into_iter().rev().map(|child| (child, depth + 1)));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+use std::{collections::HashSet, hash::Hasher};
+
+use moss_hecs::{Component, Entity};
+use moss_hecs_schedule::GenericWorld;
+
+use crate::Hierarchy;
+
+/// An entity whose children are still being hashed.
+struct Pending<H> {
+    children: Vec<Entity>,
+    next: usize,
+    hasher: H,
+}
+
+/// Hashes the tree below `root` bottom up, see [Hierarchy::tree_hash].
+pub(crate) fn tree_hash<T, H, W, F>(frame: &W, root: Entity, mut node_hash: F) -> u64
+where
+    T: Component,
+    H: Hasher + Default,
+    W: GenericWorld + Hierarchy,
+    F: FnMut(&W, Entity) -> u64,
+{
+    let mut start = |entity: Entity| {
+        let children = frame.children_vec::<T>(entity);
+        let mut hasher = H::default();
+        hasher.write_u64(node_hash(frame, entity));
+        hasher.write_usize(children.len());
+
+        Pending {
+            children,
+            next: 0,
+            hasher,
+        }
+    };
+
+    let mut visited = HashSet::new();
+    visited.insert(root);
+    let mut stack = vec![start(root)];
+
+    loop {
+        let top = stack.last_mut().unwrap();
+
+        if let Some(&child) = top.children.get(top.next) {
+            top.next += 1;
+            // Entities closing a cycle are not descended into again
+            if visited.insert(child) {
+                stack.push(start(child));
+            }
+            continue;
+        }
+
+        let hash = stack.pop().unwrap().hasher.finish();
+        match stack.last_mut() {
+            Some(parent) => parent.hasher.write_u64(hash),
+            None => return hash,
+        }
+    }
+}
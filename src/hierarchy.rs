@@ -1,19 +1,90 @@
-use std::mem;
+use std::{cmp::Ordering, collections::HashSet, hash::Hasher};
 
-use moss_hecs::{Component, DynamicBundle, Entity, Frame, QueryBorrow, Without};
+use moss_hecs::{Component, DynamicBundle, Entity, Frame, Query, QueryBorrow, View, Without};
 use moss_hecs_schedule::{error::Result, GenericWorld};
 
+#[cfg(all(feature = "strict-checks", debug_assertions))]
+use crate::strict;
+#[cfg(feature = "child-index")]
+use crate::ChildIndex;
 use crate::{
-    AncestorIter, BreadthFirstIterator, Child, ChildrenIter, DepthFirstIterator, DepthFirstVisitor,
-    Parent,
+    child_list::{enable_child_lists, list_linked, list_relinked, list_unlinked},
+    hash::tree_hash,
+    iter::{sort_by_component, sort_by_id, sort_by_layer},
+    observe::{notify, observe},
+    policy::{check_policy, check_policy_all, enable_policy, num_children},
+    registry::update_roots,
+    AncestorIter, AncestorsFromRootIter, AncestorsUntilIter, AncestorsWithIter, BreadthFirstIn,
+    BreadthFirstIterator, Child, ChildList, ChildStorage, ChildrenAliveIter, ChildrenByEdgeKeyIter,
+    ChildrenIter, ChildrenWithEdgeDataIter, ChildrenWithIter, DepthBuckets, DepthFirstIn,
+    DepthFirstIterator, DepthFirstVisitor, DescendantsExcludingIter, DescendantsWithIter,
+    DetachedSubtree, EdgeData, Edges, FlattenIter, HierarchyError, HierarchyEvent, HierarchyPolicy,
+    HierarchyResult, HierarchyTransaction, LayersIter, MarkerSet, Members, MembershipFilterIter,
+    Node, Parent, ParentPairs, Role, RootRegistry, SortedDepthFirstIter, SubtreeSplit,
+    TraversalCache, TreeStats,
 };
 
 /// A trait for modifying the worlds hierarchy. Implemented for `hecs::World`>
+///
+/// Every operation verifies that the entities passed to it are alive before modifying anything,
+/// failing with [HierarchyError::NoSuchEntity] which names the role of the dead entity.
 pub trait HierarchyMut {
     /// Attach `child` to `parent`. Parent does not require an existing `Parent component`. Returns
-    /// the passed child.
+    /// the passed child. If `child` is already attached in tree `T` it is detached from its current
+    /// parent first, bringing its own children along.
     /// *Note*: The entity needs to be explicitly detached before being removed.
-    fn attach<T: Component>(&mut self, child: Entity, parent: Entity) -> Result<Entity>;
+    fn attach<T: Component>(&mut self, child: Entity, parent: Entity) -> HierarchyResult<Entity>;
+
+    /// Attach `child` to `parent` as its first child rather than its last. If `child` is already
+    /// attached in tree `T` it is detached from its current parent first. Returns the passed
+    /// child.
+    fn attach_front<T: Component>(
+        &mut self,
+        child: Entity,
+        parent: Entity,
+    ) -> HierarchyResult<Entity>;
+
+    /// Attach `child` to the parent of `sibling`, directly before `sibling`. If `child` is already
+    /// attached in tree `T` it is detached from its current parent first. Returns the passed
+    /// child.
+    ///
+    /// Fails if `sibling` is not attached, or with [HierarchyError::Cycle] if `child` is an
+    /// ancestor of `sibling`.
+    fn attach_before<T: Component>(
+        &mut self,
+        child: Entity,
+        sibling: Entity,
+    ) -> HierarchyResult<Entity>;
+
+    /// Attach `child` to the parent of `sibling`, directly after `sibling`. See
+    /// [Self::attach_before].
+    fn attach_after<T: Component>(
+        &mut self,
+        child: Entity,
+        sibling: Entity,
+    ) -> HierarchyResult<Entity>;
+
+    /// Attach `child` as the last child of `parent` in every hierarchy of the tuple of markers
+    /// `M`, e.g. `frame.attach_multi::<(Scene, Focus)>(child, parent)`. Either `child` is attached
+    /// in all of them, or every hierarchy is left as it was. Returns the passed child.
+    ///
+    /// Fails with [HierarchyError::Cycle] if `child` is an ancestor of `parent` in any of the
+    /// hierarchies, or if the enabled [HierarchyPolicy] of any of them forbids the attachment,
+    /// before modifying anything. The observers of every hierarchy are notified once `child` is
+    /// attached in all of them.
+    fn attach_multi<M: MarkerSet>(
+        &mut self,
+        child: Entity,
+        parent: Entity,
+    ) -> HierarchyResult<Entity>;
+
+    /// Same as [Self::attach], but fails with [HierarchyError::AlreadyAttached] rather than moving
+    /// `child` if it already has a parent in tree `T`.
+    fn attach_strict<T: Component>(
+        &mut self,
+        child: Entity,
+        parent: Entity,
+    ) -> HierarchyResult<Entity>;
 
     /// Attach a new entity with specified components to `parent`. Parent does not require an existing `Parent component`. Returns
     /// the passed child.
@@ -21,27 +92,292 @@ pub trait HierarchyMut {
         &mut self,
         parent: Entity,
         components: C,
-    ) -> Result<Entity>;
+    ) -> HierarchyResult<Entity>;
+
+    /// Attach `child` to `parent`, inserting it among the existing children so that the children
+    /// stay sorted by [SortedChildren::Key]. A child with the same key as an existing sibling is
+    /// placed after it. Children without a key are attached last.
+    ///
+    /// Changing the key of an already attached child does not move it.
+    fn attach_sorted<T: SortedChildren>(
+        &mut self,
+        child: Entity,
+        parent: Entity,
+    ) -> HierarchyResult<Entity>;
+
+    /// Attach `child` as the last child of `parent` into the slot named `key`, such as the header
+    /// or content of a UI component. Every slot holds at most one child, which can be looked up
+    /// with [Hierarchy::child_in_slot]. The key is stored as the [EdgeData] of `child`, and the
+    /// slot is freed when `child` is detached or moved.
+    ///
+    /// Fails with [HierarchyError::SlotOccupied] if another child is already in the slot.
+    fn attach_slot<T: Component, K: Component + Eq>(
+        &mut self,
+        child: Entity,
+        parent: Entity,
+        key: K,
+    ) -> HierarchyResult<Entity>;
+
+    /// Reorders the children of `parent` by the component `K` of each child. The key of each child
+    /// is read once. The sort is stable, and children without a `K` are placed last.
+    fn sort_children_by_key<T: Component, K: Component + Ord>(
+        &mut self,
+        parent: Entity,
+    ) -> HierarchyResult<()>;
 
     /// Detaches all children from entity and detaches entity from parent. Use this before removing
     /// entities to ensure no loose entity ids.
-    fn detach_all<T: Component>(&mut self, entity: Entity) -> Result<()>;
+    fn detach_all<T: Component>(&mut self, entity: Entity) -> HierarchyResult<()>;
 
     /// Detaches all children of parent.
-    fn detach_children<T: Component>(&mut self, parent: Entity) -> Result<Vec<Entity>>;
-    fn despawn_children<T: Component>(&mut self, parent: Entity) -> Result<()>;
+    fn detach_children<T: Component>(&mut self, parent: Entity) -> HierarchyResult<Vec<Entity>>;
+    fn despawn_children<T: Component>(&mut self, parent: Entity) -> HierarchyResult<()>;
 
     /// Detach the child from tree `T`. The children of `child` will not remain in hierachy, but will
     /// remain attached to `child`, which means a later attach also will attach the children of `child`
     /// into the hierarchy. Essentially moving the subtree.
-    fn detach<T: Component>(&mut self, child: Entity) -> Result<()>;
+    ///
+    /// The `Child` component is removed from `child`, and the `Parent` component is removed from
+    /// the parent once its last child is detached. Returns the previous parent of `child`.
+    fn detach<T: Component>(&mut self, child: Entity) -> HierarchyResult<Entity>;
 
     /// Despawn parent and all children recursively. Essentially despawns a whole subtree including
-    /// root. Does not fail if there are invalid, dangling IDs in tree.
-    fn despawn_all<T: Component>(&mut self, parent: Entity);
+    /// root. Returns the number of despawned entities, or an error if `parent` does not exist.
+    /// Children which were despawned without being detached are skipped and not counted, while
+    /// their live siblings and descendants are despawned, as found by [Hierarchy::children_alive].
+    fn despawn_all<T: Component>(&mut self, parent: Entity) -> HierarchyResult<usize>;
+
+    /// Same as [Self::despawn_all], but returns the despawned entities, starting with `parent`
+    /// followed by its descendants in depth first order.
+    fn despawn_all_collect<T: Component>(&mut self, parent: Entity)
+        -> HierarchyResult<Vec<Entity>>;
+
+    /// Exchanges the positions of `a` and `b` in tree `T`, each taking the parent and sibling slot
+    /// of the other. Their descendants move along with them. If only one of them is attached, the
+    /// other takes its place and the first becomes a root.
+    ///
+    /// Fails with [HierarchyError::Cycle] if one is an ancestor of the other.
+    fn swap_subtrees<T: Component>(&mut self, a: Entity, b: Entity) -> HierarchyResult<()>;
+
+    /// Puts `new` into the exact sibling slot of `old`, and removes the subtree of `old` according
+    /// to `removal`. If `new` is already attached it is detached first along with its children,
+    /// even when it is part of the subtree of `old`.
+    ///
+    /// Fails if `old` is not attached, or with [HierarchyError::Cycle] if `new` is an ancestor of
+    /// `old`.
+    fn replace_child<T: Component>(
+        &mut self,
+        old: Entity,
+        new: Entity,
+        removal: Removal,
+    ) -> HierarchyResult<()>;
+
+    /// Removes everything deeper than `max_depth` levels below `root` according to `removal`, where
+    /// the children of `root` are at depth 1. Returns the roots of the removed subtrees, which are
+    /// the entities at depth `max_depth + 1`.
+    fn truncate_depth<T: Component>(
+        &mut self,
+        root: Entity,
+        max_depth: usize,
+        removal: Removal,
+    ) -> HierarchyResult<Vec<Entity>>;
+
+    /// Removes every subtree below `root` whose root matches `predicate` according to `removal`,
+    /// in a single depth first traversal. The descendants of a matching entity are not visited.
+    /// `root` itself is never removed. Returns the roots of the removed subtrees in depth first
+    /// order.
+    fn prune<T: Component, F: FnMut(&Self, Entity) -> bool>(
+        &mut self,
+        root: Entity,
+        removal: Removal,
+        predicate: F,
+    ) -> HierarchyResult<Vec<Entity>>;
+
+    /// Spawns a new root with the specified components and attaches `roots` beneath it in order.
+    /// Entries which are already attached are moved along with their children. Returns the new
+    /// root. Nothing is spawned if any of `roots` does not exist, and the new root is despawned
+    /// again if attaching all of `roots` to it would violate the enabled [HierarchyPolicy] of
+    /// `T`.
+    fn merge_into<T: Component, C: DynamicBundle>(
+        &mut self,
+        roots: &[Entity],
+        components: C,
+    ) -> HierarchyResult<Entity>;
+
+    /// Detach `root` along with its descendants, returning a handle which remembers the slot it
+    /// was detached from so it can later be reattached or despawned. If `root` is not attached
+    /// the handle has no previous parent.
+    fn detach_subtree<T: Component>(&mut self, root: Entity)
+        -> HierarchyResult<DetachedSubtree<T>>;
+
+    /// Moves every child of `from` to the end of the children of `to` in one pass, preserving
+    /// their order. Returns the moved children.
+    ///
+    /// Fails with [HierarchyError::Cycle] if `to` is a descendant of `from`.
+    fn reparent_children<T: Component>(
+        &mut self,
+        from: Entity,
+        to: Entity,
+    ) -> HierarchyResult<Vec<Entity>>;
+
+    /// Makes `children` the exact children of `parent`, in that order. Entries attached elsewhere
+    /// are moved from their current parent, and current children missing from `children` are
+    /// detached, becoming roots along with their descendants. Entries passed more than once are
+    /// linked at their first occurrence. Returns the detached children.
+    ///
+    /// Fails with [HierarchyError::Cycle] if an entry is `parent` or one of its ancestors, and
+    /// with the error of the enabled [HierarchyPolicy] if `parent` would get too many children or
+    /// an added entry may not be attached to it, both before changing anything.
+    fn set_children<T: Component>(
+        &mut self,
+        parent: Entity,
+        children: &[Entity],
+    ) -> HierarchyResult<Vec<Entity>>;
+
+    /// Recreates the structure of the tree of `From` below `root` in hierarchy `To` for the same
+    /// entities, so that every entity of the tree has the same children in the same order in both.
+    /// The position of `root` itself in `To` is left untouched, and the previous children in `To`
+    /// of the tree's entities are detached. The two hierarchies are independent afterwards.
+    ///
+    /// Fails with [HierarchyError::Cycle] before changing anything if a descendant of `root` in
+    /// `From` is an ancestor of `root` in `To`, and like [Self::set_children] if the children of
+    /// an entity violate the enabled [HierarchyPolicy] of `To`, leaving the entities before it
+    /// mirrored.
+    fn mirror<From: Component, To: Component>(&mut self, root: Entity) -> HierarchyResult<()>;
+
+    /// Removes `entity` from tree `T` and splices its children into its former slot among the
+    /// children of its parent, preserving their order. `entity` itself is left alive without
+    /// parent or children. Returns the spliced children.
+    ///
+    /// Fails if `entity` is not attached.
+    fn collapse<T: Component>(&mut self, entity: Entity) -> HierarchyResult<Vec<Entity>>;
+
+    /// Attaches every orphan of tree `T`, a child whose parent has been despawned, as the last
+    /// children of `new_parent` in the order of their ids. Returns the adopted orphans.
+    ///
+    /// Fails with [HierarchyError::Cycle] if `new_parent` is a descendant of an orphan, and with
+    /// the error of the enabled [HierarchyPolicy] if an orphan may not be attached to
+    /// `new_parent`, both before adopting any orphan.
+    fn adopt_orphans<T: Component>(&mut self, new_parent: Entity) -> HierarchyResult<Vec<Entity>>;
+
+    /// Enables the [RootRegistry] of hierarchy `T`, populated with the current roots. From then on
+    /// the operations of this trait keep it up to date. Does nothing if it is already enabled.
+    fn enable_root_registry<T: Component>(&mut self) -> HierarchyResult<()>;
+
+    /// Enables the [ChildStorage] of hierarchy `T`. If it keeps a [ChildList](crate::ChildList),
+    /// a list is inserted into every current parent, and from then on the operations of this
+    /// trait keep the lists in sync with the sibling rings and [Hierarchy::children] iterates
+    /// them. Does nothing if it is already enabled.
+    fn enable_child_storage<T: ChildStorage>(&mut self) -> HierarchyResult<()>;
+
+    /// Enables the [HierarchyPolicy] of hierarchy `T`. From then on the attach family of this
+    /// trait fails rather than violating it, as do the operations placing a child at an index:
+    /// [DetachedSubtree::reattach_at], [TreeBuilder::graft_at](crate::TreeBuilder::graft_at)
+    /// the undo and redo of [HierarchyJournal](crate::HierarchyJournal), and the operations
+    /// adding children to a parent: [Self::set_children], [Self::mirror], [Self::adopt_orphans]
+    /// and [TreeBuilderClone::reconcile](crate::TreeBuilderClone::reconcile). The other
+    /// operations, which rearrange existing children, do not check it, nor do rolling back a
+    /// [HierarchyTransaction] and restoring a [HierarchySnapshot](crate::HierarchySnapshot),
+    /// which return to an earlier state. Trees which already violate the policy are left as they
+    /// are. Does nothing if it is already enabled.
+    fn enable_policy<T: HierarchyPolicy>(&mut self) -> HierarchyResult<()>;
+
+    /// Sets the data of the edge between `child` and its parent in tree `T` to `data`, replacing
+    /// any previous data. The data is stored on `child` as [EdgeData], and is stale once `child`
+    /// is moved to another parent. Fails if `child` is not attached.
+    fn set_edge_data<T: Component, D: Component>(
+        &mut self,
+        child: Entity,
+        data: D,
+    ) -> HierarchyResult<()>;
+
+    /// Registers `observer` to be invoked synchronously with every [HierarchyEvent] of hierarchy
+    /// `T`: attaching through the `attach` family, [Self::adopt_orphans],
+    /// [DetachedSubtree::reattach_at], [TreeBuilder::graft_at](crate::TreeBuilder::graft_at) and
+    /// the undo and redo of [HierarchyJournal](crate::HierarchyJournal), [Self::detach], and
+    /// despawning through [Self::despawn_all] and its variants. Observers are invoked after the
+    /// change, in the order they were registered. Other operations, such as [Self::set_children],
+    /// are not observed.
+    fn observe<T: Component, F: FnMut(&HierarchyEvent) + Send + Sync + 'static>(
+        &mut self,
+        observer: F,
+    );
+
+    /// Runs `f` with a [HierarchyTransaction] through which the hierarchy `T` is modified. If `f`
+    /// fails, every structural change made through the transaction is reverted before the error
+    /// is returned, so that a multi step operation is applied either completely or not at all.
+    /// Reverting restores the previous state even if it violates the enabled [HierarchyPolicy] of
+    /// `T`.
+    ///
+    /// ```rust
+    /// # use moss_hecs_hierarchy::*;
+    /// # struct Tree;
+    /// # let mut frame = moss_hecs::Frame::default();
+    /// # let [a, b, c] = [(); 3].map(|_| frame.spawn(()));
+    /// let result = frame.hierarchy_transaction::<Tree, _, _>(|tx| {
+    ///     tx.attach(b, a)?;
+    ///     tx.attach(c, b)?;
+    ///     // Fails since `a` is not attached, which reverts the attachments above
+    ///     tx.detach(a)?;
+    ///     Ok(())
+    /// });
+    ///
+    /// assert!(result.is_err());
+    /// assert!(frame.parent::<Tree>(b).is_err());
+    /// ```
+    fn hierarchy_transaction<
+        T: Component,
+        R,
+        F: FnOnce(&mut HierarchyTransaction<T>) -> HierarchyResult<R>,
+    >(
+        &mut self,
+        f: F,
+    ) -> HierarchyResult<R>;
+}
+
+/// Declares the component used to order the children of hierarchy `T` when attaching with
+/// [HierarchyMut::attach_sorted].
+///
+/// ```rust
+/// use moss_hecs_hierarchy::*;
+///
+/// #[derive(PartialEq, Eq, PartialOrd, Ord)]
+/// struct OrderKey(i32);
+///
+/// struct Layers;
+///
+/// impl SortedChildren for Layers {
+///     type Key = OrderKey;
+/// }
+///
+/// let mut frame = moss_hecs::Frame::default();
+/// let root = frame.spawn(("Root",));
+/// let top = frame.spawn((OrderKey(2),));
+/// let bottom = frame.spawn((OrderKey(1),));
+///
+/// frame.attach_sorted::<Layers>(top, root).unwrap();
+/// frame.attach_sorted::<Layers>(bottom, root).unwrap();
+///
+/// assert_eq!(frame.children::<Layers>(root).collect::<Vec<_>>(), [bottom, top]);
+/// ```
+pub trait SortedChildren: Component {
+    /// The component of the children which determines their order
+    type Key: Component + Ord;
 }
 
 /// Non mutating part of hierarchy
+///
+/// Implemented for every [GenericWorld], so that every method is available on a
+/// [SubWorldRef](moss_hecs_schedule::SubWorldRef) as well as on a [Frame], which lets systems
+/// traversing a hierarchy run in parallel. A subworld needs shared access to `Parent<T>` and
+/// `Child<T>`, as granted by [HierarchyQuery], and to [RootRegistry] of `T` for
+/// [Self::registered_roots], as granted by [HierarchyQueryFull]. Methods reading another
+/// component, such as [Self::children_with] or [Self::descendants_depth_first_sorted_by_key],
+/// need access to that component as well.
+///
+/// Without the access, lookups of single entities fail and children or ancestors are empty,
+/// including the children filtered by [Self::children_with]. The depth first traversals and
+/// [Self::visit] panic instead, since they borrow the components for their whole lifetime.
 pub trait Hierarchy
 where
     Self: Sized,
@@ -51,16 +387,151 @@ where
 
     fn root<T: Component>(&self, child: Entity) -> Result<Entity>;
 
+    /// Returns the first child of parent. Fails if parent has no children.
+    fn first_child<T: Component>(&self, parent: Entity) -> Result<Entity>;
+
+    /// Returns the last child of parent without walking the siblings. Fails if parent has no
+    /// children.
+    fn last_child<T: Component>(&self, parent: Entity) -> Result<Entity>;
+
     /// Traverses the immediate children of parent. If parent is not a Parent, an empty iterator is
     /// returned.
     fn children<T: Component>(&self, parent: Entity) -> ChildrenIter<T>;
 
+    /// Traverses the immediate children of parent which have component `C`, skipping the rest.
+    /// Component `C` is borrowed immutably for the lifetime of the iterator.
+    fn children_with<T: Component, C: Component>(&self, parent: Entity) -> ChildrenWithIter<T, C>;
+
+    /// Traverses the immediate children of parent which have data `D` on their edge to it, along
+    /// with the data, skipping the rest. See [HierarchyMut::set_edge_data].
+    fn children_with_edge_data<T: Component, D: Component>(
+        &self,
+        parent: Entity,
+    ) -> ChildrenWithEdgeDataIter<Self, T, D>;
+
+    /// Traverses the immediate children of parent ordered by the data `D` on their edges to it,
+    /// without changing their stored order, such as for processing them by priority. Children
+    /// with equal data keep their sibling order, and children without data come last. The data is
+    /// only borrowed while sorting.
+    fn children_by_edge_key<T: Component, D: Component + Ord>(
+        &self,
+        parent: Entity,
+    ) -> ChildrenByEdgeKeyIter;
+
+    /// Returns the child of parent in the slot named `key`. See [HierarchyMut::attach_slot].
+    fn child_in_slot<T: Component, K: Component + Eq>(
+        &self,
+        parent: Entity,
+        key: &K,
+    ) -> Option<Entity>;
+
+    /// Collects the immediate children of parent in order, borrowing the components of `T` only
+    /// once. Useful for snapshotting the children before mutating the world. If parent is not a
+    /// Parent, an empty vector is returned.
+    fn children_vec<T: Component>(&self, parent: Entity) -> Vec<Entity>;
+
+    /// Returns a rayon parallel iterator over the immediate children of parent, for processing
+    /// them on the rayon thread pool. The children are collected up front, in order, which
+    /// borrows the components of `T` only for the duration of this call.
+    #[cfg(feature = "parallel")]
+    fn par_children<T: Component>(&self, parent: Entity) -> rayon::vec::IntoIter<Entity>;
+
+    /// Traverses the immediate children of parent which are still alive. Unlike [Self::children],
+    /// children which have been despawned without being detached are skipped rather than ending
+    /// the iteration, which makes it tolerant to entities despawned directly through the world.
+    ///
+    /// The ring is walked from both ends of the list. When that does not reach every live child,
+    /// including when the last child itself is despawned, the children of `T` are scanned for those
+    /// of `parent`. Sibling order is kept across single despawned siblings, while runs of children
+    /// separated from the rest by several consecutive despawned siblings are yielded in between,
+    /// in the order of their ids.
+    fn children_alive<T: Component>(&self, parent: Entity) -> ChildrenAliveIter;
+
     /// Traverse the tree upwards. Iterator does not include the child itself.
     fn ancestors<T: Component>(&self, child: Entity) -> AncestorIter<T>;
 
+    /// Traverse the ancestors of child which have component `C`, from the immediate parent
+    /// upwards, along with the component. The nearest such ancestor is the first item. Iterator
+    /// does not include the child itself.
+    fn ancestors_with<T: Component, C: Component>(
+        &self,
+        child: Entity,
+    ) -> AncestorsWithIter<Self, T, C>;
+
+    /// Traverse the ancestors of child from the immediate parent upwards, up to and including the
+    /// first ancestor which matches `predicate`, such as the nearest scroll container. All
+    /// ancestors are yielded if none matches. Iterator does not include the child itself.
+    fn ancestors_until<T: Component, F: FnMut(&Self, Entity) -> bool>(
+        &self,
+        child: Entity,
+        predicate: F,
+    ) -> AncestorsUntilIter<Self, T, F>;
+
+    /// Traverse the ancestors of child in order from the root down to the immediate parent, which
+    /// is the reverse order of [Self::ancestors]. Iterator does not include the child itself.
+    fn ancestors_from_root<T: Component>(&self, child: Entity) -> AncestorsFromRootIter;
+
+    /// Returns the shortest path from `a` to `b` in tree `T`, going up from `a` to their lowest
+    /// common ancestor and down to `b`. The path includes both `a` and `b`. Returns `None` if they
+    /// are not in the same tree.
+    fn path_between<T: Component>(&self, a: Entity, b: Entity) -> Option<Vec<Entity>>;
+
+    /// Returns the entity following `entity` in document order, which is the depth first order of
+    /// its tree, such as for moving the focus forward. Steps into the first child, or else to the
+    /// next sibling of `entity` or of its nearest ancestor which has one. After the last entity
+    /// of the tree, returns the root if `wrap` is set and `None` otherwise.
+    fn next_in_tree<T: Component>(&self, entity: Entity, wrap: bool) -> Option<Entity>;
+
+    /// Returns the entity preceding `entity` in document order, the reverse of
+    /// [Self::next_in_tree]. Steps to the deepest last descendant of the previous sibling, or
+    /// else to the parent. Before the root, returns the last entity of the tree if `wrap` is set
+    /// and `None` otherwise.
+    fn prev_in_tree<T: Component>(&self, entity: Entity, wrap: bool) -> Option<Entity>;
+
+    /// Traverse the tree below `root` in document order, including `root`, skipping the entities
+    /// for which `skip` returns `true` along with their descendants, such as hidden or disabled
+    /// parts of a UI. This is the focus order matching [Self::next_in_tree].
+    fn flatten<T: Component, F: FnMut(&Self, Entity) -> bool>(
+        &self,
+        root: Entity,
+        skip: F,
+    ) -> FlattenIter<Self, T, F>;
+
     /// Traverse the tree depth first. Iterator does not include the child itself.
     fn descendants_depth_first<T: Component>(&self, root: Entity) -> DepthFirstIterator<T>;
 
+    /// Same as [Self::descendants_depth_first], but keeps the traversal stack in `cache` rather
+    /// than allocating it.
+    fn descendants_depth_first_in<'a, 'c, T: Component>(
+        &'a self,
+        root: Entity,
+        cache: &'c mut TraversalCache,
+    ) -> DepthFirstIn<'a, 'c, T>;
+
+    /// Traverse the tree depth first, yielding only the descendants which satisfy the query of
+    /// `view` along with their query item. The query is borrowed once for the whole traversal
+    /// rather than once per entity. Iterator does not include the root itself.
+    ///
+    /// ```rust
+    /// use moss_hecs_hierarchy::*;
+    ///
+    /// struct Tree;
+    /// let mut frame = moss_hecs::Frame::default();
+    /// let root = frame.spawn(("Root",));
+    /// frame.attach_new::<Tree, _>(root, ("Child", 5_u32)).unwrap();
+    ///
+    /// let mut query = frame.query::<&u32>();
+    /// let view = query.view();
+    /// for (_, value) in frame.descendants_with::<Tree, _>(root, &view) {
+    ///     assert_eq!(*value, 5);
+    /// }
+    /// ```
+    fn descendants_with<'a, 'v, 'q, T: Component, Q: Query>(
+        &'a self,
+        root: Entity,
+        view: &'v View<'q, Q>,
+    ) -> DescendantsWithIter<'a, 'v, 'q, T, Q>;
+
     /// Traverse the tree depth first with an acceptance function
     fn visit<T: Component, F: Fn(&Self, Entity) -> bool + Component>(
         &self,
@@ -74,48 +545,338 @@ where
         root: Entity,
     ) -> BreadthFirstIterator<Self, T>;
 
+    /// Traverse tree `A` depth first, yielding only the descendants which are also members of
+    /// hierarchy `B`, having a `Parent<B>` or `Child<B>`. Iterator does not include the root
+    /// itself.
+    fn descendants_in_both<A: Component, B: Component>(
+        &self,
+        root: Entity,
+    ) -> MembershipFilterIter<A, B>;
+
+    /// Traverse tree `A` depth first, yielding only the descendants which are not members of
+    /// hierarchy `B`. Iterator does not include the root itself.
+    fn descendants_not_in<A: Component, B: Component>(
+        &self,
+        root: Entity,
+    ) -> MembershipFilterIter<A, B>;
+
+    /// Traverse the tree depth first, skipping `excluded` and everything beneath it. Iterator does
+    /// not include the root itself, and is empty if the root is excluded.
+    fn descendants_excluding<T: Component>(
+        &self,
+        root: Entity,
+        excluded: Entity,
+    ) -> DescendantsExcludingIter<T>;
+
+    /// Traverse the tree depth first, visiting the children of every entity in the order of their
+    /// ids rather than their sibling order. The order only depends on the shape of the tree and
+    /// the entity ids, which makes it reproducible. Iterator does not include the root itself.
+    fn descendants_depth_first_sorted<T: Component>(
+        &self,
+        root: Entity,
+    ) -> SortedDepthFirstIter<Self, T>;
+
+    /// Traverse the tree depth first, visiting the children of every entity ordered by their
+    /// component `K`. Children without `K` are visited last, and ties are broken by entity id.
+    /// Iterator does not include the root itself.
+    fn descendants_depth_first_sorted_by_key<T: Component, K: Component + Ord>(
+        &self,
+        root: Entity,
+    ) -> SortedDepthFirstIter<Self, T>;
+
+    /// Traverse the tree depth first, visiting the children of every entity ordered by their
+    /// layer component `Z`, such as for rendering in an order which differs from the stored
+    /// sibling order. Children with the same or incomparable `Z` keep their sibling order, and
+    /// children without `Z` are visited last. The stored order is not modified. Iterator does not
+    /// include the root itself.
+    fn descendants_z_ordered<T: Component, Z: Component + PartialOrd>(
+        &self,
+        root: Entity,
+    ) -> SortedDepthFirstIter<Self, T>;
+
+    /// Traverse the tree one depth level at a time, yielding the entities of each level in
+    /// breadth first order. The first level are the children of `root`, which itself is not
+    /// included.
+    fn layers<T: Component>(&self, root: Entity) -> LayersIter<Self, T>;
+
+    /// Measures the tree below `root`: node count, depth, branching and the number of entities at
+    /// each depth. Visits every entity of the tree once.
+    fn stats<T: Component>(&self, root: Entity) -> TreeStats;
+
+    /// Groups the tree below `root`, including `root`, by depth. The entities at depth `i` are in
+    /// the `i`th group, in breadth first order.
+    ///
+    /// The groups double as batches for work which depends on the parent of each entity: every
+    /// entity is in a later group than its parent, so the entities of a single group can be
+    /// processed in parallel, and no grouping needs fewer batches.
+    fn entities_by_depth<T: Component>(&self, root: Entity) -> Vec<Vec<Entity>>;
+
+    /// Alias of [Self::entities_by_depth], named after its use for processing a tree in
+    /// dependency order, such as propagating transforms from parents to children. Every batch
+    /// only depends on earlier batches, so the entities of a single batch can be processed in
+    /// parallel.
+    fn topological_batches<T: Component>(&self, root: Entity) -> Vec<Vec<Entity>> {
+        self.entities_by_depth::<T>(root)
+    }
+
+    /// Same as [Self::entities_by_depth], but fills the reusable `buckets` rather than allocating
+    /// new groups, and returns the filled groups.
+    fn entities_by_depth_in<'b, T: Component>(
+        &self,
+        root: Entity,
+        buckets: &'b mut DepthBuckets,
+    ) -> &'b [Vec<Entity>];
+
+    /// Partitions the tree below `root` into about `approx_chunks` chunks of disjoint subtrees of
+    /// roughly equal total size, for handing to worker threads. Subtrees which are larger than a
+    /// chunk are split into the subtrees of their children, and reported as interior entities to
+    /// be processed separately. Fewer chunks are returned if there are not enough subtrees.
+    fn split_subtrees<T: Component>(&self, root: Entity, approx_chunks: usize) -> SubtreeSplit;
+
+    /// Computes an order sensitive hash of the tree below `root`, including `root`. Every entity
+    /// is hashed with a new `H` from the hash `node_hash` returns for it, its number of children
+    /// and the hashes of its children in order. The hash of a subtree therefore only changes if
+    /// the subtree or the node hashes within it change, which makes it useful for detecting
+    /// changes and invalidating data derived per subtree.
+    fn tree_hash<T: Component, H: Hasher + Default, F: FnMut(&Self, Entity) -> u64>(
+        &self,
+        root: Entity,
+        node_hash: F,
+    ) -> u64;
+
+    /// Returns the first descendant of `root` in depth first order which matches `predicate`,
+    /// ending the traversal at the match. `root` itself is not tested.
+    fn find_descendant<T: Component, F: FnMut(&Self, Entity) -> bool>(
+        &self,
+        root: Entity,
+        predicate: F,
+    ) -> Option<Entity>;
+
+    /// Returns the deepest entity of the tree below `root` which matches `predicate`, such as the
+    /// entity under the pointer. Starting at `root`, the children of every matching entity are
+    /// tested front to back, from the last child to the first, descending into the first match. The
+    /// subtrees of entities which do not match are not visited, and `None` is returned if `root`
+    /// itself does not match.
+    fn hit_test<T: Component, F: FnMut(&Self, Entity) -> bool>(
+        &self,
+        root: Entity,
+        predicate: F,
+    ) -> Option<Entity>;
+
+    /// Returns the nearest ancestor of `child` which matches `predicate`, ending the traversal at
+    /// the match. `child` itself is not tested.
+    fn find_ancestor<T: Component, F: FnMut(&Self, Entity) -> bool>(
+        &self,
+        child: Entity,
+        predicate: F,
+    ) -> Option<Entity>;
+
+    /// Dispatches `event` to `target` and then to its ancestors from the immediate parent upwards,
+    /// such as routing pointer and keyboard events through a UI. Dispatching ends at the first
+    /// entity for which `handler` returns `true`, reporting the event as handled, which is
+    /// returned. Returns `None` if no entity handled the event.
+    fn bubble<T: Component, E, F: FnMut(&Self, Entity, &mut E) -> bool>(
+        &self,
+        target: Entity,
+        event: &mut E,
+        handler: F,
+    ) -> Option<Entity>;
+
+    /// Dispatches `event` to the ancestors of `target` from the root down to the immediate parent
+    /// and then to `target` itself, which is the capture phase preceding [Self::bubble].
+    /// Dispatching ends at the first entity for which `handler` returns `true`, reporting the
+    /// event as handled, which is returned. Returns `None` if no entity handled the event.
+    fn capture<T: Component, E, F: FnMut(&Self, Entity, &mut E) -> bool>(
+        &self,
+        target: Entity,
+        event: &mut E,
+        handler: F,
+    ) -> Option<Entity>;
+
+    /// Same as [Self::descendants_breadth_first], but keeps the traversal queue in `cache` rather
+    /// than allocating it.
+    fn descendants_breadth_first_in<'a, 'c, T: Component>(
+        &'a self,
+        root: Entity,
+        cache: &'c mut TraversalCache,
+    ) -> BreadthFirstIn<'a, 'c, Self, T>;
+
     /// Returns an iterator over all root objects in the world
     fn roots<T: Component>(&self) -> Result<QueryBorrow<Without<&Parent<T>, &Child<T>>>>;
+
+    /// Returns the roots of hierarchy `T` as tracked by its [RootRegistry], in no particular
+    /// order, or `None` if the registry is not enabled. Unlike [Self::roots] this does not scan the
+    /// archetypes of the world.
+    fn registered_roots<T: Component>(&self) -> Option<Vec<Entity>>;
+
+    /// Returns a handle to `entity` in tree `T` for convenient traversal.
+    fn node<T: Component>(&self, entity: Entity) -> Node<Self, T>;
+
+    /// Returns every `(parent, child)` pair of hierarchy `T` in the world.
+    fn edges<T: Component>(&self) -> Result<Edges<T>>;
+
+    /// Returns every entity in the world which has a `Parent<T>` or `Child<T>`, regardless of
+    /// which tree it belongs to.
+    fn members<T: Component>(&self) -> Result<Members<T>>;
+
+    /// Returns every entity in the world which belongs to hierarchy `T` along with its parent, or
+    /// `None` for the roots, such as for building an external index of the hierarchy in a single
+    /// pass.
+    fn parents_iter<T: Component>(&self) -> Result<ParentPairs<T>>;
 }
 
 impl HierarchyMut for Frame {
-    fn attach<T: Component>(&mut self, child: Entity, parent: Entity) -> Result<Entity> {
-        let mut maybe_p = self.try_get_mut::<Parent<T>>(parent);
-        if let Ok(ref mut p) = maybe_p {
-            p.num_children += 1;
-            let prev = p.last_child;
-            p.last_child = child;
-
-            if p.num_children == 1 {
-                mem::drop(maybe_p);
-                self.try_insert(child, (Child::<T>::new(parent, child, child),))?;
-            } else {
-                let mut prev_data = self.try_get_mut::<Child<T>>(prev)?;
-                let next = prev_data.next;
-                prev_data.next = child;
+    fn attach<T: Component>(&mut self, child: Entity, parent: Entity) -> HierarchyResult<Entity> {
+        ensure_alive(self, child, Role::Child)?;
+        ensure_alive(self, parent, Role::Parent)?;
 
-                mem::drop(prev_data);
-                mem::drop(maybe_p);
+        #[cfg(all(feature = "strict-checks", debug_assertions))]
+        strict::check_acyclic::<T>(self, "attach", child, parent);
 
-                // Update backward linking
-                {
-                    let mut next_data = self.try_get_mut::<Child<T>>(next)?;
-                    next_data.prev = child;
-                }
+        check_policy::<T>(self, child, parent)?;
 
-                self.try_insert(child, (Child::<T>::new(parent, next, prev),))?;
-            }
+        if self.try_get::<Child<T>>(child).is_ok() {
+            unlink_child::<T>(self, child)?;
+        }
+
+        link_child::<T>(self, child, parent, Position::Back)?;
+
+        #[cfg(all(feature = "strict-checks", debug_assertions))]
+        strict::check_ring::<T>(self, "attach", parent);
+
+        notify::<T>(self, HierarchyEvent::Attached { child, parent });
+        Ok(child)
+    }
+
+    fn attach_front<T: Component>(
+        &mut self,
+        child: Entity,
+        parent: Entity,
+    ) -> HierarchyResult<Entity> {
+        ensure_alive(self, child, Role::Child)?;
+        ensure_alive(self, parent, Role::Parent)?;
+
+        #[cfg(all(feature = "strict-checks", debug_assertions))]
+        strict::check_acyclic::<T>(self, "attach_front", child, parent);
+
+        check_policy::<T>(self, child, parent)?;
+
+        if self.try_get::<Child<T>>(child).is_ok() {
+            unlink_child::<T>(self, child)?;
+        }
+
+        link_child::<T>(self, child, parent, Position::Front)?;
+
+        #[cfg(all(feature = "strict-checks", debug_assertions))]
+        strict::check_ring::<T>(self, "attach_front", parent);
+
+        notify::<T>(self, HierarchyEvent::Attached { child, parent });
+        Ok(child)
+    }
+
+    fn attach_before<T: Component>(
+        &mut self,
+        child: Entity,
+        sibling: Entity,
+    ) -> HierarchyResult<Entity> {
+        let parent = unlink_for_sibling::<T>(self, child, sibling)?;
+        if child == sibling {
+            return Ok(child);
+        }
 
+        let position = if self.try_get::<Parent<T>>(parent)?.first_child(self)? == sibling {
+            Position::Front
+        } else {
+            Position::After(self.try_get::<Child<T>>(sibling)?.prev)
+        };
+
+        link_child::<T>(self, child, parent, position)?;
+        notify::<T>(self, HierarchyEvent::Attached { child, parent });
+        Ok(child)
+    }
+
+    fn attach_after<T: Component>(
+        &mut self,
+        child: Entity,
+        sibling: Entity,
+    ) -> HierarchyResult<Entity> {
+        let parent = unlink_for_sibling::<T>(self, child, sibling)?;
+        if child == sibling {
             return Ok(child);
         }
 
-        mem::drop(maybe_p);
+        link_child::<T>(self, child, parent, Position::After(sibling))?;
+        notify::<T>(self, HierarchyEvent::Attached { child, parent });
+        Ok(child)
+    }
+
+    fn attach_multi<M: MarkerSet>(
+        &mut self,
+        child: Entity,
+        parent: Entity,
+    ) -> HierarchyResult<Entity> {
+        M::attach_all(self, child, parent)?;
+        Ok(child)
+    }
+
+    fn attach_strict<T: Component>(
+        &mut self,
+        child: Entity,
+        parent: Entity,
+    ) -> HierarchyResult<Entity> {
+        if let Ok(current) = self.parent::<T>(child) {
+            return Err(HierarchyError::AlreadyAttached {
+                child,
+                parent: current,
+            });
+        }
+
+        self.attach::<T>(child, parent)
+    }
+
+    fn attach_sorted<T: SortedChildren>(
+        &mut self,
+        child: Entity,
+        parent: Entity,
+    ) -> HierarchyResult<Entity> {
+        ensure_alive(self, child, Role::Child)?;
+        ensure_alive(self, parent, Role::Parent)?;
+
+        if self.try_get::<T::Key>(child).is_err() {
+            return self.attach::<T>(child, parent);
+        }
+
+        #[cfg(all(feature = "strict-checks", debug_assertions))]
+        strict::check_acyclic::<T>(self, "attach_sorted", child, parent);
+
+        check_policy::<T>(self, child, parent)?;
+
+        // Unlinked rather than detached, as the move is observed as a single attachment
+        if self.try_get::<Child<T>>(child).is_ok() {
+            unlink_child::<T>(self, child)?;
+        }
+
+        let position = {
+            let key = self.try_get::<T::Key>(child)?;
+            let mut position = Position::Front;
+
+            // Insert after the last sibling with a key less than or equal to the key of child
+            for sibling in self.children::<T>(parent) {
+                match self.try_get::<T::Key>(sibling) {
+                    Ok(sibling_key) if *sibling_key <= *key => position = Position::After(sibling),
+                    _ => break,
+                }
+            }
+
+            position
+        };
 
-        // Parent component didn't exist
-        self.try_insert(parent, (Parent::<T>::new(1, child),))?;
+        link_child::<T>(self, child, parent, position)?;
 
-        self.try_insert(child, (Child::<T>::new(parent, child, child),))?;
+        #[cfg(all(feature = "strict-checks", debug_assertions))]
+        strict::check_ring::<T>(self, "attach_sorted", parent);
 
+        notify::<T>(self, HierarchyEvent::Attached { child, parent });
         Ok(child)
     }
 
@@ -123,100 +884,579 @@ impl HierarchyMut for Frame {
         &mut self,
         parent: Entity,
         components: C,
-    ) -> Result<Entity> {
+    ) -> HierarchyResult<Entity> {
+        ensure_alive(self, parent, Role::Parent)?;
+
         let child = self.spawn(components);
-        self.attach::<T>(child, parent)
+        self.attach::<T>(child, parent).map_err(|err| {
+            let _ = self.despawn(child);
+            err
+        })
+    }
+
+    fn sort_children_by_key<T: Component, K: Component + Ord>(
+        &mut self,
+        parent: Entity,
+    ) -> HierarchyResult<()> {
+        ensure_alive(self, parent, Role::Parent)?;
+
+        let children = {
+            let mut keyed = self
+                .children::<T>(parent)
+                .map(|child| (self.try_get::<K>(child).ok(), child))
+                .collect::<Vec<_>>();
+
+            keyed.sort_by(|(a, _), (b, _)| match (a, b) {
+                (Some(a), Some(b)) => (**a).cmp(&**b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            });
+
+            keyed
+                .into_iter()
+                .map(|(_, child)| child)
+                .collect::<Vec<_>>()
+        };
+
+        if children.is_empty() {
+            return Ok(());
+        }
+
+        relink_children::<T>(self, parent, &children)?;
+        Ok(())
+    }
+
+    fn attach_slot<T: Component, K: Component + Eq>(
+        &mut self,
+        child: Entity,
+        parent: Entity,
+        key: K,
+    ) -> HierarchyResult<Entity> {
+        ensure_alive(self, child, Role::Child)?;
+        ensure_alive(self, parent, Role::Parent)?;
+
+        #[cfg(all(feature = "strict-checks", debug_assertions))]
+        strict::check_acyclic::<T>(self, "attach_slot", child, parent);
+
+        match self.child_in_slot::<T, K>(parent, &key) {
+            Some(occupant) if occupant != child => {
+                return Err(HierarchyError::SlotOccupied {
+                    child,
+                    parent,
+                    occupant,
+                })
+            }
+            Some(_) => return Ok(child),
+            None => {}
+        }
+
+        self.attach::<T>(child, parent)?;
+        self.set_edge_data::<T, K>(child, key)?;
+        Ok(child)
     }
 
-    fn detach_all<T: Component>(&mut self, entity: Entity) -> Result<()> {
+    fn detach_all<T: Component>(&mut self, entity: Entity) -> HierarchyResult<()> {
+        ensure_alive(self, entity, Role::Child)?;
+
         self.detach_children::<T>(entity)?;
         self.detach::<T>(entity)?;
         Ok(())
     }
 
     /// Detaches all children of parent.
-    fn detach_children<T: Component>(&mut self, parent: Entity) -> Result<Vec<Entity>> {
+    fn detach_children<T: Component>(&mut self, parent: Entity) -> HierarchyResult<Vec<Entity>> {
+        ensure_alive(self, parent, Role::Parent)?;
+
         let children = self.children::<T>(parent).collect::<Vec<Entity>>();
 
         children.iter().try_for_each(|child| -> Result<_> {
             self.try_remove_one::<Child<T>>(*child)?;
+            #[cfg(feature = "child-index")]
+            let _ = self.remove_one::<ChildIndex<T>>(*child);
             Ok(())
         })?;
 
-        self.remove_one::<Parent<T>>(parent).unwrap();
+        let _ = self.remove_one::<Parent<T>>(parent);
+        let _ = self.remove_one::<ChildList<T>>(parent);
+
+        update_roots::<T>(self, &children);
+        update_roots::<T>(self, &[parent]);
 
         Ok(children)
     }
 
-    /// Detaches all children of parent.
-    fn despawn_children<T: Component>(&mut self, parent: Entity) -> Result<()> {
-        let children = self.children::<T>(parent).collect::<Vec<Entity>>();
+    /// Despawns all children of parent along with their descendants.
+    fn despawn_children<T: Component>(&mut self, parent: Entity) -> HierarchyResult<()> {
+        ensure_alive(self, parent, Role::Parent)?;
+
+        let children = self.children_vec::<T>(parent);
 
+        // Share the worklist between the subtrees
+        let mut despawned = Vec::new();
         children
             .iter()
-            .for_each(|child| self.despawn_all::<Child<T>>(*child));
+            .try_for_each(|&child| despawn_tree::<T>(self, child, &mut despawned))
+    }
 
-        self.remove_one::<Parent<T>>(parent).unwrap();
+    fn detach<T: Component>(&mut self, child: Entity) -> HierarchyResult<Entity> {
+        ensure_alive(self, child, Role::Child)?;
 
-        Ok(())
+        let parent = unlink_child::<T>(self, child)?;
+
+        #[cfg(all(feature = "strict-checks", debug_assertions))]
+        strict::check_ring::<T>(self, "detach", parent);
+
+        notify::<T>(self, HierarchyEvent::Detached { child, parent });
+        Ok(parent)
     }
 
-    fn detach<T: Component>(&mut self, child: Entity) -> Result<()> {
-        let data = self.try_get_mut::<Child<T>>(child)?;
-        let parent = data.parent;
-        let prev = data.prev;
-        let next = data.next;
+    fn despawn_all<T: Component>(&mut self, parent: Entity) -> HierarchyResult<usize> {
+        self.despawn_all_collect::<T>(parent)
+            .map(|despawned| despawned.len())
+    }
 
-        mem::drop(data);
+    fn despawn_all_collect<T: Component>(
+        &mut self,
+        parent: Entity,
+    ) -> HierarchyResult<Vec<Entity>> {
+        let mut despawned = Vec::new();
+        despawn_tree::<T>(self, parent, &mut despawned)?;
+        Ok(despawned)
+    }
 
-        self.try_get_mut::<Child<T>>(prev)?.next = next;
-        self.try_get_mut::<Child<T>>(next)?.prev = prev;
+    fn swap_subtrees<T: Component>(&mut self, a: Entity, b: Entity) -> HierarchyResult<()> {
+        ensure_alive(self, a, Role::Child)?;
+        ensure_alive(self, b, Role::Child)?;
 
-        let mut parent = self.try_get_mut::<Parent<T>>(parent)?;
-        parent.num_children -= 1;
-        if parent.last_child == child {
-            parent.last_child = prev;
+        if a == b {
+            return Ok(());
         }
 
-        Ok(())
-    }
+        if self.ancestors::<T>(a).any(|ancestor| ancestor == b) {
+            return Err(HierarchyError::Cycle {
+                ancestor: b,
+                descendant: a,
+            });
+        }
+
+        if self.ancestors::<T>(b).any(|ancestor| ancestor == a) {
+            return Err(HierarchyError::Cycle {
+                ancestor: a,
+                descendant: b,
+            });
+        }
+
+        let parent_a = self.parent::<T>(a).ok();
+        let parent_b = self.parent::<T>(b).ok();
 
-    fn despawn_all<T: Component>(&mut self, parent: Entity) {
-        let to_despawn = self
-            .descendants_depth_first::<T>(parent)
+        // Collect every sibling list before relinking, as relinking one list changes the links
+        // of `a` or `b` which the other list is traversed through.
+        let lists = parent_a
+            .into_iter()
+            .chain(parent_b.filter(|&parent| Some(parent) != parent_a))
+            .map(|parent| {
+                let children = self
+                    .children::<T>(parent)
+                    .map(|child| {
+                        if child == a {
+                            b
+                        } else if child == b {
+                            a
+                        } else {
+                            child
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                (parent, children)
+            })
             .collect::<Vec<_>>();
 
-        // Detach from parent if necessary
-        let _ = self.detach::<T>(parent);
+        for (parent, children) in lists {
+            relink_children::<T>(self, parent, &children)?;
+        }
 
-        // Should not panic since we just
-        to_despawn.iter().for_each(|entity| {
-            let _ = self.despawn(*entity);
-        });
+        // When swapping with a root, the entity taking its place still has a `Child` component
+        // for the slot it left
+        match (parent_a, parent_b) {
+            (Some(_), None) => clear_child::<T>(self, a)?,
+            (None, Some(_)) => clear_child::<T>(self, b)?,
+            _ => {}
+        }
 
-        let _ = self.despawn(parent);
+        Ok(())
     }
-}
 
-impl<W: GenericWorld> Hierarchy for W {
-    fn parent<T: Component>(&self, child: Entity) -> Result<Entity> {
-        self.try_get::<Child<T>>(child).map(|child| child.parent)
-    }
+    fn replace_child<T: Component>(
+        &mut self,
+        old: Entity,
+        new: Entity,
+        removal: Removal,
+    ) -> HierarchyResult<()> {
+        ensure_alive(self, old, Role::Child)?;
+        ensure_alive(self, new, Role::Child)?;
 
-    fn root<T: Component>(&self, child: Entity) -> Result<Entity> {
-        let mut cur = child;
-        loop {
-            match self.parent::<T>(cur) {
-                Ok(val) => cur = val,
-                Err(moss_hecs_schedule::Error::MissingComponent(_, _)) => break,
-                Err(val) => return Err(val),
-            }
+        let parent = self.parent::<T>(old)?;
+        if old == new {
+            return Ok(());
         }
 
-        Ok(cur)
+        if parent == new || self.ancestors::<T>(parent).any(|ancestor| ancestor == new) {
+            return Err(HierarchyError::Cycle {
+                ancestor: new,
+                descendant: old,
+            });
+        }
+
+        if self.try_get::<Child<T>>(new).is_ok() {
+            unlink_child::<T>(self, new)?;
+        }
+
+        link_child::<T>(self, new, parent, Position::After(old))?;
+        unlink_child::<T>(self, old)?;
+
+        if removal == Removal::Despawn {
+            self.despawn_all::<T>(old)?;
+        }
+
+        Ok(())
+    }
+
+    fn truncate_depth<T: Component>(
+        &mut self,
+        root: Entity,
+        max_depth: usize,
+        removal: Removal,
+    ) -> HierarchyResult<Vec<Entity>> {
+        ensure_alive(self, root, Role::Parent)?;
+
+        let mut frontier = vec![root];
+        for _ in 0..max_depth {
+            frontier = frontier
+                .iter()
+                .flat_map(|&entity| self.children::<T>(entity))
+                .collect();
+        }
+
+        let mut removed = Vec::new();
+        for parent in frontier {
+            let children = self.detach_children::<T>(parent)?;
+            if removal == Removal::Despawn {
+                for &child in &children {
+                    self.despawn_all::<T>(child)?;
+                }
+            }
+
+            removed.extend(children);
+        }
+
+        Ok(removed)
+    }
+
+    fn prune<T: Component, F: FnMut(&Self, Entity) -> bool>(
+        &mut self,
+        root: Entity,
+        removal: Removal,
+        mut predicate: F,
+    ) -> HierarchyResult<Vec<Entity>> {
+        ensure_alive(self, root, Role::Parent)?;
+
+        let mut matched = Vec::new();
+        let mut stack = self.children_vec::<T>(root);
+        stack.reverse();
+
+        while let Some(entity) = stack.pop() {
+            if predicate(self, entity) {
+                matched.push(entity);
+            } else {
+                let len = stack.len();
+                stack.extend(self.children::<T>(entity));
+                stack[len..].reverse();
+            }
+        }
+
+        for &entity in &matched {
+            match removal {
+                Removal::Detach => {
+                    unlink_child::<T>(self, entity)?;
+                }
+                Removal::Despawn => {
+                    self.despawn_all::<T>(entity)?;
+                }
+            }
+        }
+
+        Ok(matched)
+    }
+
+    fn merge_into<T: Component, C: DynamicBundle>(
+        &mut self,
+        roots: &[Entity],
+        components: C,
+    ) -> HierarchyResult<Entity> {
+        for &root in roots {
+            ensure_alive(self, root, Role::Child)?;
+        }
+
+        let mut seen = HashSet::new();
+        let added = roots
+            .iter()
+            .copied()
+            .filter(|&root| seen.insert(root))
+            .collect::<Vec<_>>();
+
+        // The policy may inspect the components of the new parent, so it is spawned before the
+        // roots are checked as a whole
+        let parent = self.spawn(components);
+        if let Err(err) = check_policy_all::<T>(self, &added, parent, added.len()) {
+            let _ = self.despawn(parent);
+            return Err(err);
+        }
+
+        for &root in roots {
+            self.attach::<T>(root, parent)?;
+        }
+
+        Ok(parent)
+    }
+
+    fn detach_subtree<T: Component>(
+        &mut self,
+        root: Entity,
+    ) -> HierarchyResult<DetachedSubtree<T>> {
+        ensure_alive(self, root, Role::Child)?;
+
+        let parent = match self.parent::<T>(root) {
+            Ok(parent) => parent,
+            Err(_) => return Ok(DetachedSubtree::new(root, None, 0)),
+        };
+
+        let index = self
+            .children::<T>(parent)
+            .position(|child| child == root)
+            .unwrap_or_default();
+
+        unlink_child::<T>(self, root)?;
+
+        Ok(DetachedSubtree::new(root, Some(parent), index))
+    }
+
+    fn reparent_children<T: Component>(
+        &mut self,
+        from: Entity,
+        to: Entity,
+    ) -> HierarchyResult<Vec<Entity>> {
+        ensure_alive(self, from, Role::Parent)?;
+        ensure_alive(self, to, Role::Parent)?;
+
+        let moved = self.children_vec::<T>(from);
+        if from == to || moved.is_empty() {
+            return Ok(moved);
+        }
+
+        if self.ancestors::<T>(to).any(|ancestor| ancestor == from) {
+            return Err(HierarchyError::Cycle {
+                ancestor: from,
+                descendant: to,
+            });
+        }
+
+        let children = self
+            .children::<T>(to)
+            .chain(moved.iter().copied())
+            .collect::<Vec<_>>();
+
+        // Relinking overwrites the links of every moved child, which leaves nothing pointing
+        // into the old sibling list of `from`.
+        relink_children::<T>(self, to, &children)?;
+        relink_children::<T>(self, from, &[])?;
+
+        Ok(moved)
+    }
+
+    fn set_children<T: Component>(
+        &mut self,
+        parent: Entity,
+        children: &[Entity],
+    ) -> HierarchyResult<Vec<Entity>> {
+        set_children_inner::<T>(self, parent, children, true)
+    }
+
+    fn mirror<From: Component, To: Component>(&mut self, root: Entity) -> HierarchyResult<()> {
+        ensure_alive(self, root, Role::Parent)?;
+
+        let nodes = std::iter::once(root)
+            .chain(self.descendants_depth_first::<From>(root))
+            .collect::<Vec<_>>();
+
+        let members = nodes.iter().copied().collect::<HashSet<_>>();
+        if let Some(ancestor) = self
+            .ancestors::<To>(root)
+            .find(|ancestor| members.contains(ancestor))
+        {
+            return Err(HierarchyError::Cycle {
+                ancestor,
+                descendant: root,
+            });
+        }
+
+        for node in nodes {
+            let children = self.children_vec::<From>(node);
+            self.set_children::<To>(node, &children)?;
+        }
+
+        Ok(())
+    }
+
+    fn collapse<T: Component>(&mut self, entity: Entity) -> HierarchyResult<Vec<Entity>> {
+        ensure_alive(self, entity, Role::Child)?;
+
+        let parent = self.parent::<T>(entity)?;
+        let spliced = self.children_vec::<T>(entity);
+
+        let children = self
+            .children::<T>(parent)
+            .flat_map(|child| {
+                if child == entity {
+                    spliced.clone()
+                } else {
+                    vec![child]
+                }
+            })
+            .collect::<Vec<_>>();
+
+        relink_children::<T>(self, parent, &children)?;
+        relink_children::<T>(self, entity, &[])?;
+        clear_child::<T>(self, entity)?;
+
+        Ok(spliced)
+    }
+
+    fn adopt_orphans<T: Component>(&mut self, new_parent: Entity) -> HierarchyResult<Vec<Entity>> {
+        ensure_alive(self, new_parent, Role::Parent)?;
+
+        let mut orphans = self
+            .query::<&Child<T>>()
+            .iter()
+            .filter(|(_, child)| !self.contains(child.parent))
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+        orphans.sort();
+
+        if let Some(&orphan) = orphans.iter().find(|&&orphan| {
+            orphan == new_parent || self.ancestors::<T>(new_parent).any(|e| e == orphan)
+        }) {
+            return Err(HierarchyError::Cycle {
+                ancestor: orphan,
+                descendant: new_parent,
+            });
+        }
+
+        let num_children = num_children::<T>(self, new_parent) + orphans.len();
+        check_policy_all::<T>(self, &orphans, new_parent, num_children)?;
+
+        // The links of an orphan point into the sibling list of its despawned parent, so they are
+        // cleared rather than unlinked before attaching
+        for &orphan in &orphans {
+            clear_child::<T>(self, orphan)?;
+            self.attach::<T>(orphan, new_parent)?;
+        }
+
+        Ok(orphans)
+    }
+
+    fn enable_root_registry<T: Component>(&mut self) -> HierarchyResult<()> {
+        if self.query::<&RootRegistry<T>>().iter().next().is_some() {
+            return Ok(());
+        }
+
+        let roots = self.roots::<T>()?.iter().map(|(root, _)| root).collect();
+        self.spawn((RootRegistry::<T>::new(roots),));
+
+        Ok(())
+    }
+
+    fn enable_child_storage<T: ChildStorage>(&mut self) -> HierarchyResult<()> {
+        if T::CHILD_LISTS {
+            enable_child_lists::<T>(self);
+        }
+
+        Ok(())
+    }
+
+    fn enable_policy<T: HierarchyPolicy>(&mut self) -> HierarchyResult<()> {
+        enable_policy::<T>(self);
+        Ok(())
+    }
+
+    fn set_edge_data<T: Component, D: Component>(
+        &mut self,
+        child: Entity,
+        data: D,
+    ) -> HierarchyResult<()> {
+        ensure_alive(self, child, Role::Child)?;
+        let parent = self.parent::<T>(child)?;
+        self.try_insert(child, (EdgeData::<T, D>::new(parent, data),))?;
+        Ok(())
+    }
+
+    fn observe<T: Component, F: FnMut(&HierarchyEvent) + Send + Sync + 'static>(
+        &mut self,
+        observer: F,
+    ) {
+        observe::<T>(self, Box::new(observer));
+    }
+
+    fn hierarchy_transaction<
+        T: Component,
+        R,
+        F: FnOnce(&mut HierarchyTransaction<T>) -> HierarchyResult<R>,
+    >(
+        &mut self,
+        f: F,
+    ) -> HierarchyResult<R> {
+        let mut tx = HierarchyTransaction::new(self);
+        let result = f(&mut tx);
+        if result.is_err() {
+            tx.rollback();
+        }
+
+        result
+    }
+}
+
+impl<W: GenericWorld> Hierarchy for W {
+    fn parent<T: Component>(&self, child: Entity) -> Result<Entity> {
+        self.try_get::<Child<T>>(child).map(|child| child.parent)
+    }
+
+    fn first_child<T: Component>(&self, parent: Entity) -> Result<Entity> {
+        self.try_get::<Parent<T>>(parent)?.first_child(self)
+    }
+
+    fn last_child<T: Component>(&self, parent: Entity) -> Result<Entity> {
+        self.try_get::<Parent<T>>(parent)
+            .map(|parent| parent.last_child)
+    }
+
+    fn root<T: Component>(&self, child: Entity) -> Result<Entity> {
+        let mut cur = child;
+        loop {
+            match self.parent::<T>(cur) {
+                Ok(val) => cur = val,
+                Err(moss_hecs_schedule::Error::MissingComponent(_, _)) => break,
+                Err(val) => return Err(val),
+            }
+        }
+
+        Ok(cur)
     }
 
     fn children<T: Component>(&self, parent: Entity) -> ChildrenIter<T> {
+        if let Ok(list) = self.try_get::<ChildList<T>>(parent) {
+            return ChildrenIter::from_list(list);
+        }
+
         self.try_get::<Parent<T>>(parent)
             .and_then(|parent| {
                 let first_child = parent.first_child(self)?;
@@ -233,14 +1473,185 @@ impl<W: GenericWorld> Hierarchy for W {
             })
     }
 
+    fn children_with<T: Component, C: Component>(&self, parent: Entity) -> ChildrenWithIter<T, C> {
+        ChildrenWithIter::new(self, self.children::<T>(parent))
+    }
+
+    fn children_with_edge_data<T: Component, D: Component>(
+        &self,
+        parent: Entity,
+    ) -> ChildrenWithEdgeDataIter<Self, T, D> {
+        ChildrenWithEdgeDataIter::new(self, parent, self.children::<T>(parent))
+    }
+
+    fn children_by_edge_key<T: Component, D: Component + Ord>(
+        &self,
+        parent: Entity,
+    ) -> ChildrenByEdgeKeyIter {
+        ChildrenByEdgeKeyIter::new::<T, D, _>(self, parent, self.children::<T>(parent))
+    }
+
+    fn child_in_slot<T: Component, K: Component + Eq>(
+        &self,
+        parent: Entity,
+        key: &K,
+    ) -> Option<Entity> {
+        self.children_with_edge_data::<T, K>(parent)
+            .find(|(_, slot)| ***slot == *key)
+            .map(|(child, _)| child)
+    }
+
+    fn children_vec<T: Component>(&self, parent: Entity) -> Vec<Entity> {
+        if let Ok(list) = self.try_get::<ChildList<T>>(parent) {
+            return list.as_slice().to_vec();
+        }
+
+        let (num_children, last_child) = match self.try_get::<Parent<T>>(parent) {
+            Ok(data) => (data.num_children, data.last_child),
+            Err(_) => return Vec::new(),
+        };
+
+        let mut query = match self.try_query::<&Child<T>>() {
+            Ok(query) => query,
+            Err(_) => return Vec::new(),
+        };
+        let view = query.view();
+
+        let mut children = Vec::with_capacity(num_children);
+        let mut cur = match view.get(last_child) {
+            Some(last) => last.next,
+            None => return children,
+        };
+
+        while children.len() < num_children {
+            match view.get(cur) {
+                Some(data) => {
+                    children.push(cur);
+                    cur = data.next;
+                }
+                None => break,
+            }
+        }
+
+        children
+    }
+
+    #[cfg(feature = "parallel")]
+    fn par_children<T: Component>(&self, parent: Entity) -> rayon::vec::IntoIter<Entity> {
+        use rayon::iter::IntoParallelIterator;
+
+        self.children_vec::<T>(parent).into_par_iter()
+    }
+
+    fn children_alive<T: Component>(&self, parent: Entity) -> ChildrenAliveIter {
+        ChildrenAliveIter::new::<T, _>(self, parent)
+    }
+
     fn ancestors<T: Component>(&self, child: Entity) -> AncestorIter<T> {
         AncestorIter::new(self, child)
     }
 
+    fn ancestors_with<T: Component, C: Component>(
+        &self,
+        child: Entity,
+    ) -> AncestorsWithIter<Self, T, C> {
+        AncestorsWithIter::new(self, self.ancestors::<T>(child))
+    }
+
+    fn ancestors_until<T: Component, F: FnMut(&Self, Entity) -> bool>(
+        &self,
+        child: Entity,
+        predicate: F,
+    ) -> AncestorsUntilIter<Self, T, F> {
+        AncestorsUntilIter::new(self, self.ancestors::<T>(child), predicate)
+    }
+
+    fn ancestors_from_root<T: Component>(&self, child: Entity) -> AncestorsFromRootIter {
+        AncestorsFromRootIter::new(self.ancestors::<T>(child))
+    }
+
+    fn path_between<T: Component>(&self, a: Entity, b: Entity) -> Option<Vec<Entity>> {
+        let up = std::iter::once(a)
+            .chain(self.ancestors::<T>(a))
+            .collect::<Vec<_>>();
+        let down = std::iter::once(b)
+            .chain(self.ancestors::<T>(b))
+            .collect::<Vec<_>>();
+
+        // Strip the common ancestors above the lowest common ancestor
+        let common = up
+            .iter()
+            .rev()
+            .zip(down.iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        if common == 0 {
+            return None;
+        }
+
+        let mut path = up[..=up.len() - common].to_vec();
+        path.extend(down[..down.len() - common].iter().rev());
+        Some(path)
+    }
+
+    fn next_in_tree<T: Component>(&self, entity: Entity, wrap: bool) -> Option<Entity> {
+        if let Some(last) = last_child_of::<T, _>(self, entity) {
+            return Some(self.try_get::<Child<T>>(last).ok()?.next);
+        }
+
+        let mut cur = entity;
+        while let Ok(child) = self.try_get::<Child<T>>(cur) {
+            let (parent, next) = (child.parent, child.next);
+            if last_child_of::<T, _>(self, parent) != Some(cur) {
+                return Some(next);
+            }
+
+            cur = parent;
+        }
+
+        if wrap {
+            Some(cur)
+        } else {
+            None
+        }
+    }
+
+    fn prev_in_tree<T: Component>(&self, entity: Entity, wrap: bool) -> Option<Entity> {
+        let (parent, prev) = match self.try_get::<Child<T>>(entity) {
+            Ok(child) => (child.parent, child.prev),
+            Err(_) if wrap => return Some(last_descendant::<T, _>(self, entity)),
+            Err(_) => return None,
+        };
+
+        // The first child links back to the last one
+        if last_child_of::<T, _>(self, parent) == Some(prev) {
+            Some(parent)
+        } else {
+            Some(last_descendant::<T, _>(self, prev))
+        }
+    }
+
+    fn flatten<T: Component, F: FnMut(&Self, Entity) -> bool>(
+        &self,
+        root: Entity,
+        skip: F,
+    ) -> FlattenIter<Self, T, F> {
+        FlattenIter::new(self, root, skip)
+    }
+
     fn descendants_depth_first<T: Component>(&self, root: Entity) -> DepthFirstIterator<T> {
         DepthFirstIterator::new(self, root)
     }
 
+    fn descendants_depth_first_in<'a, 'c, T: Component>(
+        &'a self,
+        root: Entity,
+        cache: &'c mut TraversalCache,
+    ) -> DepthFirstIn<'a, 'c, T> {
+        DepthFirstIn::new(self, root, cache)
+    }
+
     /// Traverse the tree breadth first. Iterator does not include the child itself.
     fn descendants_breadth_first<T: Component>(
         &self,
@@ -249,6 +1660,165 @@ impl<W: GenericWorld> Hierarchy for W {
         BreadthFirstIterator::new(self, root)
     }
 
+    fn descendants_breadth_first_in<'a, 'c, T: Component>(
+        &'a self,
+        root: Entity,
+        cache: &'c mut TraversalCache,
+    ) -> BreadthFirstIn<'a, 'c, Self, T> {
+        BreadthFirstIn::new(self, root, cache)
+    }
+
+    fn descendants_in_both<A: Component, B: Component>(
+        &self,
+        root: Entity,
+    ) -> MembershipFilterIter<A, B> {
+        MembershipFilterIter::new(self, root, true)
+    }
+
+    fn descendants_not_in<A: Component, B: Component>(
+        &self,
+        root: Entity,
+    ) -> MembershipFilterIter<A, B> {
+        MembershipFilterIter::new(self, root, false)
+    }
+
+    fn descendants_excluding<T: Component>(
+        &self,
+        root: Entity,
+        excluded: Entity,
+    ) -> DescendantsExcludingIter<T> {
+        DescendantsExcludingIter::new(self, root, excluded)
+    }
+
+    fn descendants_depth_first_sorted<T: Component>(
+        &self,
+        root: Entity,
+    ) -> SortedDepthFirstIter<Self, T> {
+        SortedDepthFirstIter::new(self, root, sort_by_id)
+    }
+
+    fn descendants_depth_first_sorted_by_key<T: Component, K: Component + Ord>(
+        &self,
+        root: Entity,
+    ) -> SortedDepthFirstIter<Self, T> {
+        SortedDepthFirstIter::new(self, root, sort_by_component::<Self, K>)
+    }
+
+    fn descendants_z_ordered<T: Component, Z: Component + PartialOrd>(
+        &self,
+        root: Entity,
+    ) -> SortedDepthFirstIter<Self, T> {
+        SortedDepthFirstIter::new(self, root, sort_by_layer::<Self, Z>)
+    }
+
+    fn layers<T: Component>(&self, root: Entity) -> LayersIter<Self, T> {
+        LayersIter::new(self, root)
+    }
+
+    fn stats<T: Component>(&self, root: Entity) -> TreeStats {
+        TreeStats::new::<T, _>(self, root)
+    }
+
+    fn entities_by_depth<T: Component>(&self, root: Entity) -> Vec<Vec<Entity>> {
+        let mut buckets = DepthBuckets::new();
+        buckets.fill::<T, _>(self, root);
+        buckets.into_vec()
+    }
+
+    fn entities_by_depth_in<'b, T: Component>(
+        &self,
+        root: Entity,
+        buckets: &'b mut DepthBuckets,
+    ) -> &'b [Vec<Entity>] {
+        buckets.fill::<T, _>(self, root);
+        buckets.as_slice()
+    }
+
+    fn split_subtrees<T: Component>(&self, root: Entity, approx_chunks: usize) -> SubtreeSplit {
+        SubtreeSplit::new::<T, _>(self, root, approx_chunks)
+    }
+
+    fn tree_hash<T: Component, H: Hasher + Default, F: FnMut(&Self, Entity) -> u64>(
+        &self,
+        root: Entity,
+        node_hash: F,
+    ) -> u64 {
+        tree_hash::<T, H, _, _>(self, root, node_hash)
+    }
+
+    fn find_descendant<T: Component, F: FnMut(&Self, Entity) -> bool>(
+        &self,
+        root: Entity,
+        mut predicate: F,
+    ) -> Option<Entity> {
+        self.descendants_depth_first::<T>(root)
+            .find(|&entity| predicate(self, entity))
+    }
+
+    fn hit_test<T: Component, F: FnMut(&Self, Entity) -> bool>(
+        &self,
+        root: Entity,
+        mut predicate: F,
+    ) -> Option<Entity> {
+        if !predicate(self, root) {
+            return None;
+        }
+
+        let mut hit = root;
+        'descend: loop {
+            let (num_children, last_child) = match self.try_get::<Parent<T>>(hit) {
+                Ok(parent) => (parent.num_children, parent.last_child),
+                Err(_) => return Some(hit),
+            };
+
+            let mut cur = last_child;
+            for _ in 0..num_children {
+                if predicate(self, cur) {
+                    hit = cur;
+                    continue 'descend;
+                }
+
+                cur = match self.try_get::<Child<T>>(cur) {
+                    Ok(child) => child.prev,
+                    Err(_) => break,
+                };
+            }
+
+            return Some(hit);
+        }
+    }
+
+    fn find_ancestor<T: Component, F: FnMut(&Self, Entity) -> bool>(
+        &self,
+        child: Entity,
+        mut predicate: F,
+    ) -> Option<Entity> {
+        self.ancestors::<T>(child)
+            .find(|&entity| predicate(self, entity))
+    }
+
+    fn bubble<T: Component, E, F: FnMut(&Self, Entity, &mut E) -> bool>(
+        &self,
+        target: Entity,
+        event: &mut E,
+        mut handler: F,
+    ) -> Option<Entity> {
+        std::iter::once(target)
+            .chain(self.ancestors::<T>(target))
+            .find(|&entity| handler(self, entity, event))
+    }
+
+    fn capture<T: Component, E, F: FnMut(&Self, Entity, &mut E) -> bool>(
+        &self,
+        target: Entity,
+        event: &mut E,
+        mut handler: F,
+    ) -> Option<Entity> {
+        self.ancestors_from_root::<T>(target)
+            .chain(std::iter::once(target))
+            .find(|&entity| handler(self, entity, event))
+    }
+
     fn visit<T: Component, F: Fn(&Self, Entity) -> bool + Component>(
         &self,
         root: Entity,
@@ -257,9 +1827,474 @@ impl<W: GenericWorld> Hierarchy for W {
         DepthFirstVisitor::new(self, root, accept)
     }
 
+    fn descendants_with<'a, 'v, 'q, T: Component, Q: Query>(
+        &'a self,
+        root: Entity,
+        view: &'v View<'q, Q>,
+    ) -> DescendantsWithIter<'a, 'v, 'q, T, Q> {
+        DescendantsWithIter::new(self, root, view)
+    }
+
     fn roots<T: Component>(&self) -> Result<QueryBorrow<Without<&Parent<T>, &Child<T>>>> {
         Ok(self.try_query::<&Parent<T>>()?.without::<&Child<T>>())
     }
+
+    fn registered_roots<T: Component>(&self) -> Option<Vec<Entity>> {
+        let mut query = self.try_query::<&RootRegistry<T>>().ok()?;
+        let roots = query
+            .iter()
+            .next()
+            .map(|(_, registry)| registry.iter().collect());
+        roots
+    }
+
+    fn node<T: Component>(&self, entity: Entity) -> Node<Self, T> {
+        Node::new(self, entity)
+    }
+
+    fn edges<T: Component>(&self) -> Result<Edges<T>> {
+        Ok(Edges::new(self.try_query::<&Child<T>>()?))
+    }
+
+    fn members<T: Component>(&self) -> Result<Members<T>> {
+        Ok(Members::new(
+            self.roots::<T>()?,
+            self.try_query::<&Child<T>>()?,
+        ))
+    }
+
+    fn parents_iter<T: Component>(&self) -> Result<ParentPairs<T>> {
+        Ok(ParentPairs::new(
+            self.roots::<T>()?,
+            self.try_query::<&Child<T>>()?,
+        ))
+    }
+}
+
+/// Despawns `root` and its descendants, replacing the contents of `despawned` with the despawned
+/// entities in depth first order. The traversal keeps its stack on the heap rather than recursing,
+/// so arbitrarily deep trees can not overflow the call stack.
+fn despawn_tree<T: Component>(
+    frame: &mut Frame,
+    root: Entity,
+    despawned: &mut Vec<Entity>,
+) -> HierarchyResult<()> {
+    ensure_alive(frame, root, Role::Parent)?;
+
+    // Detach from parent if necessary
+    let former_parent = frame.parent::<T>(root).ok();
+    if former_parent.is_some() {
+        unlink_child::<T>(frame, root)?;
+    }
+
+    // Children despawned without being detached are skipped rather than ending the traversal
+    despawned.clear();
+    let mut stack = vec![root];
+    while let Some(entity) = stack.pop() {
+        despawned.push(entity);
+        let children = frame.children_alive::<T>(entity).collect::<Vec<_>>();
+        stack.extend(children.into_iter().rev());
+    }
+
+    for &entity in despawned.iter() {
+        frame
+            .despawn(entity)
+            .map_err(|_| HierarchyError::NoSuchEntity {
+                entity,
+                which: Role::Child,
+            })?;
+    }
+
+    update_roots::<T>(frame, &[root]);
+
+    #[cfg(all(feature = "strict-checks", debug_assertions))]
+    if let Some(former_parent) = former_parent {
+        strict::check_ring::<T>(frame, "despawn_all", former_parent);
+    }
+
+    notify::<T>(
+        frame,
+        HierarchyEvent::Despawned {
+            root,
+            entities: despawned,
+        },
+    );
+    Ok(())
+}
+
+/// Prepares attaching `child` next to `sibling` by detaching `child` from its current parent.
+/// Returns the parent of `sibling`. Does not detach anything if `child` and `sibling` are the
+/// same.
+fn unlink_for_sibling<T: Component>(
+    frame: &mut Frame,
+    child: Entity,
+    sibling: Entity,
+) -> HierarchyResult<Entity> {
+    ensure_alive(frame, child, Role::Child)?;
+    ensure_alive(frame, sibling, Role::Child)?;
+
+    let parent = frame.parent::<T>(sibling)?;
+    if child == sibling {
+        return Ok(parent);
+    }
+
+    if parent == child
+        || frame
+            .ancestors::<T>(parent)
+            .any(|ancestor| ancestor == child)
+    {
+        return Err(HierarchyError::Cycle {
+            ancestor: child,
+            descendant: sibling,
+        });
+    }
+
+    check_policy::<T>(frame, child, parent)?;
+
+    if frame.try_get::<Child<T>>(child).is_ok() {
+        unlink_child::<T>(frame, child)?;
+    }
+
+    Ok(parent)
+}
+
+/// Returns the last child of `parent`, or `None` if it has no children.
+fn last_child_of<T: Component, W: GenericWorld>(frame: &W, parent: Entity) -> Option<Entity> {
+    frame
+        .try_get::<Parent<T>>(parent)
+        .ok()
+        .filter(|parent| parent.num_children > 0)
+        .map(|parent| parent.last_child)
+}
+
+/// Returns the last entity of the tree below `root` in depth first order, following the last
+/// children down from `root`.
+fn last_descendant<T: Component, W: GenericWorld>(frame: &W, root: Entity) -> Entity {
+    let mut cur = root;
+    while let Some(last) = last_child_of::<T, _>(frame, cur) {
+        cur = last;
+    }
+
+    cur
+}
+
+/// Fails with [HierarchyError::NoSuchEntity] if `entity` does not exist.
+pub(crate) fn ensure_alive(frame: &Frame, entity: Entity, which: Role) -> HierarchyResult<()> {
+    if frame.contains(entity) {
+        Ok(())
+    } else {
+        Err(HierarchyError::NoSuchEntity { entity, which })
+    }
+}
+
+/// Returns the parent of `child` along with its index among the children of the parent, or `None`
+/// if `child` is not attached.
+pub(crate) fn slot_of<T: Component, W: GenericWorld + Hierarchy>(
+    frame: &W,
+    child: Entity,
+) -> Option<(Entity, usize)> {
+    let parent = frame.parent::<T>(child).ok()?;
+    let index = frame
+        .children::<T>(parent)
+        .position(|sibling| sibling == child)
+        .unwrap_or_default();
+    Some((parent, index))
+}
+
+/// What to do with a subtree which is removed from the hierarchy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Removal {
+    /// Detach the subtree, keeping it intact as a tree of its own
+    Detach,
+    /// Despawn the subtree root and all its descendants
+    Despawn,
+}
+
+/// Where to link a child among its siblings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Position {
+    /// Link as the first child
+    Front,
+    /// Link as the last child
+    Back,
+    /// Link directly after an existing child
+    After(Entity),
+}
+
+/// Links `child` into the children of `parent` at `position`. The child must not currently be
+/// linked into any sibling list of `T`.
+pub(crate) fn link_child<T: Component>(
+    frame: &mut Frame,
+    child: Entity,
+    parent: Entity,
+    position: Position,
+) -> Result<()> {
+    link_ring::<T>(frame, child, parent, position)?;
+    list_linked::<T>(frame, child, parent, position);
+
+    #[cfg(feature = "child-index")]
+    refresh_child_indices::<T>(frame, parent)?;
+
+    update_roots::<T>(frame, &[child, parent]);
+
+    Ok(())
+}
+
+fn link_ring<T: Component>(
+    frame: &mut Frame,
+    child: Entity,
+    parent: Entity,
+    position: Position,
+) -> Result<()> {
+    let last = frame
+        .try_get::<Parent<T>>(parent)
+        .ok()
+        .filter(|p| p.num_children > 0)
+        .map(|p| p.last_child);
+
+    let last = match last {
+        Some(last) => last,
+        None => {
+            // Parent component didn't exist, or has no children
+            frame.try_insert(parent, (Parent::<T>::new(1, child),))?;
+            frame.try_insert(child, (Child::<T>::new(parent, child, child),))?;
+            return Ok(());
+        }
+    };
+
+    // Since the children form a ring, inserting after the last child without moving the tail
+    // makes `child` the first child.
+    let prev = match position {
+        Position::Front | Position::Back => last,
+        Position::After(sibling) => sibling,
+    };
+    let next = frame.try_get::<Child<T>>(prev)?.next;
+
+    frame.try_get_mut::<Child<T>>(prev)?.next = child;
+    frame.try_get_mut::<Child<T>>(next)?.prev = child;
+    frame.try_insert(child, (Child::<T>::new(parent, next, prev),))?;
+
+    {
+        let mut data = frame.try_get_mut::<Parent<T>>(parent)?;
+        data.num_children += 1;
+        if position == Position::Back || position == Position::After(last) {
+            data.last_child = child;
+        }
+    }
+
+    Ok(())
+}
+
+/// Attaches `child` to `parent` as the child at `index`, or as the last child if `parent` has
+/// fewer children, checking the entities and the enabled policy of `T` like
+/// [HierarchyMut::attach], and notifies the observers of `T`. Backs the operations which place a
+/// child at an index.
+pub(crate) fn attach_at<T: Component>(
+    frame: &mut Frame,
+    child: Entity,
+    parent: Entity,
+    index: usize,
+) -> HierarchyResult<()> {
+    ensure_alive(frame, child, Role::Child)?;
+    ensure_alive(frame, parent, Role::Parent)?;
+
+    #[cfg(all(feature = "strict-checks", debug_assertions))]
+    strict::check_acyclic::<T>(frame, "attach_at", child, parent);
+
+    check_policy::<T>(frame, child, parent)?;
+    link_at::<T>(frame, child, parent, index)?;
+
+    #[cfg(all(feature = "strict-checks", debug_assertions))]
+    strict::check_ring::<T>(frame, "attach_at", parent);
+
+    notify::<T>(frame, HierarchyEvent::Attached { child, parent });
+    Ok(())
+}
+
+/// Makes `children` the children of `parent` like [HierarchyMut::set_children]. The enabled
+/// policy of `T` is only checked for the newly added children if `checked` is set, otherwise it
+/// is bypassed to return to an earlier state.
+pub(crate) fn set_children_inner<T: Component>(
+    frame: &mut Frame,
+    parent: Entity,
+    children: &[Entity],
+    checked: bool,
+) -> HierarchyResult<Vec<Entity>> {
+    ensure_alive(frame, parent, Role::Parent)?;
+
+    let mut seen = HashSet::new();
+    let children = children
+        .iter()
+        .copied()
+        .filter(|&child| seen.insert(child))
+        .collect::<Vec<_>>();
+
+    for &child in &children {
+        ensure_alive(frame, child, Role::Child)?;
+
+        if child == parent
+            || frame
+                .ancestors::<T>(parent)
+                .any(|ancestor| ancestor == child)
+        {
+            return Err(HierarchyError::Cycle {
+                ancestor: child,
+                descendant: parent,
+            });
+        }
+    }
+
+    if checked {
+        let added = children
+            .iter()
+            .copied()
+            .filter(|&child| !matches!(frame.try_get::<Child<T>>(child), Ok(data) if data.parent == parent))
+            .collect::<Vec<_>>();
+        check_policy_all::<T>(frame, &added, parent, children.len())?;
+    }
+
+    let detached = frame
+        .children::<T>(parent)
+        .filter(|child| !seen.contains(child))
+        .collect::<Vec<_>>();
+
+    // The links of the old children are either overwritten by relinking or cleared, so only
+    // entries moved from other parents need to be unlinked properly.
+    for &child in &detached {
+        clear_child::<T>(frame, child)?;
+    }
+
+    for &child in &children {
+        let moved = matches!(frame.try_get::<Child<T>>(child), Ok(data) if data.parent != parent);
+        if moved {
+            unlink_child::<T>(frame, child)?;
+        }
+    }
+
+    relink_children::<T>(frame, parent, &children)?;
+
+    Ok(detached)
+}
+
+/// Attaches `child` to `parent` as the child at `index`, or as the last child if `parent` has
+/// fewer children. If `child` is already attached it is detached first.
+pub(crate) fn link_at<T: Component>(
+    frame: &mut Frame,
+    child: Entity,
+    parent: Entity,
+    index: usize,
+) -> Result<()> {
+    if frame.try_get::<Child<T>>(child).is_ok() {
+        unlink_child::<T>(frame, child)?;
+    }
+
+    let position = match index.checked_sub(1) {
+        None => Position::Front,
+        Some(prev) => frame
+            .children::<T>(parent)
+            .nth(prev)
+            .map(Position::After)
+            .unwrap_or(Position::Back),
+    };
+
+    link_child::<T>(frame, child, parent, position)
+}
+
+/// Unlinks `child` from the children of its parent, removing its `Child` component. The `Parent`
+/// component of the parent is removed along with its last child. Returns the parent.
+pub(crate) fn unlink_child<T: Component>(frame: &mut Frame, child: Entity) -> Result<Entity> {
+    let (parent, prev, next) = {
+        let data = frame.try_get::<Child<T>>(child)?;
+        (data.parent, data.prev, data.next)
+    };
+
+    if prev != child {
+        frame.try_get_mut::<Child<T>>(prev)?.next = next;
+        frame.try_get_mut::<Child<T>>(next)?.prev = prev;
+    }
+
+    frame.try_remove_one::<Child<T>>(child)?;
+    #[cfg(feature = "child-index")]
+    let _ = frame.remove_one::<ChildIndex<T>>(child);
+
+    let empty = {
+        let mut data = frame.try_get_mut::<Parent<T>>(parent)?;
+        data.num_children -= 1;
+        if data.last_child == child {
+            data.last_child = prev;
+        }
+        data.num_children == 0
+    };
+
+    if empty {
+        frame.try_remove_one::<Parent<T>>(parent)?;
+    }
+    list_unlinked::<T>(frame, child, parent);
+
+    #[cfg(feature = "child-index")]
+    if !empty {
+        refresh_child_indices::<T>(frame, parent)?;
+    }
+
+    update_roots::<T>(frame, &[child, parent]);
+
+    Ok(parent)
+}
+
+/// Removes the `Child` component of `child` without touching the sibling list it links into.
+pub(crate) fn clear_child<T: Component>(frame: &mut Frame, child: Entity) -> Result<()> {
+    frame.try_remove_one::<Child<T>>(child)?;
+    #[cfg(feature = "child-index")]
+    let _ = frame.remove_one::<ChildIndex<T>>(child);
+
+    update_roots::<T>(frame, &[child]);
+
+    Ok(())
+}
+
+/// Relinks the children of `parent` into a ring following the order of `children`. Every entity
+/// in `children` must either be a child of `parent`, or not be linked into any sibling list of `T`.
+pub(crate) fn relink_children<T: Component>(
+    frame: &mut Frame,
+    parent: Entity,
+    children: &[Entity],
+) -> Result<()> {
+    let n = children.len();
+    if n == 0 {
+        let _ = frame.remove_one::<Parent<T>>(parent);
+        list_relinked::<T>(frame, parent, children);
+        update_roots::<T>(frame, &[parent]);
+        return Ok(());
+    }
+
+    for (i, &child) in children.iter().enumerate() {
+        let prev = children[(i + n - 1) % n];
+        let next = children[(i + 1) % n];
+        frame.try_insert(child, (Child::<T>::new(parent, next, prev),))?;
+    }
+
+    frame.try_insert(parent, (Parent::<T>::new(n, children[n - 1]),))?;
+    list_relinked::<T>(frame, parent, children);
+
+    #[cfg(feature = "child-index")]
+    refresh_child_indices::<T>(frame, parent)?;
+
+    update_roots::<T>(frame, children);
+    update_roots::<T>(frame, &[parent]);
+
+    Ok(())
+}
+
+/// Updates the [ChildIndex] of every child of `parent`.
+#[cfg(feature = "child-index")]
+pub(crate) fn refresh_child_indices<T: Component>(frame: &mut Frame, parent: Entity) -> Result<()> {
+    let children = frame.children_vec::<T>(parent);
+
+    for (index, child) in children.into_iter().enumerate() {
+        frame.try_insert(child, (ChildIndex::<T>::new(index as u32),))?;
+    }
+
+    Ok(())
 }
 
 trait WorldExt {
@@ -280,4 +2315,14 @@ impl WorldExt for Frame {
 }
 
 /// A query for defininig a compatible subworld for [Hierarchy]
+///
+/// Grants the shared access to `Parent<T>` and `Child<T>` which the traversals of [Hierarchy]
+/// need, which also covers queries for `Option<&Child<T>>` or filtering on `Child<T>`, such as
+/// [Hierarchy::roots]. Methods reading another component, such as
+/// [Hierarchy::children_with], additionally need access to that component.
 pub type HierarchyQuery<'a, T> = (&'a Parent<T>, &'a Child<T>);
+
+/// A query for defining a subworld in which every read method of [Hierarchy] for tree `T` is
+/// available, which in addition to [HierarchyQuery] grants access to the [RootRegistry] used by
+/// [Hierarchy::registered_roots].
+pub type HierarchyQueryFull<'a, T> = (&'a Parent<T>, &'a Child<T>, &'a RootRegistry<T>);
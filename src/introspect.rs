@@ -0,0 +1,108 @@
+use std::any::{type_name, TypeId};
+
+use moss_hecs::{Component, Entity, Frame};
+
+use crate::{Child, Parent};
+
+/// How an entity participates in one registered hierarchy, as reported by
+/// [HierarchyRegistry::hierarchies_of].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Membership {
+    /// The name the hierarchy was registered with
+    pub name: &'static str,
+    /// The type id of the marker type of the hierarchy
+    pub type_id: TypeId,
+    /// The parent of the entity, or `None` if it is a root
+    pub parent: Option<Entity>,
+    /// The number of children of the entity
+    pub num_children: usize,
+}
+
+struct Registered {
+    name: &'static str,
+    type_id: TypeId,
+    membership: fn(&Frame, Entity) -> Option<(Option<Entity>, usize)>,
+}
+
+/// A list of the hierarchy marker types in use, which allows asking which hierarchies an entity
+/// belongs to without knowing the markers at the call site. Useful for debugging when several
+/// hierarchies coexist.
+#[derive(Default)]
+pub struct HierarchyRegistry {
+    hierarchies: Vec<Registered>,
+}
+
+impl HierarchyRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers hierarchy `T` under `name`. Registering the same marker again only replaces its
+    /// name.
+    pub fn register<T: Component>(&mut self, name: &'static str) -> &mut Self {
+        let type_id = TypeId::of::<T>();
+        match self.hierarchies.iter_mut().find(|h| h.type_id == type_id) {
+            Some(registered) => registered.name = name,
+            None => self.hierarchies.push(Registered {
+                name,
+                type_id,
+                membership: membership::<T>,
+            }),
+        }
+
+        self
+    }
+
+    /// Registers hierarchy `T` under the name of its type.
+    pub fn register_type<T: Component>(&mut self) -> &mut Self {
+        self.register::<T>(type_name::<T>())
+    }
+
+    /// Returns the registered hierarchies which `entity` has a `Parent` or `Child` component of,
+    /// in the order they were registered.
+    pub fn hierarchies_of(&self, frame: &Frame, entity: Entity) -> Vec<Membership> {
+        self.hierarchies
+            .iter()
+            .filter_map(|registered| {
+                let (parent, num_children) = (registered.membership)(frame, entity)?;
+                Some(Membership {
+                    name: registered.name,
+                    type_id: registered.type_id,
+                    parent,
+                    num_children,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the names of the registered hierarchies in the order they were registered.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.hierarchies.iter().map(|registered| registered.name)
+    }
+}
+
+impl std::fmt::Debug for HierarchyRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+/// Returns the parent and child count of `entity` in hierarchy `T`, or `None` if it is not a
+/// member.
+fn membership<T: Component>(frame: &Frame, entity: Entity) -> Option<(Option<Entity>, usize)> {
+    let parent = frame
+        .get::<&Child<T>>(entity)
+        .ok()
+        .map(|child| child.parent);
+    let num_children = frame
+        .get::<&Parent<T>>(entity)
+        .ok()
+        .map(|parent| parent.num_children);
+
+    if parent.is_none() && num_children.is_none() {
+        return None;
+    }
+
+    Some((parent, num_children.unwrap_or_default()))
+}
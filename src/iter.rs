@@ -1,17 +1,27 @@
-use std::{collections::VecDeque, marker::PhantomData};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    iter::FusedIterator,
+    marker::PhantomData,
+};
 
-use moss_hecs::{Component, Entity, QueryBorrow};
+use moss_hecs::{
+    Component, Entity, Query, QueryBorrow, QueryItem, QueryShared, Ref, View, Without,
+};
 use moss_hecs_schedule::GenericWorld;
 use smallvec::{smallvec, SmallVec};
 
-use crate::{Child, Hierarchy, Parent};
+use crate::{Child, ChildList, Hierarchy, Parent};
 
 const STACK_SIZE: usize = 64;
 
 /// Iterates children along with Query `Q`. Children who do not satisfy `Q` will be skipped.
 /// Count is known in advanced and will not fold iterator.
 pub struct ChildrenIter<'a, T: Component> {
-    query: QueryBorrow<'a, &'a Child<T>>,
+    /// `None` if the world lacks access to `Child<T>`
+    query: Option<QueryBorrow<'a, &'a Child<T>>>,
+    /// The [ChildList] of the parent, iterated instead of the sibling ring if present
+    list: Option<Ref<'a, ChildList<T>>>,
     remaining: usize,
     current: Option<Entity>,
     marker: PhantomData<T>,
@@ -23,13 +33,25 @@ impl<'a, T: Component> ChildrenIter<'a, T> {
         num_children: usize,
         current: Option<Entity>,
     ) -> Self {
+        let query = frame.try_query().ok();
         Self {
-            query: frame.try_query().unwrap(),
-            remaining: num_children,
+            remaining: if query.is_some() { num_children } else { 0 },
+            query,
+            list: None,
             current,
             marker: PhantomData,
         }
     }
+
+    pub(crate) fn from_list(list: Ref<'a, ChildList<T>>) -> Self {
+        Self {
+            query: None,
+            remaining: list.len(),
+            list: Some(list),
+            current: None,
+            marker: PhantomData,
+        }
+    }
 }
 
 impl<'a, T> Iterator for ChildrenIter<'a, T>
@@ -43,12 +65,24 @@ where
             return None;
         }
 
-        self.remaining -= 1;
+        if let Some(list) = &self.list {
+            let child = list.as_slice()[list.len() - self.remaining];
+            self.remaining -= 1;
+            return Some(child);
+        }
 
         let current = self.current?;
-        let view = self.query.view();
-        let data = view.get(current)?;
+        let view = self.query.as_mut()?.view();
+        let data = match view.get(current) {
+            Some(data) => data,
+            None => {
+                // A broken sibling ring ends the iteration, keep the length truthful
+                self.remaining = 0;
+                return None;
+            }
+        };
 
+        self.remaining -= 1;
         self.current = Some(data.next);
         Some(current)
     }
@@ -65,8 +99,233 @@ where
     }
 }
 
+impl<'a, T: Component> ExactSizeIterator for ChildrenIter<'a, T> {}
+
+impl<'a, T: Component> FusedIterator for ChildrenIter<'a, T> {}
+
+/// Iterates the children of a parent which have component `C`. See [Hierarchy::children_with].
+pub struct ChildrenWithIter<'a, T: Component, C: Component> {
+    children: ChildrenIter<'a, T>,
+    /// `None` if the world lacks access to `C`
+    query: Option<QueryBorrow<'a, &'a C>>,
+}
+
+impl<'a, T: Component, C: Component> ChildrenWithIter<'a, T, C> {
+    pub(crate) fn new<W: GenericWorld>(frame: &'a W, children: ChildrenIter<'a, T>) -> Self {
+        Self {
+            children,
+            query: frame.try_query().ok(),
+        }
+    }
+}
+
+impl<'a, T: Component, C: Component> Iterator for ChildrenWithIter<'a, T, C> {
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let view = self.query.as_mut()?.view();
+        self.children.find(|&child| view.get(child).is_some())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.children.size_hint().1)
+    }
+}
+
+impl<'a, T: Component, C: Component> FusedIterator for ChildrenWithIter<'a, T, C> {}
+
+/// Iterates the children of a parent which are still alive, skipping children which have been
+/// despawned without detaching them first. See [Hierarchy::children_alive].
+pub struct ChildrenAliveIter {
+    children: smallvec::IntoIter<[Entity; STACK_SIZE]>,
+}
+
+impl ChildrenAliveIter {
+    pub(crate) fn new<T: Component, W: GenericWorld>(frame: &W, parent: Entity) -> Self {
+        let mut children = SmallVec::new();
+
+        let (num_children, last_child) = match frame.try_get::<Parent<T>>(parent) {
+            Ok(data) => (data.num_children, data.last_child),
+            Err(_) => return Self::from_children(children),
+        };
+
+        let mut query = match frame.try_query::<&Child<T>>() {
+            Ok(query) => query,
+            Err(_) => return Self::from_children(children),
+        };
+
+        {
+            let view = query.view();
+            let linked = |entity: Entity| view.get(entity).filter(|child| child.parent == parent);
+
+            if let Some(last) = linked(last_child) {
+                // Walk forward from the first child until a despawned sibling is reached
+                let mut seen = HashSet::new();
+                let mut cur = last.next;
+                while let Some(child) = linked(cur) {
+                    if !seen.insert(cur) {
+                        break;
+                    }
+                    children.push(cur);
+                    if cur == last_child {
+                        return Self::from_children(children);
+                    }
+                    cur = child.next;
+                }
+                let gap = Some(cur).filter(|&cur| linked(cur).is_none());
+
+                // Walk backward from the last child. When both walks end at the same despawned
+                // sibling, every live child has been found
+                let mut back = SmallVec::<[Entity; STACK_SIZE]>::new();
+                let mut cur = last_child;
+                let before = loop {
+                    match linked(cur) {
+                        Some(child) if seen.insert(cur) => {
+                            back.push(cur);
+                            cur = child.prev;
+                        }
+                        Some(_) => break None,
+                        None => break Some(cur),
+                    }
+                };
+
+                if gap.is_some() && before == gap && children.len() + back.len() + 1 == num_children
+                {
+                    children.extend(back.into_iter().rev());
+                    return Self::from_children(children);
+                }
+            }
+        }
+
+        // Otherwise the live children can only be found through their parent
+        let alive = query
+            .iter()
+            .filter(|(_, child)| child.parent == parent)
+            .map(|(entity, child)| (entity, (child.prev, child.next)))
+            .collect::<HashMap<_, _>>();
+
+        Self::from_children(order_runs(&alive, last_child))
+    }
+
+    fn from_children(children: SmallVec<[Entity; STACK_SIZE]>) -> Self {
+        Self {
+            children: children.into_iter(),
+        }
+    }
+}
+
+/// Orders the live children in `alive`, which maps each of them to its previous and next sibling.
+///
+/// The live siblings form runs which end at despawned siblings. Runs separated by a single
+/// despawned sibling point at it from both sides and are chained in sibling order, starting from
+/// the first child and from the last child. Runs separated by several despawned siblings cannot be
+/// chained and are placed in between in the order of their first ids.
+fn order_runs(
+    alive: &HashMap<Entity, (Entity, Entity)>,
+    last_child: Entity,
+) -> SmallVec<[Entity; STACK_SIZE]> {
+    let last_alive = alive.contains_key(&last_child);
+
+    let mut heads = alive
+        .iter()
+        .filter(|&(_, &(prev, _))| !alive.contains_key(&prev) || (last_alive && prev == last_child))
+        .map(|(&entity, _)| entity)
+        .collect::<Vec<_>>();
+    heads.sort();
+
+    let mut seen = HashSet::new();
+    let mut runs = Vec::new();
+    for head in heads {
+        if !seen.insert(head) {
+            continue;
+        }
+
+        let mut run = vec![head];
+        let mut cur = head;
+        while cur != last_child {
+            let next = alive[&cur].1;
+            if !alive.contains_key(&next) || !seen.insert(next) {
+                break;
+            }
+            run.push(next);
+            cur = next;
+        }
+        runs.push(run);
+    }
+
+    // Children caught in a corrupted ring without a start
+    let mut rest = alive
+        .keys()
+        .copied()
+        .filter(|entity| !seen.contains(entity))
+        .collect::<Vec<_>>();
+    rest.sort();
+    runs.extend(rest.into_iter().map(|entity| vec![entity]));
+
+    let head_prev = |run: &Vec<Entity>| alive[&run[0]].0;
+    let tail_next = |run: &Vec<Entity>| alive[&run[run.len() - 1]].1;
+    let before = runs
+        .iter()
+        .enumerate()
+        .map(|(i, run)| (head_prev(run), i))
+        .collect::<HashMap<_, _>>();
+    let after = runs
+        .iter()
+        .enumerate()
+        .map(|(i, run)| (tail_next(run), i))
+        .collect::<HashMap<_, _>>();
+
+    let mut used = vec![false; runs.len()];
+
+    let mut prefix = Vec::new();
+    let mut next = before.get(&last_child).copied();
+    while let Some(i) = next.filter(|&i| !used[i]) {
+        used[i] = true;
+        prefix.push(i);
+        next = before.get(&tail_next(&runs[i])).copied();
+    }
+
+    let mut suffix = Vec::new();
+    let mut prev = if last_alive {
+        runs.iter().position(|run| run.last() == Some(&last_child))
+    } else {
+        after.get(&last_child).copied()
+    };
+    while let Some(i) = prev.filter(|&i| !used[i]) {
+        used[i] = true;
+        suffix.push(i);
+        prev = after.get(&head_prev(&runs[i])).copied();
+    }
+
+    let middle = (0..runs.len()).filter(|&i| !used[i]).collect::<Vec<_>>();
+
+    prefix
+        .into_iter()
+        .chain(middle)
+        .chain(suffix.into_iter().rev())
+        .flat_map(|i| runs[i].iter().copied())
+        .collect()
+}
+
+impl Iterator for ChildrenAliveIter {
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.children.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.children.size_hint()
+    }
+}
+
+impl ExactSizeIterator for ChildrenAliveIter {}
+
+impl FusedIterator for ChildrenAliveIter {}
+
 pub struct AncestorIter<'a, T: Component> {
-    query: QueryBorrow<'a, &'a Child<T>>,
+    /// `None` if the world lacks access to `Child<T>`
+    query: Option<QueryBorrow<'a, &'a Child<T>>>,
     current: Entity,
     marker: PhantomData<T>,
 }
@@ -74,7 +333,7 @@ pub struct AncestorIter<'a, T: Component> {
 impl<'a, T: Component> AncestorIter<'a, T> {
     pub(crate) fn new<W: GenericWorld>(frame: &'a W, current: Entity) -> Self {
         Self {
-            query: frame.try_query().unwrap(),
+            query: frame.try_query().ok(),
             current,
             marker: PhantomData,
         }
@@ -85,7 +344,7 @@ impl<'a, T: Component> Iterator for AncestorIter<'a, T> {
     type Item = Entity;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(child) = self.query.view().get(self.current) {
+        if let Some(child) = self.query.as_mut()?.view().get(self.current) {
             self.current = child.parent;
             Some(child.parent)
         } else {
@@ -94,6 +353,115 @@ impl<'a, T: Component> Iterator for AncestorIter<'a, T> {
     }
 }
 
+impl<'a, T: Component> FusedIterator for AncestorIter<'a, T> {}
+
+/// Traverses the ancestors of an entity which have component `C`, from the immediate parent
+/// upwards. See [Hierarchy::ancestors_with].
+pub struct AncestorsWithIter<'a, W, T: Component, C> {
+    frame: &'a W,
+    ancestors: AncestorIter<'a, T>,
+    marker: PhantomData<C>,
+}
+
+impl<'a, W: GenericWorld, T: Component, C: Component> AncestorsWithIter<'a, W, T, C> {
+    pub(crate) fn new(frame: &'a W, ancestors: AncestorIter<'a, T>) -> Self {
+        Self {
+            frame,
+            ancestors,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, W: GenericWorld, T: Component, C: Component> Iterator for AncestorsWithIter<'a, W, T, C> {
+    type Item = (Entity, Ref<'a, C>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = self.frame;
+        self.ancestors
+            .find_map(|ancestor| Some((ancestor, frame.try_get::<C>(ancestor).ok()?)))
+    }
+}
+
+impl<'a, W: GenericWorld, T: Component, C: Component> FusedIterator
+    for AncestorsWithIter<'a, W, T, C>
+{
+}
+
+/// Traverses the ancestors of an entity from the immediate parent upwards, up to and including the
+/// first ancestor which matches a predicate. See [Hierarchy::ancestors_until].
+pub struct AncestorsUntilIter<'a, W, T: Component, F> {
+    frame: &'a W,
+    ancestors: AncestorIter<'a, T>,
+    predicate: F,
+    done: bool,
+}
+
+impl<'a, W: GenericWorld, T: Component, F: FnMut(&W, Entity) -> bool>
+    AncestorsUntilIter<'a, W, T, F>
+{
+    pub(crate) fn new(frame: &'a W, ancestors: AncestorIter<'a, T>, predicate: F) -> Self {
+        Self {
+            frame,
+            ancestors,
+            predicate,
+            done: false,
+        }
+    }
+}
+
+impl<'a, W: GenericWorld, T: Component, F: FnMut(&W, Entity) -> bool> Iterator
+    for AncestorsUntilIter<'a, W, T, F>
+{
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let ancestor = self.ancestors.next()?;
+        self.done = (self.predicate)(self.frame, ancestor);
+        Some(ancestor)
+    }
+}
+
+impl<'a, W: GenericWorld, T: Component, F: FnMut(&W, Entity) -> bool> FusedIterator
+    for AncestorsUntilIter<'a, W, T, F>
+{
+}
+
+/// Traverses the ancestors of an entity in order from the root down to the immediate parent. See
+/// [Hierarchy::ancestors_from_root].
+pub struct AncestorsFromRootIter {
+    /// Ancestors in order from the parent to the root
+    ancestors: SmallVec<[Entity; STACK_SIZE]>,
+}
+
+impl AncestorsFromRootIter {
+    pub(crate) fn new<T: Component>(ancestors: AncestorIter<T>) -> Self {
+        Self {
+            ancestors: ancestors.collect(),
+        }
+    }
+}
+
+impl Iterator for AncestorsFromRootIter {
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.ancestors.pop()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.ancestors.len(), Some(self.ancestors.len()))
+    }
+}
+
+impl ExactSizeIterator for AncestorsFromRootIter {}
+
+impl FusedIterator for AncestorsFromRootIter {}
+
 #[derive(Debug)]
 struct StackFrame {
     current: Entity,
@@ -113,17 +481,8 @@ impl<'a, T: Component> DepthFirstIterator<'a, T> {
         let children = frame.try_query().unwrap();
         let mut parents = frame.try_query::<&Parent<T>>().unwrap();
 
-        let stack = parents
-            .view()
-            .get(root)
-            .and_then(|parent| {
-                let first_child = parent.first_child(frame).ok()?;
-                Some(smallvec![StackFrame {
-                    current: first_child,
-                    remaining: parent.num_children,
-                }])
-            })
-            .unwrap_or_default();
+        let mut stack = SmallVec::new();
+        push_children(frame, &mut parents, root, &mut stack);
 
         Self {
             children,
@@ -134,6 +493,228 @@ impl<'a, T: Component> DepthFirstIterator<'a, T> {
     }
 }
 
+/// Pushes a stack frame for the children of `parent`, if it has any.
+fn push_children<T: Component, W: GenericWorld>(
+    frame: &W,
+    parents: &mut QueryBorrow<&Parent<T>>,
+    parent: Entity,
+    stack: &mut SmallVec<[StackFrame; STACK_SIZE]>,
+) {
+    if let Some(parent) = parents.view().get(parent) {
+        if let Ok(first_child) = parent.first_child(frame) {
+            stack.push(StackFrame {
+                current: first_child,
+                remaining: parent.num_children,
+            });
+        }
+    }
+}
+
+/// Advances a depth first traversal by one entity, skipping `excluded` along with its
+/// descendants.
+fn depth_first_next<T: Component>(
+    children: &mut QueryBorrow<&Child<T>>,
+    parents: &mut QueryBorrow<&Parent<T>>,
+    stack: &mut SmallVec<[StackFrame; STACK_SIZE]>,
+    excluded: Option<Entity>,
+) -> Option<Entity> {
+    loop {
+        // The the topmost stackframe
+        let top = stack.last_mut()?;
+
+        // There are more children in current stackframe
+        if top.remaining > 0 {
+            let current = top.current;
+
+            let children = children.view();
+            let data = match children.get(top.current) {
+                Some(data) => data,
+                None => {
+                    // A despawned sibling cuts off the rest of the ring
+                    stack.pop();
+                    continue;
+                }
+            };
+
+            // Go to the next child in the linked list of children
+            top.current = data.next;
+            top.remaining -= 1;
+
+            if excluded == Some(current) {
+                continue;
+            }
+
+            // If current is a parent, push a new stack frame with the first child
+            if let Some(parent) = parents.view().get(current) {
+                if let Ok(first_child) = parent.view_first_child(&children) {
+                    stack.push(StackFrame {
+                        current: first_child,
+                        remaining: parent.num_children,
+                    })
+                }
+            }
+
+            return Some(current);
+        } else {
+            // End of linked list of children, pop stack frame
+            stack.pop();
+        }
+    }
+}
+
+/// Every sibling left in a stack frame is yielded, but their descendants are not known yet.
+fn depth_first_size_hint(stack: &[StackFrame]) -> (usize, Option<usize>) {
+    let remaining = stack.iter().map(|frame| frame.remaining).sum();
+    if remaining == 0 {
+        (0, Some(0))
+    } else {
+        (remaining, None)
+    }
+}
+
+/// First in first out queue of a breadth first traversal. Popped entities are only removed when
+/// they make up at least half of the buffer, so that shallow trees stay within the inline
+/// capacity.
+#[derive(Debug, Default)]
+struct Queue {
+    items: SmallVec<[Entity; STACK_SIZE]>,
+    head: usize,
+}
+
+impl Queue {
+    fn clear(&mut self) {
+        self.items.clear();
+        self.head = 0;
+    }
+
+    fn len(&self) -> usize {
+        self.items.len() - self.head
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn pop_front(&mut self) -> Option<Entity> {
+        let front = *self.items.get(self.head)?;
+        self.head += 1;
+        Some(front)
+    }
+
+    fn extend(&mut self, entities: impl IntoIterator<Item = Entity>) {
+        if self.head > 0 && self.head * 2 >= self.items.len() {
+            self.items.drain(..self.head);
+            self.head = 0;
+        }
+
+        self.items.extend(entities);
+    }
+}
+
+/// Scratch space for traversals which is reused between them, so that repeated traversals of
+/// large trees, such as once per frame, do not allocate. See
+/// [Hierarchy::descendants_depth_first_in] and [Hierarchy::descendants_breadth_first_in].
+#[derive(Debug, Default)]
+pub struct TraversalCache {
+    stack: SmallVec<[StackFrame; STACK_SIZE]>,
+    queue: Queue,
+}
+
+impl TraversalCache {
+    /// Creates an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Traverses the descendants depth first using the buffers of a [TraversalCache]. See
+/// [Hierarchy::descendants_depth_first_in].
+pub struct DepthFirstIn<'a, 'c, T: Component> {
+    children: QueryBorrow<'a, &'a Child<T>>,
+    parents: QueryBorrow<'a, &'a Parent<T>>,
+    stack: &'c mut SmallVec<[StackFrame; STACK_SIZE]>,
+}
+
+impl<'a, 'c, T: Component> DepthFirstIn<'a, 'c, T> {
+    pub(crate) fn new<W: GenericWorld>(
+        frame: &'a W,
+        root: Entity,
+        cache: &'c mut TraversalCache,
+    ) -> Self {
+        let children = frame.try_query().unwrap();
+        let mut parents = frame.try_query::<&Parent<T>>().unwrap();
+
+        let stack = &mut cache.stack;
+        stack.clear();
+        push_children(frame, &mut parents, root, stack);
+
+        Self {
+            children,
+            parents,
+            stack,
+        }
+    }
+}
+
+impl<'a, 'c, T: Component> Iterator for DepthFirstIn<'a, 'c, T> {
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        depth_first_next(&mut self.children, &mut self.parents, self.stack, None)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        depth_first_size_hint(&self.stack)
+    }
+}
+
+impl<'a, 'c, T: Component> FusedIterator for DepthFirstIn<'a, 'c, T> {}
+
+/// Traverses the descendants breadth first using the buffers of a [TraversalCache]. See
+/// [Hierarchy::descendants_breadth_first_in].
+pub struct BreadthFirstIn<'a, 'c, W, T> {
+    frame: &'a W,
+    queue: &'c mut Queue,
+    marker: PhantomData<T>,
+}
+
+impl<'a, 'c, W: GenericWorld + Hierarchy, T: Component> BreadthFirstIn<'a, 'c, W, T> {
+    pub(crate) fn new(frame: &'a W, root: Entity, cache: &'c mut TraversalCache) -> Self {
+        let queue = &mut cache.queue;
+        queue.clear();
+        queue.extend(frame.children::<T>(root));
+
+        Self {
+            frame,
+            queue,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, 'c, W: GenericWorld + Hierarchy, T: Component> Iterator for BreadthFirstIn<'a, 'c, W, T> {
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let front = self.queue.pop_front()?;
+        self.queue.extend(self.frame.children::<T>(front));
+        Some(front)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.queue.is_empty() {
+            (0, Some(0))
+        } else {
+            (self.queue.len(), None)
+        }
+    }
+}
+
+impl<'a, 'c, W: GenericWorld + Hierarchy, T: Component> FusedIterator
+    for BreadthFirstIn<'a, 'c, W, T>
+{
+}
+
 pub struct DepthFirstVisitor<'a, W, T: Component, F> {
     frame: &'a W,
     children: QueryBorrow<'a, &'a Child<T>>,
@@ -192,7 +773,14 @@ impl<'a, F: Fn(&W, Entity) -> bool + Component, W: GenericWorld, T: Component> I
                 let current = top.current;
 
                 let children = self.children.view();
-                let data = children.get(top.current).unwrap();
+                let data = match children.get(top.current) {
+                    Some(data) => data,
+                    None => {
+                        // A despawned sibling cuts off the rest of the ring
+                        self.stack.pop();
+                        continue;
+                    }
+                };
 
                 // Go to the next child in the linked list of children
                 top.current = data.next;
@@ -219,55 +807,457 @@ impl<'a, F: Fn(&W, Entity) -> bool + Component, W: GenericWorld, T: Component> I
             }
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.stack.is_empty() {
+            (0, Some(0))
+        } else {
+            (0, None)
+        }
+    }
+}
+
+impl<'a, F: Fn(&W, Entity) -> bool + Component, W: GenericWorld, T: Component> FusedIterator
+    for DepthFirstVisitor<'a, W, T, F>
+{
 }
 
 impl<'a, T: Component> Iterator for DepthFirstIterator<'a, T> {
     type Item = Entity;
 
     fn next(&mut self) -> Option<Self::Item> {
-        // The the topmost stackframe
-        let top = self.stack.last_mut()?;
+        depth_first_next(&mut self.children, &mut self.parents, &mut self.stack, None)
+    }
 
-        // There are more children in current stackframe
-        if top.remaining > 0 {
-            let current = top.current;
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        depth_first_size_hint(&self.stack)
+    }
+}
 
-            let children = self.children.view();
-            let data = children.get(top.current).unwrap();
+impl<'a, T: Component> FusedIterator for DepthFirstIterator<'a, T> {}
 
-            // Go to the next child in the linked list of children
-            top.current = data.next;
+/// Traverses a tree in document order, skipping the entities which match a predicate along with
+/// their descendants. See [Hierarchy::flatten].
+pub struct FlattenIter<'a, W, T: Component, F> {
+    frame: &'a W,
+    children: QueryBorrow<'a, &'a Child<T>>,
+    parents: QueryBorrow<'a, &'a Parent<T>>,
+    /// The root, until it has been visited
+    root: Option<Entity>,
+    stack: SmallVec<[StackFrame; STACK_SIZE]>,
+    skip: F,
+}
+
+impl<'a, W: GenericWorld, T: Component, F: FnMut(&W, Entity) -> bool> FlattenIter<'a, W, T, F> {
+    pub(crate) fn new(frame: &'a W, root: Entity, skip: F) -> Self {
+        Self {
+            frame,
+            children: frame.try_query().unwrap(),
+            parents: frame.try_query().unwrap(),
+            root: Some(root),
+            stack: SmallVec::new(),
+            skip,
+        }
+    }
+}
+
+impl<'a, W: GenericWorld, T: Component, F: FnMut(&W, Entity) -> bool> Iterator
+    for FlattenIter<'a, W, T, F>
+{
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(root) = self.root.take() {
+            if (self.skip)(self.frame, root) {
+                return None;
+            }
+
+            push_children(self.frame, &mut self.parents, root, &mut self.stack);
+            return Some(root);
+        }
+
+        loop {
+            let top = self.stack.last_mut()?;
+            if top.remaining == 0 {
+                self.stack.pop();
+                continue;
+            }
+
+            let current = top.current;
+            top.current = self.children.view().get(current)?.next;
             top.remaining -= 1;
 
-            // If current is a parent, push a new stack frame with the first child
-            if let Some(parent) = self.parents.view().get(current) {
-                if let Ok(first_child) = parent.view_first_child(&children) {
-                    self.stack.push(StackFrame {
-                        current: first_child,
-                        remaining: parent.num_children,
-                    })
-                }
+            if (self.skip)(self.frame, current) {
+                continue;
             }
 
-            Some(current)
+            push_children(self.frame, &mut self.parents, current, &mut self.stack);
+            return Some(current);
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.root.is_none() && self.stack.iter().all(|frame| frame.remaining == 0) {
+            (0, Some(0))
         } else {
-            // End of linked list of children, pop stack frame
-            self.stack.pop();
-            self.next()
+            (0, None)
         }
     }
 }
 
+impl<'a, W: GenericWorld, T: Component, F: FnMut(&W, Entity) -> bool> FusedIterator
+    for FlattenIter<'a, W, T, F>
+{
+}
+
+/// Traverses the descendants depth first, skipping an excluded entity along with its
+/// descendants. See [Hierarchy::descendants_excluding].
+pub struct DescendantsExcludingIter<'a, T: Component> {
+    children: QueryBorrow<'a, &'a Child<T>>,
+    parents: QueryBorrow<'a, &'a Parent<T>>,
+    stack: SmallVec<[StackFrame; STACK_SIZE]>,
+    excluded: Entity,
+}
+
+impl<'a, T: Component> DescendantsExcludingIter<'a, T> {
+    pub(crate) fn new<W: GenericWorld>(frame: &'a W, root: Entity, excluded: Entity) -> Self {
+        let children = frame.try_query().unwrap();
+        let mut parents = frame.try_query::<&Parent<T>>().unwrap();
+
+        let mut stack = SmallVec::new();
+        if root != excluded {
+            push_children(frame, &mut parents, root, &mut stack);
+        }
+
+        Self {
+            children,
+            parents,
+            stack,
+            excluded,
+        }
+    }
+}
+
+impl<'a, T: Component> Iterator for DescendantsExcludingIter<'a, T> {
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        depth_first_next(
+            &mut self.children,
+            &mut self.parents,
+            &mut self.stack,
+            Some(self.excluded),
+        )
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, depth_first_size_hint(&self.stack).1)
+    }
+}
+
+impl<'a, T: Component> FusedIterator for DescendantsExcludingIter<'a, T> {}
+
+/// Traverses the descendants in hierarchy `A` depth first, keeping only those which are, or are
+/// not, members of hierarchy `B`. See [Hierarchy::descendants_in_both] and
+/// [Hierarchy::descendants_not_in].
+pub struct MembershipFilterIter<'a, A: Component, B: Component> {
+    iter: DepthFirstIterator<'a, A>,
+    children: QueryBorrow<'a, &'a Child<B>>,
+    parents: QueryBorrow<'a, &'a Parent<B>>,
+    members: bool,
+}
+
+impl<'a, A: Component, B: Component> MembershipFilterIter<'a, A, B> {
+    pub(crate) fn new<W: GenericWorld>(frame: &'a W, root: Entity, members: bool) -> Self {
+        Self {
+            iter: DepthFirstIterator::new(frame, root),
+            children: frame.try_query().unwrap(),
+            parents: frame.try_query().unwrap(),
+            members,
+        }
+    }
+}
+
+impl<'a, A: Component, B: Component> Iterator for MembershipFilterIter<'a, A, B> {
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let children = self.children.view();
+        let parents = self.parents.view();
+        let members = self.members;
+
+        self.iter.find(|&entity| {
+            let member = children.get(entity).is_some() || parents.get(entity).is_some();
+            member == members
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.iter.size_hint().1)
+    }
+}
+
+impl<'a, A: Component, B: Component> FusedIterator for MembershipFilterIter<'a, A, B> {}
+
+/// Traverses the descendants depth first, yielding the descendants which satisfy query `Q` along
+/// with the query item. See [Hierarchy::descendants_with].
+pub struct DescendantsWithIter<'a, 'v, 'q, T: Component, Q: Query> {
+    iter: DepthFirstIterator<'a, T>,
+    view: &'v View<'q, Q>,
+}
+
+impl<'a, 'v, 'q, T: Component, Q: Query> DescendantsWithIter<'a, 'v, 'q, T, Q> {
+    pub(crate) fn new<W: GenericWorld>(frame: &'a W, root: Entity, view: &'v View<'q, Q>) -> Self {
+        Self {
+            iter: DepthFirstIterator::new(frame, root),
+            view,
+        }
+    }
+}
+
+impl<'a, 'v, 'q, T: Component, Q: QueryShared> Iterator for DescendantsWithIter<'a, 'v, 'q, T, Q> {
+    type Item = (Entity, QueryItem<'v, Q>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let view = self.view;
+        self.iter
+            .find_map(move |entity| Some((entity, view.get(entity)?)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.iter.size_hint().1)
+    }
+}
+
+impl<'a, 'v, 'q, T: Component, Q: QueryShared> FusedIterator
+    for DescendantsWithIter<'a, 'v, 'q, T, Q>
+{
+}
+
+/// Every parent and child pair of hierarchy `T`. See [Hierarchy::edges].
+pub struct Edges<'a, T: Component> {
+    query: QueryBorrow<'a, &'a Child<T>>,
+}
+
+impl<'a, T: Component> Edges<'a, T> {
+    pub(crate) fn new(query: QueryBorrow<'a, &'a Child<T>>) -> Self {
+        Self { query }
+    }
+
+    /// Iterates all `(parent, child)` pairs in no particular order.
+    pub fn iter(&mut self) -> impl Iterator<Item = (Entity, Entity)> + '_ {
+        self.query.iter().map(|(child, data)| (data.parent, child))
+    }
+}
+
+/// Every entity which belongs to hierarchy `T`. See [Hierarchy::members].
+pub struct Members<'a, T: Component> {
+    roots: QueryBorrow<'a, Without<&'a Parent<T>, &'a Child<T>>>,
+    children: QueryBorrow<'a, &'a Child<T>>,
+}
+
+impl<'a, T: Component> Members<'a, T> {
+    pub(crate) fn new(
+        roots: QueryBorrow<'a, Without<&'a Parent<T>, &'a Child<T>>>,
+        children: QueryBorrow<'a, &'a Child<T>>,
+    ) -> Self {
+        Self { roots, children }
+    }
+
+    /// Iterates all entities with a `Parent<T>` or `Child<T>` in no particular order. Each entity
+    /// is yielded exactly once.
+    pub fn iter(&mut self) -> impl Iterator<Item = Entity> + '_ {
+        self.roots
+            .iter()
+            .map(|(e, _)| e)
+            .chain(self.children.iter().map(|(e, _)| e))
+    }
+}
+
+/// Every entity which belongs to hierarchy `T` along with its parent. See
+/// [Hierarchy::parents_iter].
+pub struct ParentPairs<'a, T: Component> {
+    roots: QueryBorrow<'a, Without<&'a Parent<T>, &'a Child<T>>>,
+    children: QueryBorrow<'a, &'a Child<T>>,
+}
+
+impl<'a, T: Component> ParentPairs<'a, T> {
+    pub(crate) fn new(
+        roots: QueryBorrow<'a, Without<&'a Parent<T>, &'a Child<T>>>,
+        children: QueryBorrow<'a, &'a Child<T>>,
+    ) -> Self {
+        Self { roots, children }
+    }
+
+    /// Iterates all `(entity, parent)` pairs in no particular order, where the parent of a root is
+    /// `None`. Each entity is yielded exactly once.
+    pub fn iter(&mut self) -> impl Iterator<Item = (Entity, Option<Entity>)> + '_ {
+        self.roots.iter().map(|(e, _)| (e, None)).chain(
+            self.children
+                .iter()
+                .map(|(e, child)| (e, Some(child.parent))),
+        )
+    }
+}
+
+/// Traverses the descendants depth first, visiting the children of every entity in a
+/// deterministic order rather than their sibling order. See
+/// [Hierarchy::descendants_depth_first_sorted],
+/// [Hierarchy::descendants_depth_first_sorted_by_key] and [Hierarchy::descendants_z_ordered].
+pub struct SortedDepthFirstIter<'a, W, T> {
+    frame: &'a W,
+    /// Entities left to visit, in reverse order
+    stack: Vec<Entity>,
+    sort: fn(&W, &mut [Entity]),
+    marker: PhantomData<T>,
+}
+
+impl<'a, W: GenericWorld + Hierarchy, T: Component> SortedDepthFirstIter<'a, W, T> {
+    pub(crate) fn new(frame: &'a W, root: Entity, sort: fn(&W, &mut [Entity])) -> Self {
+        let mut iter = Self {
+            frame,
+            stack: Vec::new(),
+            sort,
+            marker: PhantomData,
+        };
+
+        iter.push_children(root);
+        iter
+    }
+
+    fn push_children(&mut self, parent: Entity) {
+        let mut children = self.frame.children_vec::<T>(parent);
+        (self.sort)(self.frame, &mut children);
+        self.stack.extend(children.into_iter().rev());
+    }
+}
+
+impl<'a, W: GenericWorld + Hierarchy, T: Component> Iterator for SortedDepthFirstIter<'a, W, T> {
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.stack.pop()?;
+        self.push_children(current);
+        Some(current)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.stack.is_empty() {
+            (0, Some(0))
+        } else {
+            (self.stack.len(), None)
+        }
+    }
+}
+
+impl<'a, W: GenericWorld + Hierarchy, T: Component> FusedIterator
+    for SortedDepthFirstIter<'a, W, T>
+{
+}
+
+/// Sorts `entities` by their ids.
+pub(crate) fn sort_by_id<W>(_: &W, entities: &mut [Entity]) {
+    entities.sort();
+}
+
+/// Sorts `entities` by their component `K`, followed by the entities without it. Ties are broken
+/// by the ids of the entities.
+pub(crate) fn sort_by_component<W: GenericWorld, K: Component + Ord>(
+    frame: &W,
+    entities: &mut [Entity],
+) {
+    let mut keyed = entities
+        .iter()
+        .map(|&entity| (frame.try_get::<K>(entity).ok(), entity))
+        .collect::<Vec<_>>();
+
+    keyed.sort_by(|(a, a_entity), (b, b_entity)| {
+        let by_key = match (a, b) {
+            (Some(a), Some(b)) => (**a).cmp(&**b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+        by_key.then(a_entity.cmp(b_entity))
+    });
+
+    for (slot, (_, entity)) in entities.iter_mut().zip(keyed) {
+        *slot = entity;
+    }
+}
+
+/// Stably sorts `entities` by their component `Z`, followed by the entities without it. Entities
+/// whose `Z` are equal or incomparable keep their order.
+pub(crate) fn sort_by_layer<W: GenericWorld, Z: Component + PartialOrd>(
+    frame: &W,
+    entities: &mut [Entity],
+) {
+    let mut keyed = entities
+        .iter()
+        .map(|&entity| (frame.try_get::<Z>(entity).ok(), entity))
+        .collect::<Vec<_>>();
+
+    keyed.sort_by(|(a, _), (b, _)| match (a, b) {
+        (Some(a), Some(b)) => (**a).partial_cmp(&**b).unwrap_or(Ordering::Equal),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    });
+
+    for (slot, (_, entity)) in entities.iter_mut().zip(keyed) {
+        *slot = entity;
+    }
+}
+
+/// Traverses the descendants one depth level at a time. See [Hierarchy::layers].
+pub struct LayersIter<'a, W, T> {
+    frame: &'a W,
+    layer: Vec<Entity>,
+    marker: PhantomData<T>,
+}
+
+impl<'a, W: GenericWorld + Hierarchy, T: Component> LayersIter<'a, W, T> {
+    pub(crate) fn new(frame: &'a W, root: Entity) -> Self {
+        Self {
+            frame,
+            layer: frame.children_vec::<T>(root),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, W: GenericWorld + Hierarchy, T: Component> Iterator for LayersIter<'a, W, T> {
+    type Item = Vec<Entity>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.layer.is_empty() {
+            return None;
+        }
+
+        let frame = self.frame;
+        let next = self
+            .layer
+            .iter()
+            .flat_map(|&entity| frame.children::<T>(entity))
+            .collect();
+
+        Some(std::mem::replace(&mut self.layer, next))
+    }
+}
+
+impl<'a, W: GenericWorld + Hierarchy, T: Component> FusedIterator for LayersIter<'a, W, T> {}
+
 pub struct BreadthFirstIterator<'a, W, T> {
     frame: &'a W,
     marker: PhantomData<T>,
-    queue: VecDeque<Entity>,
+    queue: Queue,
 }
 
 impl<'a, W: GenericWorld + Hierarchy, T: 'static + Send + Sync> BreadthFirstIterator<'a, W, T> {
     pub(crate) fn new(frame: &'a W, root: Entity) -> Self {
         // Add immediate children of root to queue
-        let queue = frame.children::<T>(root).collect();
+        let mut queue = Queue::default();
+        queue.extend(frame.children::<T>(root));
 
         Self {
             frame,
@@ -290,4 +1280,17 @@ impl<'a, W: GenericWorld + Hierarchy, T: 'static + Send + Sync> Iterator
 
         Some(front)
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.queue.is_empty() {
+            (0, Some(0))
+        } else {
+            (self.queue.len(), None)
+        }
+    }
+}
+
+impl<'a, W: GenericWorld + Hierarchy, T: 'static + Send + Sync> FusedIterator
+    for BreadthFirstIterator<'a, W, T>
+{
 }
@@ -0,0 +1,264 @@
+use std::marker::PhantomData;
+
+use moss_hecs::{Component, Entity, Frame};
+
+use crate::{
+    hierarchy::{attach_at, link_at, slot_of},
+    observe::notify,
+    HierarchyEvent, HierarchyMut, HierarchyResult,
+};
+
+/// The move of one entity between two slots, where a slot is a parent and an index among its
+/// children, or `None` when the entity is not attached.
+#[derive(Debug, Clone, Copy)]
+struct Move {
+    child: Entity,
+    from: Option<(Entity, usize)>,
+    to: Option<(Entity, usize)>,
+}
+
+/// Performs structural operations on hierarchy `T` while recording them, so that they can be
+/// undone and redone. Every operation remembers the slot the moved entity occupied among its
+/// siblings, which makes undoing restore the original order as well.
+///
+/// Undoing and redoing assume that the hierarchy has not been modified by other means in the
+/// meantime. Performing a new operation discards the operations which could have been redone.
+pub struct HierarchyJournal<T> {
+    done: Vec<Move>,
+    undone: Vec<Move>,
+    marker: PhantomData<T>,
+}
+
+impl<T: Component> HierarchyJournal<T> {
+    /// Creates an empty journal.
+    pub fn new() -> Self {
+        Self {
+            done: Vec::new(),
+            undone: Vec::new(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Attach `child` to `parent` and record it. See [HierarchyMut::attach].
+    pub fn attach(
+        &mut self,
+        frame: &mut Frame,
+        child: Entity,
+        parent: Entity,
+    ) -> HierarchyResult<Entity> {
+        self.record(frame, child, |frame| frame.attach::<T>(child, parent))
+    }
+
+    /// Attach `child` to `parent` as its first child and record it. See
+    /// [HierarchyMut::attach_front].
+    pub fn attach_front(
+        &mut self,
+        frame: &mut Frame,
+        child: Entity,
+        parent: Entity,
+    ) -> HierarchyResult<Entity> {
+        self.record(frame, child, |frame| frame.attach_front::<T>(child, parent))
+    }
+
+    /// Attach `child` right before `sibling` and record it. See [HierarchyMut::attach_before].
+    pub fn attach_before(
+        &mut self,
+        frame: &mut Frame,
+        child: Entity,
+        sibling: Entity,
+    ) -> HierarchyResult<Entity> {
+        self.record(frame, child, |frame| {
+            frame.attach_before::<T>(child, sibling)
+        })
+    }
+
+    /// Attach `child` right after `sibling` and record it. See [HierarchyMut::attach_after].
+    pub fn attach_after(
+        &mut self,
+        frame: &mut Frame,
+        child: Entity,
+        sibling: Entity,
+    ) -> HierarchyResult<Entity> {
+        self.record(frame, child, |frame| {
+            frame.attach_after::<T>(child, sibling)
+        })
+    }
+
+    /// Detach `child` from its parent and record it. Returns the parent. See
+    /// [HierarchyMut::detach].
+    pub fn detach(&mut self, frame: &mut Frame, child: Entity) -> HierarchyResult<Entity> {
+        self.record(frame, child, |frame| frame.detach::<T>(child))
+    }
+
+    fn record<R>(
+        &mut self,
+        frame: &mut Frame,
+        child: Entity,
+        op: impl FnOnce(&mut Frame) -> HierarchyResult<R>,
+    ) -> HierarchyResult<R> {
+        let from = slot_of::<T, _>(frame, child);
+        let result = op(frame)?;
+        let to = slot_of::<T, _>(frame, child);
+
+        self.done.push(Move { child, from, to });
+        self.undone.clear();
+
+        Ok(result)
+    }
+
+    /// Reverts the last performed or redone operation. Returns false if there is nothing to undo.
+    pub fn undo(&mut self, frame: &mut Frame) -> HierarchyResult<bool> {
+        self.undo_inner(frame, true)
+    }
+
+    /// Same as [Self::undo], but only checks the enabled policy of `T` if `checked` is set.
+    fn undo_inner(&mut self, frame: &mut Frame, checked: bool) -> HierarchyResult<bool> {
+        let op = match self.done.pop() {
+            Some(op) => op,
+            None => return Ok(false),
+        };
+
+        if let Err(err) = place::<T>(frame, op.child, op.from, checked) {
+            self.done.push(op);
+            return Err(err);
+        }
+
+        self.undone.push(op);
+        Ok(true)
+    }
+
+    /// Performs the last undone operation again. Returns false if there is nothing to redo.
+    pub fn redo(&mut self, frame: &mut Frame) -> HierarchyResult<bool> {
+        let op = match self.undone.pop() {
+            Some(op) => op,
+            None => return Ok(false),
+        };
+
+        if let Err(err) = place::<T>(frame, op.child, op.to, true) {
+            self.undone.push(op);
+            return Err(err);
+        }
+
+        self.done.push(op);
+        Ok(true)
+    }
+
+    /// Returns true if there is an operation to undo.
+    pub fn can_undo(&self) -> bool {
+        !self.done.is_empty()
+    }
+
+    /// Returns true if there is an operation to redo.
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    /// Forgets every recorded operation.
+    pub fn clear(&mut self) {
+        self.done.clear();
+        self.undone.clear();
+    }
+}
+
+impl<T: Component> Default for HierarchyJournal<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> std::fmt::Debug for HierarchyJournal<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HierarchyJournal")
+            .field("done", &self.done)
+            .field("undone", &self.undone)
+            .finish()
+    }
+}
+
+/// Moves `child` into `slot`, detaching it if `slot` is `None`. The enabled policy of `T` is only
+/// checked if `checked` is set.
+fn place<T: Component>(
+    frame: &mut Frame,
+    child: Entity,
+    slot: Option<(Entity, usize)>,
+    checked: bool,
+) -> HierarchyResult<()> {
+    match slot {
+        Some((parent, index)) if checked => attach_at::<T>(frame, child, parent, index)?,
+        Some((parent, index)) => {
+            link_at::<T>(frame, child, parent, index)?;
+            notify::<T>(frame, HierarchyEvent::Attached { child, parent });
+        }
+        None => {
+            if slot_of::<T, _>(frame, child).is_some() {
+                frame.detach::<T>(child)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// A set of structural changes to hierarchy `T` which is reverted as a whole if the closure
+/// passed to [HierarchyMut::hierarchy_transaction] fails. The frame can be read through
+/// [Self::frame] while the transaction is open.
+pub struct HierarchyTransaction<'a, T> {
+    frame: &'a mut Frame,
+    journal: HierarchyJournal<T>,
+}
+
+impl<'a, T: Component> HierarchyTransaction<'a, T> {
+    pub(crate) fn new(frame: &'a mut Frame) -> Self {
+        Self {
+            frame,
+            journal: HierarchyJournal::new(),
+        }
+    }
+
+    /// Returns the frame the transaction modifies.
+    pub fn frame(&self) -> &Frame {
+        self.frame
+    }
+
+    /// Attach `child` to `parent`. See [HierarchyMut::attach].
+    pub fn attach(&mut self, child: Entity, parent: Entity) -> HierarchyResult<Entity> {
+        self.journal.attach(self.frame, child, parent)
+    }
+
+    /// Attach `child` to `parent` as its first child. See [HierarchyMut::attach_front].
+    pub fn attach_front(&mut self, child: Entity, parent: Entity) -> HierarchyResult<Entity> {
+        self.journal.attach_front(self.frame, child, parent)
+    }
+
+    /// Attach `child` right before `sibling`. See [HierarchyMut::attach_before].
+    pub fn attach_before(&mut self, child: Entity, sibling: Entity) -> HierarchyResult<Entity> {
+        self.journal.attach_before(self.frame, child, sibling)
+    }
+
+    /// Attach `child` right after `sibling`. See [HierarchyMut::attach_after].
+    pub fn attach_after(&mut self, child: Entity, sibling: Entity) -> HierarchyResult<Entity> {
+        self.journal.attach_after(self.frame, child, sibling)
+    }
+
+    /// Detach `child` from its parent. Returns the parent. See [HierarchyMut::detach].
+    pub fn detach(&mut self, child: Entity) -> HierarchyResult<Entity> {
+        self.journal.detach(self.frame, child)
+    }
+
+    /// Reverts every change made through the transaction, in reverse order. The enabled policy of
+    /// `T` is bypassed, since the hierarchy returns to the state it was in before, which may
+    /// violate a policy enabled later.
+    pub(crate) fn rollback(&mut self) {
+        // Only the transaction modifies the hierarchy while it is open and the policy is not
+        // checked, so undoing can not fail
+        while let Ok(true) = self.journal.undo_inner(self.frame, false) {}
+    }
+}
+
+impl<'a, T> std::fmt::Debug for HierarchyTransaction<'a, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HierarchyTransaction")
+            .field("journal", &self.journal)
+            .finish()
+    }
+}
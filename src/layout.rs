@@ -0,0 +1,44 @@
+use moss_hecs::{Component, Entity, Frame};
+
+use crate::Hierarchy;
+
+/// Runs a two phase layout pass over the tree below `root` in hierarchy `T`, including `root`.
+/// First `measure` is called for every entity after its children, bottom up, so that the size of
+/// an entity can be computed from the sizes of its children. Then `arrange` is called for every
+/// entity before its children, top down, so that a parent can place its children within the
+/// space it was given. Both phases visit the children of an entity in their sibling order.
+///
+/// The tree is traversed once, and both phases reuse the resulting order. The closures may
+/// modify the components of the frame, but attaching or detaching entities during the pass does
+/// not change which entities are visited. Returns the number of entities in the tree.
+pub fn layout<T, M, A>(frame: &mut Frame, root: Entity, mut measure: M, mut arrange: A) -> usize
+where
+    T: Component,
+    M: FnMut(&mut Frame, Entity),
+    A: FnMut(&mut Frame, Entity),
+{
+    let mut pre_order = Vec::new();
+    let mut post_order = Vec::new();
+    let mut stack = vec![(root, false)];
+    while let Some((entity, expanded)) = stack.pop() {
+        if expanded {
+            post_order.push(entity);
+            continue;
+        }
+
+        pre_order.push(entity);
+        stack.push((entity, true));
+        let children = frame.children_vec::<T>(entity);
+        stack.extend(children.into_iter().rev().map(|child| (child, false)));
+    }
+
+    for &entity in &post_order {
+        measure(frame, entity);
+    }
+
+    for &entity in &pre_order {
+        arrange(frame, entity);
+    }
+
+    pre_order.len()
+}
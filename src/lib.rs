@@ -14,7 +14,7 @@
 //! - [X] Traverse ancestors
 //! - [X] Detach child from hierarchy
 //! - [ ] Reverse iteration
-//! - [ ] Sorting
+//! - [X] Sorting
 //! - [ ] (Optional) associated data to relation
 //!
 //! ## Getting Started
@@ -125,14 +125,79 @@
 
 mod builder;
 mod builder_clone;
+mod child_list;
 mod components;
+mod depth;
+mod diff;
+mod dynamic;
+mod edge;
+mod error;
+mod forest;
+mod format;
+mod hash;
 mod hierarchy;
+mod introspect;
 mod iter;
+mod journal;
+mod layout;
+mod maintenance;
+mod multi;
+mod node;
+mod observe;
+mod outline;
+mod policy;
+mod propagate;
+mod registry;
+mod remap;
+mod replication;
+#[cfg(feature = "scene")]
+pub mod scene;
+#[cfg(feature = "serde")]
+mod serialize;
+mod snapshot;
+mod split;
+mod stats;
+#[cfg(all(feature = "strict-checks", debug_assertions))]
+mod strict;
+mod subtree;
+mod transplant;
+mod validate;
+#[cfg(feature = "serde")]
+mod value;
 
 pub use builder::*;
 pub use builder_clone::*;
+pub use child_list::*;
 pub use components::*;
+pub use depth::*;
+pub use diff::*;
+pub use dynamic::*;
+pub use edge::*;
+pub use error::*;
+pub use forest::*;
+pub use format::*;
 pub use hierarchy::*;
+pub use introspect::*;
 pub use iter::*;
+pub use journal::*;
+pub use layout::*;
+pub use maintenance::*;
+pub use multi::*;
+pub use node::*;
+pub use observe::*;
+pub use outline::*;
+pub use policy::*;
+pub use propagate::*;
+pub use registry::*;
+pub use remap::*;
+pub use replication::*;
+pub use snapshot::*;
+pub use split::*;
+pub use stats::*;
+pub use subtree::*;
+pub use transplant::*;
+pub use validate::*;
+#[cfg(feature = "serde")]
+pub use value::*;
 
 pub use moss_hecs_schedule::Error;
@@ -0,0 +1,118 @@
+use std::marker::PhantomData;
+
+use moss_hecs::{Component, Entity, Frame};
+use moss_hecs_schedule::{CommandBuffer, GenericWorld, Write};
+
+use crate::{registry::prune_roots, sync_despawns, Hierarchy, HierarchyMut};
+
+/// Tags an entity to be despawned along with its descendants in hierarchy `T` by the next
+/// [flush_despawns] or [maintain_hierarchy]. Inserting the tag, directly or through a
+/// `CommandBuffer`, is safe while iterating the hierarchy, unlike despawning right away.
+pub struct DespawnRecursive<T>(PhantomData<T>);
+
+impl<T: Component> DespawnRecursive<T> {
+    /// Creates the tag.
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: Component> Default for DespawnRecursive<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> std::fmt::Debug for DespawnRecursive<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("DespawnRecursive")
+    }
+}
+
+/// Despawns every entity tagged with [DespawnRecursive] of `T` along with its descendants in
+/// hierarchy `T`. Returns the number of despawned entities.
+pub fn flush_despawns<T: Component>(frame: &mut Frame) -> usize {
+    let tagged = frame
+        .query::<&DespawnRecursive<T>>()
+        .iter()
+        .map(|(entity, _)| entity)
+        .collect::<Vec<_>>();
+
+    // Tagged entities below another tagged entity are gone by the time they are reached
+    tagged
+        .into_iter()
+        .filter(|&entity| frame.contains(entity))
+        .filter_map(|entity| frame.despawn_all::<T>(entity).ok())
+        .sum()
+}
+
+/// Queues despawning `entity` along with its descendants in hierarchy `T` into `cmd`, for systems
+/// which only have shared access to the frame. Returns `entity` followed by its descendants in
+/// depth first order, which are the entities that would be despawned if the buffer was executed
+/// right away.
+///
+/// The descendants are resolved again when the buffer is executed, so that entities attached in
+/// the meantime are despawned as well. Nothing is despawned if `entity` is gone by then.
+pub fn despawn_all_deferred<T: Component, W: GenericWorld + Hierarchy>(
+    frame: &W,
+    cmd: &mut CommandBuffer,
+    entity: Entity,
+) -> Vec<Entity> {
+    cmd.write(move |w: &mut Frame| {
+        let _ = w.despawn_all::<T>(entity);
+    });
+
+    std::iter::once(entity)
+        .chain(frame.descendants_depth_first::<T>(entity))
+        .collect()
+}
+
+/// Performs the upkeep of hierarchy `T` which the structural operations can not do themselves,
+/// since it is caused by modifying the frame directly:
+///
+/// - Entities tagged with [DespawnRecursive] are despawned along with their descendants, using
+///   [flush_despawns].
+/// - The hierarchy is repaired around entities which have been despawned through the frame, using
+///   [sync_despawns]. Their children become roots, and the sibling rings, child counts and
+///   child lists of their parents are rebuilt.
+/// - Despawned entities are removed from the [RootRegistry](crate::RootRegistry).
+///
+/// Returns the entities despawned through the frame which were still referenced by the hierarchy,
+/// in the order of their ids. This is cheap when there is nothing to do.
+pub fn maintain_hierarchy<T: Component>(frame: &mut Frame) -> Vec<Entity> {
+    flush_despawns::<T>(frame);
+    let despawned = sync_despawns::<T>(frame);
+    prune_roots::<T>(frame);
+    despawned
+}
+
+/// Returns a system for `moss_hecs_schedule` which runs [maintain_hierarchy] for hierarchy `T`
+/// on the frame of the schedule. Add it once per hierarchy, usually at the end of the schedule.
+///
+/// ```rust
+/// use moss_hecs::Frame;
+/// use moss_hecs_hierarchy::*;
+/// use moss_hecs_schedule::{Schedule, Write};
+///
+/// struct Tree;
+///
+/// let mut frame = Frame::default();
+/// let root = frame.spawn(("Root",));
+/// let child = frame.attach_new::<Tree, _>(root, ("Child",)).unwrap();
+///
+/// let mut schedule = Schedule::builder()
+///     .add_system(move |mut frame: Write<Frame>| {
+///         frame.insert_one(root, DespawnRecursive::<Tree>::new()).unwrap();
+///     })
+///     .add_system(hierarchy_maintenance_system::<Tree>())
+///     .build();
+///
+/// schedule.execute_seq((&mut frame,)).unwrap();
+/// assert!(!frame.contains(root));
+/// assert!(!frame.contains(child));
+/// ```
+pub fn hierarchy_maintenance_system<T: Component>() -> impl FnMut(Write<Frame>) + Send + Sync {
+    |mut frame: Write<Frame>| {
+        maintain_hierarchy::<T>(&mut frame);
+    }
+}
@@ -0,0 +1,108 @@
+use moss_hecs::{Component, Entity, Frame};
+
+use crate::{
+    hierarchy::{ensure_alive, link_at, link_child, unlink_child, Position},
+    observe::notify,
+    policy::check_policy,
+    Hierarchy, HierarchyError, HierarchyEvent, HierarchyResult, Role,
+};
+
+/// A tuple of hierarchy marker types, such as `(Scene, Focus)`, which can be attached to as a
+/// unit using [HierarchyMut::attach_multi](crate::HierarchyMut::attach_multi). Implemented for
+/// tuples of up to eight markers.
+pub trait MarkerSet {
+    /// Attaches `child` to `parent` in every hierarchy of the set, or in none of them.
+    fn attach_all(frame: &mut Frame, child: Entity, parent: Entity) -> HierarchyResult<()>;
+}
+
+/// Where a child was attached before moving it, as the parent and index among its siblings.
+type Slot = Option<(Entity, usize)>;
+
+/// Fails if attaching `child` to `parent` in hierarchy `T` would create a cycle or violate the
+/// enabled policy of `T`, without modifying anything.
+fn check_one<T: Component>(frame: &Frame, child: Entity, parent: Entity) -> HierarchyResult<()> {
+    if child == parent
+        || frame
+            .ancestors::<T>(parent)
+            .any(|ancestor| ancestor == child)
+    {
+        return Err(HierarchyError::Cycle {
+            ancestor: child,
+            descendant: parent,
+        });
+    }
+
+    check_policy::<T>(frame, child, parent)
+}
+
+/// Attaches `child` as the last child of `parent` in hierarchy `T`, returning the slot it was
+/// moved from. If linking fails, `child` is moved back into that slot.
+fn attach_one<T: Component>(
+    frame: &mut Frame,
+    child: Entity,
+    parent: Entity,
+) -> HierarchyResult<Slot> {
+    let slot = match frame.parent::<T>(child) {
+        Ok(old) => {
+            let index = frame
+                .children::<T>(old)
+                .position(|sibling| sibling == child)
+                .unwrap_or_default();
+            unlink_child::<T>(frame, child)?;
+            Some((old, index))
+        }
+        Err(_) => None,
+    };
+
+    if let Err(err) = link_child::<T>(frame, child, parent, Position::Back) {
+        restore_one::<T>(frame, child, slot);
+        return Err(err);
+    }
+
+    Ok(slot)
+}
+
+/// Moves `child` back into `slot` in hierarchy `T`, or detaches it if it was not attached.
+fn restore_one<T: Component>(frame: &mut Frame, child: Entity, slot: Slot) {
+    let _ = match slot {
+        Some((parent, index)) => link_at::<T>(frame, child, parent, index),
+        None => unlink_child::<T>(frame, child).map(|_| ()),
+    };
+}
+
+macro_rules! impl_marker_set {
+    ($($marker:ident)+) => {
+        impl<$($marker: Component),+> MarkerSet for ($($marker,)+) {
+            fn attach_all(frame: &mut Frame, child: Entity, parent: Entity) -> HierarchyResult<()> {
+                ensure_alive(frame, child, Role::Child)?;
+                ensure_alive(frame, parent, Role::Parent)?;
+                $(check_one::<$marker>(frame, child, parent)?;)+
+
+                let mut attached: Vec<(fn(&mut Frame, Entity, Slot), Slot)> = Vec::new();
+                $(
+                    match attach_one::<$marker>(frame, child, parent) {
+                        Ok(slot) => attached.push((restore_one::<$marker>, slot)),
+                        Err(err) => {
+                            for (restore, slot) in attached.into_iter().rev() {
+                                restore(frame, child, slot);
+                            }
+                            return Err(err);
+                        }
+                    }
+                )+
+
+                $(notify::<$marker>(frame, HierarchyEvent::Attached { child, parent });)+
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_marker_set!(A);
+impl_marker_set!(A B);
+impl_marker_set!(A B C);
+impl_marker_set!(A B C D);
+impl_marker_set!(A B C D E);
+impl_marker_set!(A B C D E F);
+impl_marker_set!(A B C D E F G);
+impl_marker_set!(A B C D E F G H);
@@ -0,0 +1,105 @@
+use std::marker::PhantomData;
+
+use moss_hecs::{Component, Entity};
+use moss_hecs_schedule::{error::Result, GenericWorld};
+
+use crate::{
+    AncestorIter, BreadthFirstIterator, Child, ChildrenIter, DepthFirstIterator, Hierarchy, Parent,
+};
+
+/// A handle to an entity in hierarchy tree `T`, obtained through [Hierarchy::node].
+///
+/// Allows traversing the hierarchy without respecifying the marker type and entity at each call.
+pub struct Node<'a, W, T> {
+    frame: &'a W,
+    entity: Entity,
+    marker: PhantomData<T>,
+}
+
+impl<'a, W: GenericWorld, T: Component> Node<'a, W, T> {
+    pub(crate) fn new(frame: &'a W, entity: Entity) -> Self {
+        Self {
+            frame,
+            entity,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the entity of the node.
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    /// Returns the frame the node belongs to.
+    pub fn frame(&self) -> &'a W {
+        self.frame
+    }
+
+    /// Returns the parent of the node.
+    pub fn parent(&self) -> Result<Self> {
+        self.frame
+            .parent::<T>(self.entity)
+            .map(|parent| Self::new(self.frame, parent))
+    }
+
+    /// Returns the root of the tree the node belongs to. Returns the node itself if it is a root.
+    pub fn root(&self) -> Result<Self> {
+        self.frame
+            .root::<T>(self.entity)
+            .map(|root| Self::new(self.frame, root))
+    }
+
+    /// Returns true if the node has no parent.
+    pub fn is_root(&self) -> bool {
+        self.frame.try_get::<Child<T>>(self.entity).is_err()
+    }
+
+    /// Returns the number of immediate children of the node.
+    pub fn num_children(&self) -> usize {
+        self.frame
+            .try_get::<Parent<T>>(self.entity)
+            .map(|parent| parent.num_children())
+            .unwrap_or_default()
+    }
+
+    /// Returns the number of ancestors of the node. A root has a depth of 0.
+    pub fn depth(&self) -> usize {
+        self.ancestors().count()
+    }
+
+    /// Traverses the immediate children of the node.
+    pub fn children(&self) -> ChildrenIter<'a, T> {
+        self.frame.children::<T>(self.entity)
+    }
+
+    /// Traverse the tree upwards. Iterator does not include the node itself.
+    pub fn ancestors(&self) -> AncestorIter<'a, T> {
+        self.frame.ancestors::<T>(self.entity)
+    }
+
+    /// Traverse the subtree depth first. Iterator does not include the node itself.
+    pub fn descendants(&self) -> DepthFirstIterator<'a, T> {
+        self.frame.descendants_depth_first::<T>(self.entity)
+    }
+
+    /// Traverse the subtree breadth first. Iterator does not include the node itself.
+    pub fn descendants_breadth_first(&self) -> BreadthFirstIterator<'a, W, T> {
+        self.frame.descendants_breadth_first::<T>(self.entity)
+    }
+}
+
+impl<'a, W, T> Clone for Node<'a, W, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, W, T> Copy for Node<'a, W, T> {}
+
+impl<'a, W, T> std::fmt::Debug for Node<'a, W, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Node")
+            .field("entity", &self.entity)
+            .finish()
+    }
+}
@@ -0,0 +1,69 @@
+use std::marker::PhantomData;
+
+use moss_hecs::{Component, Entity, Frame};
+
+/// A structural change of hierarchy `T`, as passed to the observers registered with
+/// [HierarchyMut::observe](crate::HierarchyMut::observe).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HierarchyEvent<'a> {
+    /// `child` was attached to `parent`. Also sent when `child` was moved from another parent or
+    /// to another position among its siblings.
+    Attached {
+        /// The attached child
+        child: Entity,
+        /// Its new parent
+        parent: Entity,
+    },
+    /// `child` was detached from `parent`.
+    Detached {
+        /// The detached child
+        child: Entity,
+        /// Its former parent
+        parent: Entity,
+    },
+    /// `root` was despawned along with its descendants.
+    Despawned {
+        /// The root of the despawned subtree
+        root: Entity,
+        /// The despawned entities, starting with `root`
+        entities: &'a [Entity],
+    },
+}
+
+type Observer = Box<dyn FnMut(&HierarchyEvent) + Send + Sync>;
+
+/// The observers of hierarchy `T`, stored on a singleton entity.
+pub(crate) struct HierarchyObservers<T> {
+    observers: Vec<Observer>,
+    marker: PhantomData<T>,
+}
+
+/// Registers `observer` for the structural changes of hierarchy `T`.
+pub(crate) fn observe<T: Component>(frame: &mut Frame, observer: Observer) {
+    if let Some((_, observers)) = frame
+        .query_mut::<&mut HierarchyObservers<T>>()
+        .into_iter()
+        .next()
+    {
+        observers.observers.push(observer);
+        return;
+    }
+
+    frame.spawn((HierarchyObservers::<T> {
+        observers: vec![observer],
+        marker: PhantomData,
+    },));
+}
+
+/// Invokes the observers of hierarchy `T` with `event`, in the order they were registered.
+pub(crate) fn notify<T: Component>(frame: &mut Frame, event: HierarchyEvent) {
+    if let Some((_, observers)) = frame
+        .query_mut::<&mut HierarchyObservers<T>>()
+        .into_iter()
+        .next()
+    {
+        for observer in &mut observers.observers {
+            observer(&event);
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use moss_hecs::{Component, Entity};
+use moss_hecs_schedule::GenericWorld;
+
+use crate::{Hierarchy, TreeBuilderClone, TreeDisplay};
+
+/// The number of spaces every level of an outline is indented by.
+const OUTLINE_INDENT: usize = 2;
+
+/// Formats the tree below `root` as an outline: one label per line, indented by two spaces per
+/// level below the root. The output only depends on the labels and the order of the children,
+/// which makes it suitable for golden tests. Labels should not contain line breaks.
+///
+/// ```text
+/// Root
+///   Child 1
+///   Child 2
+///     Grandchild
+/// ```
+pub fn to_outline<T, W, F>(frame: &W, root: Entity, labeler: F) -> String
+where
+    T: Component,
+    W: GenericWorld + Hierarchy,
+    F: Fn(&W, Entity) -> String,
+{
+    TreeDisplay::<T, W, F>::new(frame, root, labeler)
+        .indent(OUTLINE_INDENT)
+        .branch("  ")
+        .to_string()
+}
+
+/// Errors of parsing an outline, as returned by [from_outline]. Lines are counted from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutlineError {
+    /// The outline contains no labels.
+    Empty,
+    /// The line is not indented by a multiple of two spaces, or by more than one level deeper
+    /// than the line before it.
+    InvalidIndent {
+        /// The offending line
+        line: usize,
+    },
+    /// The line is not indented, but the root has already been given.
+    MultipleRoots {
+        /// The offending line
+        line: usize,
+    },
+}
+
+impl std::fmt::Display for OutlineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutlineError::Empty => write!(f, "The outline is empty"),
+            OutlineError::InvalidIndent { line } => {
+                write!(f, "Invalid indentation on line {}", line)
+            }
+            OutlineError::MultipleRoots { line } => {
+                write!(f, "Line {} starts a second root", line)
+            }
+        }
+    }
+}
+
+impl std::error::Error for OutlineError {}
+
+/// Parses an outline as produced by [to_outline] into a builder. Every node is
+/// [labeled](TreeBuilderClone::label) with its line, and carries the line as a `String`
+/// component, so that `to_outline` with a labeler reading the `String` reproduces the outline.
+/// Blank lines are ignored.
+pub fn from_outline<T: Component>(outline: &str) -> Result<TreeBuilderClone<T>, OutlineError> {
+    // The open nodes from the root down to the previous line
+    let mut stack: Vec<TreeBuilderClone<T>> = Vec::new();
+
+    for (i, line) in outline.lines().enumerate() {
+        let label = line.trim_start_matches(' ');
+        if label.trim().is_empty() {
+            continue;
+        }
+
+        let indent = line.len() - label.len();
+        let depth = indent / OUTLINE_INDENT;
+        if indent % OUTLINE_INDENT != 0 || depth > stack.len() {
+            return Err(OutlineError::InvalidIndent { line: i + 1 });
+        }
+
+        if depth == 0 && !stack.is_empty() {
+            return Err(OutlineError::MultipleRoots { line: i + 1 });
+        }
+
+        while stack.len() > depth {
+            close(&mut stack);
+        }
+
+        let mut node = TreeBuilderClone::from((label.to_string(),));
+        node.label(label);
+        stack.push(node);
+    }
+
+    while stack.len() > 1 {
+        close(&mut stack);
+    }
+
+    stack.pop().ok_or(OutlineError::Empty)
+}
+
+/// Pops the deepest open node and attaches it to its parent.
+fn close<T: Component>(stack: &mut Vec<TreeBuilderClone<T>>) {
+    let node = stack.pop().unwrap();
+    stack.last_mut().unwrap().attach_tree(node);
+}
@@ -0,0 +1,166 @@
+use std::marker::PhantomData;
+
+use moss_hecs::{Component, Entity, Frame};
+
+use crate::{Child, Hierarchy, HierarchyError, HierarchyResult, Parent};
+
+/// Structural constraints of hierarchy `Self`, enforced by the attach family of
+/// [HierarchyMut](crate::HierarchyMut) once enabled with
+/// [HierarchyMut::enable_policy](crate::HierarchyMut::enable_policy). Every constraint is
+/// optional and unconstrained by default.
+///
+/// ```rust
+/// use moss_hecs_hierarchy::*;
+///
+/// // A behavior tree decorator, which wraps a single node
+/// struct Decorator;
+///
+/// impl HierarchyPolicy for Decorator {
+///     const MAX_CHILDREN: Option<usize> = Some(1);
+/// }
+///
+/// let mut frame = moss_hecs::Frame::default();
+/// frame.enable_policy::<Decorator>().unwrap();
+///
+/// let root = frame.spawn(("Repeat",));
+/// frame.attach_new::<Decorator, _>(root, ("Action",)).unwrap();
+/// assert!(matches!(
+///     frame.attach_new::<Decorator, _>(root, ("Other",)),
+///     Err(HierarchyError::TooManyChildren { .. })
+/// ));
+/// ```
+pub trait HierarchyPolicy: Component {
+    /// The most children a single entity may have, or `None` for no limit.
+    const MAX_CHILDREN: Option<usize> = None;
+
+    /// The greatest depth of an entity in a tree, where the root is at depth 0, or `None` for no
+    /// limit. Attaching is rejected if the child or one of its descendants would end up deeper.
+    /// Checking visits the ancestors of the parent and the subtree of the child, but no more
+    /// levels than the limit.
+    const MAX_DEPTH: Option<usize> = None;
+
+    /// Returns whether `child` may be attached to `parent`, which declares the shape of the
+    /// hierarchy, such as rows only being allowed in tables. Checked after the other constraints.
+    /// Allows every attachment by default.
+    fn can_attach(_frame: &Frame, _child: Entity, _parent: Entity) -> bool {
+        true
+    }
+}
+
+/// The constraints of the enabled [HierarchyPolicy] of hierarchy `T`, stored on a singleton
+/// entity.
+pub(crate) struct PolicyEnabled<T> {
+    max_children: Option<usize>,
+    max_depth: Option<usize>,
+    can_attach: fn(&Frame, Entity, Entity) -> bool,
+    marker: PhantomData<T>,
+}
+
+/// Enables the [HierarchyPolicy] of `T` in `frame`. Does nothing if it is already enabled.
+pub(crate) fn enable_policy<T: HierarchyPolicy>(frame: &mut Frame) {
+    if frame.query::<&PolicyEnabled<T>>().iter().next().is_some() {
+        return;
+    }
+
+    frame.spawn((PolicyEnabled::<T> {
+        max_children: T::MAX_CHILDREN,
+        max_depth: T::MAX_DEPTH,
+        can_attach: T::can_attach,
+        marker: PhantomData,
+    },));
+}
+
+/// Fails if attaching `child` to `parent` would violate the enabled policy of hierarchy `T`. Does
+/// nothing if no policy is enabled.
+pub(crate) fn check_policy<T: Component>(
+    frame: &Frame,
+    child: Entity,
+    parent: Entity,
+) -> HierarchyResult<()> {
+    // Moving a child within its parent does not add a child
+    let moved = frame
+        .get::<&Child<T>>(child)
+        .map(|data| data.parent == parent)
+        .unwrap_or_default();
+
+    let num_children = if moved {
+        None
+    } else {
+        Some(num_children::<T>(frame, parent) + 1)
+    };
+
+    check_attach::<T>(frame, &[child], parent, num_children)
+}
+
+/// Fails if attaching all of `children` to `parent`, none of which is a child of it yet, would
+/// violate the enabled policy of hierarchy `T`, leaving `parent` with `num_children` children.
+/// Does nothing if no policy is enabled or `children` is empty.
+pub(crate) fn check_policy_all<T: Component>(
+    frame: &Frame,
+    children: &[Entity],
+    parent: Entity,
+    num_children: usize,
+) -> HierarchyResult<()> {
+    if children.is_empty() {
+        return Ok(());
+    }
+
+    check_attach::<T>(frame, children, parent, Some(num_children))
+}
+
+/// Returns the number of children of `parent`.
+pub(crate) fn num_children<T: Component>(frame: &Frame, parent: Entity) -> usize {
+    frame
+        .get::<&Parent<T>>(parent)
+        .map(|data| data.num_children)
+        .unwrap_or_default()
+}
+
+/// Checks attaching `children` to `parent` against the enabled policy of `T`. The limit on
+/// children is only checked if the number of children changes to `num_children`.
+fn check_attach<T: Component>(
+    frame: &Frame,
+    children: &[Entity],
+    parent: Entity,
+    num_children: Option<usize>,
+) -> HierarchyResult<()> {
+    let (max_children, max_depth, can_attach) =
+        match frame.query::<&PolicyEnabled<T>>().iter().next() {
+            Some((_, policy)) => (policy.max_children, policy.max_depth, policy.can_attach),
+            None => return Ok(()),
+        };
+
+    if let (Some(max), Some(num_children)) = (max_children, num_children) {
+        if num_children > max {
+            return Err(HierarchyError::TooManyChildren { parent, max });
+        }
+    }
+
+    if let Some(max) = max_depth {
+        let parent_depth = frame.ancestors::<T>(parent).take(max).count() + 1;
+
+        for &child in children {
+            let too_deep = Err(HierarchyError::TooDeep { child, max });
+
+            let mut depth = parent_depth;
+            if depth > max {
+                return too_deep;
+            }
+
+            for _ in frame.layers::<T>(child) {
+                depth += 1;
+                if depth > max {
+                    return too_deep;
+                }
+            }
+        }
+    }
+
+    for &child in children {
+        if !can_attach(frame, child, parent) {
+            return Err(HierarchyError::NotAllowed { child, parent });
+        }
+    }
+
+    Ok(())
+}
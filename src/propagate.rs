@@ -0,0 +1,252 @@
+use std::{collections::HashSet, marker::PhantomData};
+
+use moss_hecs::{Component, Entity, Frame};
+
+use crate::{Hierarchy, Parent};
+
+/// Marks a component `C` which has been copied from an ancestor by [propagate_inherited], rather
+/// than declared by the entity itself. Inherited values are overwritten on the next propagation,
+/// and removed once no ancestor declares `C` anymore.
+pub struct Inherited<C>(PhantomData<C>);
+
+impl<C: Component> Inherited<C> {
+    pub(crate) fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<C> std::fmt::Debug for Inherited<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Inherited")
+    }
+}
+
+/// Propagates component `C` down the tree below `root` in hierarchy `T`, such as visibility, tint
+/// or an enabled state. Every descendant which does not declare its own `C` inherits the value of
+/// its closest ancestor which does, marked with [Inherited]. A declared value overrides the
+/// inherited one for the whole subtree below it. The value of `root` itself is never changed,
+/// whether it is declared or inherited.
+///
+/// Descendants whose inherited value is no longer declared by any ancestor lose it. To stop
+/// inheriting, remove `C` from the declaring entity and propagate again. Returns the number of
+/// descendants which inherited a value.
+pub fn propagate_inherited<T: Component, C: Component + Clone>(
+    frame: &mut Frame,
+    root: Entity,
+) -> usize {
+    let value = frame.get::<&C>(root).map(|value| (*value).clone()).ok();
+    let mut stack = frame
+        .children_vec::<T>(root)
+        .into_iter()
+        .rev()
+        .map(|child| (child, value.clone()))
+        .collect::<Vec<_>>();
+
+    let mut inherited = 0;
+    while let Some((entity, value)) = stack.pop() {
+        let inherits = frame.get::<&Inherited<C>>(entity).is_ok();
+        let declared = match frame.get::<&C>(entity) {
+            Ok(own) if !inherits => Some((*own).clone()),
+            _ => None,
+        };
+
+        let value = match (declared, value) {
+            (Some(own), _) => Some(own),
+            (None, Some(value)) => {
+                let _ = frame.insert(entity, (value.clone(), Inherited::<C>::new()));
+                inherited += 1;
+                Some(value)
+            }
+            (None, None) => {
+                if inherits {
+                    let _ = frame.remove::<(C, Inherited<C>)>(entity);
+                }
+                None
+            }
+        };
+
+        let children = frame.children_vec::<T>(entity);
+        stack.extend(
+            children
+                .into_iter()
+                .rev()
+                .map(|child| (child, value.clone())),
+        );
+    }
+
+    inherited
+}
+
+/// Marks component `C` of an entity as changed, so that the values derived from it are recomputed
+/// by the next [derive_down] or [aggregate]. Also insert it when attaching or detaching an entity,
+/// since that changes what its values are derived from.
+pub struct Dirty<C>(PhantomData<C>);
+
+impl<C: Component> Dirty<C> {
+    /// Creates the tag.
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<C: Component> Default for Dirty<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C> std::fmt::Debug for Dirty<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Dirty")
+    }
+}
+
+/// Returns the entities in the tree below `root`, including `root`, which are tagged with
+/// [Dirty] of `C`.
+fn dirty_in_tree<T: Component, C: Component>(frame: &Frame, root: Entity) -> HashSet<Entity> {
+    frame
+        .query::<&Dirty<C>>()
+        .iter()
+        .map(|(entity, _)| entity)
+        .filter(|&entity| entity == root || frame.ancestors::<T>(entity).any(|a| a == root))
+        .collect()
+}
+
+/// Computes component `Dst` of every entity in the tree below `root` in hierarchy `T`, including
+/// `root`, from the `Dst` of its parent and its own `Src`, such as global transforms from local
+/// ones. The parents are computed before their children, and `f` receives `None` for an entity
+/// whose parent has no `Dst`.
+///
+/// Only the entities tagged with [Dirty] of `Src` and their descendants are recomputed, which
+/// skips the clean subtrees entirely; the tags are removed afterwards. Entities without `Src` are
+/// not computed, and neither are their descendants, unless they are dirty themselves. Returns the
+/// number of computed entities.
+pub fn derive_down<T, Src, Dst, F>(frame: &mut Frame, root: Entity, mut f: F) -> usize
+where
+    T: Component,
+    Src: Component,
+    Dst: Component,
+    F: FnMut(Option<&Dst>, &Src) -> Dst,
+{
+    let dirty = dirty_in_tree::<T, Src>(frame, root);
+
+    // Dirty entities below another dirty entity are computed along with it
+    let starts = dirty
+        .iter()
+        .copied()
+        .filter(|&entity| {
+            entity == root
+                || !frame
+                    .ancestors::<T>(entity)
+                    .take_while(|&ancestor| ancestor != root)
+                    .chain(std::iter::once(root))
+                    .any(|ancestor| dirty.contains(&ancestor))
+        })
+        .collect::<Vec<_>>();
+
+    let mut computed = 0;
+    for start in starts {
+        let mut stack = vec![(start, true)];
+        while let Some((entity, stale)) = stack.pop() {
+            let stale = stale || dirty.contains(&entity);
+            if dirty.contains(&entity) {
+                let _ = frame.remove_one::<Dirty<Src>>(entity);
+            }
+
+            // Below an entity without `Src` only the dirty entities are computed
+            let value = match frame.get::<&Src>(entity) {
+                Ok(src) if stale => {
+                    let parent = frame
+                        .parent::<T>(entity)
+                        .ok()
+                        .and_then(|parent| frame.get::<&Dst>(parent).ok());
+
+                    Some(f(parent.as_deref(), &src))
+                }
+                _ => None,
+            };
+
+            let computed_entity = value.is_some();
+            if let Some(value) = value {
+                let _ = frame.insert_one(entity, value);
+                computed += 1;
+            }
+
+            let children = frame.children_vec::<T>(entity);
+            stack.extend(
+                children
+                    .into_iter()
+                    .rev()
+                    .map(|child| (child, computed_entity)),
+            );
+        }
+    }
+
+    computed
+}
+
+/// Recomputes component `C` of the entities in the tree below `root` in hierarchy `T`, including
+/// `root`, from the `C` of their children, such as bounding volumes, content sizes or subtree
+/// counts. The children are computed before their parents, and `combine` receives the entity
+/// along with the `C` of its children in order, skipping the children without `C`.
+///
+/// Only the ancestors of the entities tagged with [Dirty] of `C` are recomputed, along with the
+/// dirty entities themselves if they have children; the tags are removed afterwards. Entities
+/// without children keep their `C`. Returns the number of recomputed entities.
+pub fn aggregate<T, C, F>(frame: &mut Frame, root: Entity, mut combine: F) -> usize
+where
+    T: Component,
+    C: Component + Clone,
+    F: FnMut(&Frame, Entity, &[C]) -> C,
+{
+    let dirty = dirty_in_tree::<T, C>(frame, root);
+
+    let mut stale = HashSet::new();
+    for &entity in &dirty {
+        if entity != root {
+            let ancestors = frame
+                .ancestors::<T>(entity)
+                .take_while(|&ancestor| ancestor != root)
+                .chain(std::iter::once(root));
+            stale.extend(ancestors);
+        }
+
+        let has_children = frame
+            .get::<&Parent<T>>(entity)
+            .map(|parent| parent.num_children > 0)
+            .unwrap_or_default();
+        if has_children {
+            stale.insert(entity);
+        }
+    }
+
+    for &entity in &dirty {
+        let _ = frame.remove_one::<Dirty<C>>(entity);
+    }
+
+    let mut computed = 0;
+    let mut stack = vec![(root, false)];
+    while let Some((entity, expanded)) = stack.pop() {
+        if !stale.contains(&entity) {
+            continue;
+        }
+
+        let children = frame.children_vec::<T>(entity);
+        if !expanded {
+            stack.push((entity, true));
+            stack.extend(children.into_iter().rev().map(|child| (child, false)));
+            continue;
+        }
+
+        let values = children
+            .into_iter()
+            .filter_map(|child| frame.get::<&C>(child).ok().map(|value| (*value).clone()))
+            .collect::<Vec<_>>();
+
+        let value = combine(frame, entity, &values);
+        let _ = frame.insert_one(entity, value);
+        computed += 1;
+    }
+
+    computed
+}
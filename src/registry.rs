@@ -0,0 +1,106 @@
+use std::{collections::HashSet, marker::PhantomData};
+
+use moss_hecs::{Component, Entity, Frame};
+
+use crate::{Child, Parent};
+
+/// The set of roots of hierarchy `T`, stored on a singleton entity. Once enabled through
+/// [HierarchyMut::enable_root_registry](crate::HierarchyMut::enable_root_registry), the
+/// structural operations of [HierarchyMut](crate::HierarchyMut) keep it up to date, which makes
+/// looking up the roots O(roots) rather than a scan of every archetype.
+///
+/// Components inserted or removed directly, without going through
+/// [HierarchyMut](crate::HierarchyMut), are not tracked.
+pub struct RootRegistry<T> {
+    roots: HashSet<Entity>,
+    marker: PhantomData<T>,
+}
+
+impl<T: Component> RootRegistry<T> {
+    pub(crate) fn new(roots: HashSet<Entity>) -> Self {
+        Self {
+            roots,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the number of registered roots.
+    pub fn len(&self) -> usize {
+        self.roots.len()
+    }
+
+    /// Returns true if there are no registered roots.
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// Returns true if `entity` is a registered root.
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.roots.contains(&entity)
+    }
+
+    /// Returns an iterator over the registered roots in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.roots.iter().copied()
+    }
+
+    pub(crate) fn remap_entities<F: FnMut(Entity) -> Entity>(&mut self, map: F) {
+        self.roots = self.roots.drain().map(map).collect();
+    }
+}
+
+impl<T> std::fmt::Debug for RootRegistry<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RootRegistry")
+            .field("roots", &self.roots)
+            .finish()
+    }
+}
+
+/// Returns true if `entity` is alive and a root of hierarchy `T`.
+fn is_root<T: Component>(frame: &Frame, entity: Entity) -> bool {
+    frame
+        .entity(entity)
+        .map(|entity| entity.has::<Parent<T>>() && !entity.has::<Child<T>>())
+        .unwrap_or(false)
+}
+
+/// Removes the despawned entities from the [RootRegistry] of `T`. Does nothing if the registry is
+/// not enabled.
+pub(crate) fn prune_roots<T: Component>(frame: &mut Frame) {
+    let dead = frame
+        .query::<&RootRegistry<T>>()
+        .iter()
+        .flat_map(|(_, registry)| registry.iter().collect::<Vec<_>>())
+        .filter(|&root| !frame.contains(root))
+        .collect::<Vec<_>>();
+
+    if let Some((_, registry)) = frame.query_mut::<&mut RootRegistry<T>>().into_iter().next() {
+        for root in dead {
+            registry.roots.remove(&root);
+        }
+    }
+}
+
+/// Re-evaluates whether each of `entities` is a root of `T` and updates the [RootRegistry] of `T`
+/// accordingly. Does nothing if the registry is not enabled.
+pub(crate) fn update_roots<T: Component>(frame: &mut Frame, entities: &[Entity]) {
+    if frame.query::<&RootRegistry<T>>().iter().next().is_none() {
+        return;
+    }
+
+    let status = entities
+        .iter()
+        .map(|&entity| (entity, is_root::<T>(frame, entity)))
+        .collect::<Vec<_>>();
+
+    if let Some((_, registry)) = frame.query_mut::<&mut RootRegistry<T>>().into_iter().next() {
+        for (entity, is_root) in status {
+            if is_root {
+                registry.roots.insert(entity);
+            } else {
+                registry.roots.remove(&entity);
+            }
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use std::collections::HashMap;
+
+use moss_hecs::{Component, Entity, Frame};
+
+use crate::{Child, ChildList, EdgeData, Parent, RootRegistry};
+
+/// Rewrites every entity reference held by the components of hierarchy `T` according to `map`.
+/// References to entities missing from `map` are kept as is.
+///
+/// This is the hook for moving hierarchy components between entities, such as when merging
+/// frames, loading saved games or rolling back replicated state: once every `Parent` and `Child`
+/// component has been moved to the entity it maps to, remapping makes the links point to the new
+/// entities as well. The [ChildList] and [RootRegistry] of `T` are remapped too, while the
+/// [EdgeData] of `T` is remapped separately for each type of data through [remap_edge_data].
+pub fn remap_entities<T: Component>(frame: &mut Frame, map: &HashMap<Entity, Entity>) {
+    let remap = |entity: Entity| map.get(&entity).copied().unwrap_or(entity);
+
+    for (_, parent) in frame.query_mut::<&mut Parent<T>>() {
+        parent.remap_entities(remap);
+    }
+
+    for (_, child) in frame.query_mut::<&mut Child<T>>() {
+        child.remap_entities(remap);
+    }
+
+    for (_, list) in frame.query_mut::<&mut ChildList<T>>() {
+        list.remap_entities(remap);
+    }
+
+    for (_, registry) in frame.query_mut::<&mut RootRegistry<T>>() {
+        registry.remap_entities(remap);
+    }
+}
+
+/// Rewrites the parent of every [EdgeData] of hierarchy `T` with data `D` according to `map`, like
+/// [remap_entities]. Parents missing from `map` are kept as is.
+pub fn remap_edge_data<T: Component, D: Component>(
+    frame: &mut Frame,
+    map: &HashMap<Entity, Entity>,
+) {
+    for (_, edge) in frame.query_mut::<&mut EdgeData<T, D>>() {
+        edge.remap_entities(|entity| map.get(&entity).copied().unwrap_or(entity));
+    }
+}
@@ -0,0 +1,201 @@
+use std::marker::PhantomData;
+
+use moss_hecs::{Component, Entity, Frame};
+
+use crate::{HierarchyError, HierarchyMut};
+
+/// A structural operation on hierarchy `T`, with entities identified by a stable key which is
+/// meaningful on every peer, such as a network id. Recorded by a [Replicator] and applied with
+/// [apply_deltas].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StructuralDelta<K> {
+    /// `child` was attached as the last child of `parent`.
+    Attach {
+        /// The key of the child
+        child: K,
+        /// The key of the parent
+        parent: K,
+    },
+    /// `child` was detached from its parent.
+    Detach {
+        /// The key of the child
+        child: K,
+    },
+    /// `root` was despawned along with its descendants.
+    Despawn {
+        /// The key of the root of the despawned subtree
+        root: K,
+    },
+}
+
+/// Errors of recording or applying structural deltas.
+#[derive(Debug)]
+pub enum ReplicationError<K> {
+    /// The entity has no key component.
+    MissingKey(Entity),
+    /// No entity is known by the key on the receiving side.
+    UnknownKey(K),
+    /// The structural operation failed.
+    Hierarchy(HierarchyError),
+}
+
+impl<K: std::fmt::Debug> std::fmt::Display for ReplicationError<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplicationError::MissingKey(entity) => {
+                write!(f, "Entity {:?} has no replication key", entity)
+            }
+            ReplicationError::UnknownKey(key) => write!(f, "No entity is known as {:?}", key),
+            ReplicationError::Hierarchy(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl<K: std::fmt::Debug> std::error::Error for ReplicationError<K> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReplicationError::Hierarchy(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl<K> From<HierarchyError> for ReplicationError<K> {
+    fn from(err: HierarchyError) -> Self {
+        ReplicationError::Hierarchy(err)
+    }
+}
+
+/// Performs structural operations on hierarchy `T` and records them as [StructuralDelta]s keyed
+/// by the component `K` of the entities involved, to be sent to other peers. Operations which
+/// fail are not recorded.
+///
+/// Replication is opt in: only the operations performed through the replicator are recorded.
+pub struct Replicator<T, K> {
+    deltas: Vec<StructuralDelta<K>>,
+    marker: PhantomData<T>,
+}
+
+impl<T: Component, K: Component + Clone> Replicator<T, K> {
+    /// Creates a replicator with no recorded deltas.
+    pub fn new() -> Self {
+        Self {
+            deltas: Vec::new(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Attach `child` to `parent` and record it. See [HierarchyMut::attach].
+    pub fn attach(
+        &mut self,
+        frame: &mut Frame,
+        child: Entity,
+        parent: Entity,
+    ) -> Result<Entity, ReplicationError<K>> {
+        let delta = StructuralDelta::Attach {
+            child: key(frame, child)?,
+            parent: key(frame, parent)?,
+        };
+
+        frame.attach::<T>(child, parent)?;
+        self.deltas.push(delta);
+        Ok(child)
+    }
+
+    /// Detach `child` from its parent and record it. See [HierarchyMut::detach].
+    pub fn detach(
+        &mut self,
+        frame: &mut Frame,
+        child: Entity,
+    ) -> Result<Entity, ReplicationError<K>> {
+        let delta = StructuralDelta::Detach {
+            child: key(frame, child)?,
+        };
+
+        let parent = frame.detach::<T>(child)?;
+        self.deltas.push(delta);
+        Ok(parent)
+    }
+
+    /// Despawn `root` along with its descendants and record it. See
+    /// [HierarchyMut::despawn_all].
+    pub fn despawn_all(
+        &mut self,
+        frame: &mut Frame,
+        root: Entity,
+    ) -> Result<usize, ReplicationError<K>> {
+        let delta = StructuralDelta::Despawn {
+            root: key(frame, root)?,
+        };
+
+        let despawned = frame.despawn_all::<T>(root)?;
+        self.deltas.push(delta);
+        Ok(despawned)
+    }
+
+    /// Returns the deltas recorded since the last drain, in the order they were performed.
+    pub fn deltas(&self) -> &[StructuralDelta<K>] {
+        &self.deltas
+    }
+
+    /// Removes and returns the recorded deltas, in the order they were performed.
+    pub fn drain(&mut self) -> Vec<StructuralDelta<K>> {
+        std::mem::take(&mut self.deltas)
+    }
+}
+
+impl<T: Component, K: Component + Clone> Default for Replicator<T, K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, K: std::fmt::Debug> std::fmt::Debug for Replicator<T, K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Replicator")
+            .field("deltas", &self.deltas)
+            .finish()
+    }
+}
+
+fn key<K: Component + Clone>(frame: &Frame, entity: Entity) -> Result<K, ReplicationError<K>> {
+    frame
+        .get::<&K>(entity)
+        .map(|key| (*key).clone())
+        .map_err(|_| ReplicationError::MissingKey(entity))
+}
+
+/// Applies `deltas` recorded by a [Replicator] to hierarchy `T` in `frame`, in order, resolving
+/// keys to local entities with `resolve`. Stops at the first delta which fails, leaving the
+/// preceding deltas applied.
+pub fn apply_deltas<T, K, F>(
+    frame: &mut Frame,
+    deltas: &[StructuralDelta<K>],
+    mut resolve: F,
+) -> Result<(), ReplicationError<K>>
+where
+    T: Component,
+    K: Clone,
+    F: FnMut(&K) -> Option<Entity>,
+{
+    let mut entity =
+        |key: &K| resolve(key).ok_or_else(|| ReplicationError::UnknownKey(key.clone()));
+
+    for delta in deltas {
+        match delta {
+            StructuralDelta::Attach { child, parent } => {
+                let child = entity(child)?;
+                frame.attach::<T>(child, entity(parent)?)?;
+            }
+            StructuralDelta::Detach { child } => {
+                frame.detach::<T>(entity(child)?)?;
+            }
+            StructuralDelta::Despawn { root } => {
+                frame.despawn_all::<T>(entity(root)?)?;
+            }
+        }
+    }
+
+    Ok(())
+}
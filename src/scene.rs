@@ -0,0 +1,72 @@
+//! Loading of scene fragments: nested documents in any self describing serde format, such as RON
+//! or JSON, where every node lists its components and children. Node documents follow the layout
+//! of [TreeBuilderClone::from_value], with components looked up by name in a
+//! [ComponentRegistry].
+//!
+//! ```ignore
+//! let mut deserializer = serde_json::Deserializer::from_str(r#"{
+//!     "components": { "name": "Root" },
+//!     "children": [{ "label": "child", "components": { "name": "Child" } }]
+//! }"#);
+//!
+//! let tree = scene::spawn::<Tree, _>(&mut frame, &registry, &mut deserializer)?;
+//! ```
+
+use moss_hecs::{Component, Frame};
+use serde::{Deserialize, Deserializer};
+use serde_value::Value;
+
+use crate::{ComponentRegistry, SpawnedTree, TreeBuilderClone, TreeValueError};
+
+/// Errors of loading a scene fragment.
+#[derive(Debug)]
+pub enum SceneError<E> {
+    /// The document could not be parsed.
+    Parse(E),
+    /// The document does not describe a valid tree.
+    Tree(TreeValueError),
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for SceneError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SceneError::Parse(err) => write!(f, "Failed to parse scene: {}", err),
+            SceneError::Tree(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for SceneError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SceneError::Parse(err) => Some(err),
+            SceneError::Tree(err) => Some(err),
+        }
+    }
+}
+
+impl<E> From<TreeValueError> for SceneError<E> {
+    fn from(err: TreeValueError) -> Self {
+        SceneError::Tree(err)
+    }
+}
+
+/// Parses a scene fragment into a builder of hierarchy `T`, which can be spawned any number of
+/// times.
+pub fn load<'de, T: Component, D: Deserializer<'de>>(
+    registry: &ComponentRegistry,
+    deserializer: D,
+) -> Result<TreeBuilderClone<T>, SceneError<D::Error>> {
+    let value = Value::deserialize(deserializer).map_err(SceneError::Parse)?;
+    Ok(TreeBuilderClone::from_value(registry, value)?)
+}
+
+/// Parses a scene fragment and spawns it into `frame` as a tree of hierarchy `T`. Returns the root
+/// along with the entities of all labeled nodes.
+pub fn spawn<'de, T: Component, D: Deserializer<'de>>(
+    frame: &mut Frame,
+    registry: &ComponentRegistry,
+    deserializer: D,
+) -> Result<SpawnedTree, SceneError<D::Error>> {
+    Ok(load::<T, _>(registry, deserializer)?.spawn_labeled(frame))
+}
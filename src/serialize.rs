@@ -0,0 +1,73 @@
+//! Serialization of the hierarchy components, for use with the row and column serialization of
+//! `moss_hecs` or any other serde based format.
+//!
+//! Entity references are stored as the bits of the entity. When the entities are spawned with
+//! other ids on deserialization, the references have to be remapped using
+//! [Parent::remap_entities] and [Child::remap_entities], or [remap_entities](crate::remap_entities)
+//! for a whole frame, before the hierarchy is used.
+
+use moss_hecs::Entity;
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{Child, Parent};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Parent")]
+struct ParentData {
+    num_children: usize,
+    last_child: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Child")]
+struct ChildData {
+    parent: u64,
+    next: u64,
+    prev: u64,
+}
+
+fn entity_from_bits<E: Error>(bits: u64) -> Result<Entity, E> {
+    Entity::from_bits(bits).ok_or_else(|| E::custom(format!("invalid entity {:#x}", bits)))
+}
+
+impl<T> Serialize for Parent<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ParentData {
+            num_children: self.num_children,
+            last_child: self.last_child.to_bits().get(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, T: 'static + Send + Sync> Deserialize<'de> for Parent<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = ParentData::deserialize(deserializer)?;
+        Ok(Self::new(
+            data.num_children,
+            entity_from_bits(data.last_child)?,
+        ))
+    }
+}
+
+impl<T> Serialize for Child<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ChildData {
+            parent: self.parent.to_bits().get(),
+            next: self.next.to_bits().get(),
+            prev: self.prev.to_bits().get(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Child<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = ChildData::deserialize(deserializer)?;
+        Ok(Self::new(
+            entity_from_bits(data.parent)?,
+            entity_from_bits(data.next)?,
+            entity_from_bits(data.prev)?,
+        ))
+    }
+}
@@ -0,0 +1,103 @@
+use std::{collections::HashSet, marker::PhantomData};
+
+use moss_hecs::{Component, Entity, Frame};
+use moss_hecs_schedule::GenericWorld;
+
+use crate::{
+    hierarchy::{ensure_alive, link_at, set_children_inner, slot_of},
+    Child, Hierarchy, HierarchyMut, HierarchyResult, Role,
+};
+
+/// The structure of a tree of hierarchy `T` at one point in time, which can be restored later to
+/// roll back structural changes. Only the links between the entities are stored, not their
+/// components.
+pub struct HierarchySnapshot<T> {
+    root: Entity,
+    slot: Option<(Entity, usize)>,
+    /// Every entity of the tree in depth first order, along with its children
+    nodes: Vec<(Entity, Vec<Entity>)>,
+    marker: PhantomData<T>,
+}
+
+impl<T: Component> HierarchySnapshot<T> {
+    /// Captures the structure of the tree below `root`, along with the slot `root` occupies among
+    /// the children of its parent.
+    pub fn capture<W: GenericWorld + Hierarchy>(frame: &W, root: Entity) -> Self {
+        let slot = slot_of::<T, _>(frame, root);
+
+        let nodes = std::iter::once(root)
+            .chain(frame.descendants_depth_first::<T>(root))
+            .map(|entity| (entity, frame.children_vec::<T>(entity)))
+            .collect();
+
+        Self {
+            root,
+            slot,
+            nodes,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the root of the captured tree.
+    pub fn root(&self) -> Entity {
+        self.root
+    }
+
+    /// Reattaches and detaches entities so that the tree below the root matches the snapshot
+    /// again, and moves the root back into its captured slot. Entities which have been attached
+    /// to captured entities since are detached, becoming roots along with their descendants, and
+    /// returned in the order of their ids. Like rolling back a
+    /// [HierarchyTransaction](crate::HierarchyTransaction), restoring returns to a captured state
+    /// and bypasses the enabled [HierarchyPolicy](crate::HierarchyPolicy) of `T`.
+    ///
+    /// Fails with [HierarchyError::NoSuchEntity](crate::HierarchyError::NoSuchEntity) without
+    /// modifying anything if a captured entity has been despawned.
+    pub fn restore(&self, frame: &mut Frame) -> HierarchyResult<Vec<Entity>> {
+        if let Some((parent, _)) = self.slot {
+            ensure_alive(frame, parent, Role::Parent)?;
+        }
+
+        for (entity, children) in &self.nodes {
+            ensure_alive(frame, *entity, Role::Parent)?;
+            for &child in children {
+                ensure_alive(frame, child, Role::Child)?;
+            }
+        }
+
+        // The root may have been moved below one of its captured descendants
+        if frame.try_get::<Child<T>>(self.root).is_ok() {
+            frame.detach::<T>(self.root)?;
+        }
+
+        let mut detached = Vec::new();
+        for (entity, children) in &self.nodes {
+            detached.extend(set_children_inner::<T>(frame, *entity, children, false)?);
+        }
+
+        if let Some((parent, index)) = self.slot {
+            link_at::<T>(frame, self.root, parent, index)?;
+        }
+
+        // Children detached from one captured entity may have been linked to another one since
+        let captured = self
+            .nodes
+            .iter()
+            .map(|(entity, _)| *entity)
+            .collect::<HashSet<_>>();
+        detached.retain(|entity| !captured.contains(entity));
+        detached.sort();
+        detached.dedup();
+
+        Ok(detached)
+    }
+}
+
+impl<T> std::fmt::Debug for HierarchySnapshot<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HierarchySnapshot")
+            .field("root", &self.root)
+            .field("slot", &self.slot)
+            .field("nodes", &self.nodes)
+            .finish()
+    }
+}
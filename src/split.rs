@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+
+use moss_hecs::{Component, Entity};
+use moss_hecs_schedule::GenericWorld;
+
+use crate::Hierarchy;
+
+/// A partition of a tree into disjoint subtrees, as returned by [Hierarchy::split_subtrees].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubtreeSplit {
+    /// The entities above the chunks, starting with the root, in depth first order. These were
+    /// split further since their subtrees were too large for a single chunk.
+    pub interior: Vec<Entity>,
+    /// The chunks, each a list of subtree roots in depth first order. Every entity below
+    /// `interior` belongs to the subtree of exactly one of the listed roots.
+    pub chunks: Vec<Vec<Entity>>,
+}
+
+impl SubtreeSplit {
+    pub(crate) fn new<T: Component, W: GenericWorld + Hierarchy>(
+        frame: &W,
+        root: Entity,
+        approx_chunks: usize,
+    ) -> Self {
+        let approx_chunks = approx_chunks.max(1);
+
+        // Measure every subtree bottom up
+        let order = frame.descendants_depth_first::<T>(root).collect::<Vec<_>>();
+        let mut sizes = order
+            .iter()
+            .map(|&entity| (entity, 1))
+            .collect::<HashMap<_, usize>>();
+        for &entity in order.iter().rev() {
+            let size = sizes[&entity];
+            if let Some(parent) = frame
+                .parent::<T>(entity)
+                .ok()
+                .and_then(|parent| sizes.get_mut(&parent))
+            {
+                *parent += size;
+            }
+        }
+
+        // Split subtrees which are larger than a chunk, keeping depth first order
+        let target = (order.len() + approx_chunks - 1) / approx_chunks;
+        let mut interior = vec![root];
+        let mut roots = Vec::new();
+        let mut stack = frame.children_vec::<T>(root);
+        stack.reverse();
+
+        while let Some(entity) = stack.pop() {
+            let children = frame.children_vec::<T>(entity);
+            if sizes[&entity] > target && !children.is_empty() {
+                interior.push(entity);
+                stack.extend(children.into_iter().rev());
+            } else {
+                roots.push(entity);
+            }
+        }
+
+        // Hand the largest subtrees out first, each to the currently smallest chunk
+        let mut by_size = (0..roots.len()).collect::<Vec<_>>();
+        by_size.sort_by_key(|&i| std::cmp::Reverse(sizes[&roots[i]]));
+
+        let mut chunks = vec![(0, Vec::new()); approx_chunks.min(roots.len())];
+        for i in by_size {
+            let (size, chunk) = chunks.iter_mut().min_by_key(|(size, _)| *size).unwrap();
+            *size += sizes[&roots[i]];
+            chunk.push(i);
+        }
+
+        // Keep the subtrees of each chunk in depth first order
+        let chunks = chunks
+            .into_iter()
+            .map(|(_, mut chunk)| {
+                chunk.sort_unstable();
+                chunk.into_iter().map(|i| roots[i]).collect()
+            })
+            .collect();
+
+        Self { interior, chunks }
+    }
+}
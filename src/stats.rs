@@ -0,0 +1,70 @@
+use moss_hecs::{Component, Entity};
+use moss_hecs_schedule::GenericWorld;
+
+use crate::{Hierarchy, Parent};
+
+/// The shape of a tree, as measured by [Hierarchy::stats]. Useful for catching degenerate trees,
+/// such as very deep chains, created by faulty generation code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeStats {
+    /// The number of entities in the tree, including the root
+    pub node_count: usize,
+    /// The number of entities without children
+    pub leaf_count: usize,
+    /// The depth of the deepest entity, where the root is at depth 0
+    pub max_depth: usize,
+    /// The largest number of children of a single entity
+    pub max_children: usize,
+    /// The average number of children of the entities which have children, or 0 if the tree is
+    /// only the root
+    pub average_branching_factor: f32,
+    /// The number of entities at each depth, starting with the root at depth 0
+    pub depth_histogram: Vec<usize>,
+}
+
+impl TreeStats {
+    pub(crate) fn new<T: Component, W: GenericWorld + Hierarchy>(frame: &W, root: Entity) -> Self {
+        let mut stats = Self {
+            node_count: 0,
+            leaf_count: 0,
+            max_depth: 0,
+            max_children: 0,
+            average_branching_factor: 0.0,
+            depth_histogram: Vec::new(),
+        };
+
+        let mut parents = 0;
+        let mut edges = 0;
+        let mut measure = |entity: Entity| {
+            let num_children = frame
+                .try_get::<Parent<T>>(entity)
+                .map(|parent| parent.num_children())
+                .unwrap_or_default();
+
+            if num_children == 0 {
+                stats.leaf_count += 1;
+            } else {
+                parents += 1;
+                edges += num_children;
+            }
+
+            stats.max_children = stats.max_children.max(num_children);
+        };
+
+        measure(root);
+        let mut histogram = vec![1];
+        for layer in frame.layers::<T>(root) {
+            layer.iter().for_each(|&entity| measure(entity));
+            histogram.push(layer.len());
+        }
+
+        stats.node_count = histogram.iter().sum();
+        stats.max_depth = histogram.len() - 1;
+        stats.depth_histogram = histogram;
+        if parents > 0 {
+            stats.average_branching_factor = edges as f32 / parents as f32;
+        }
+
+        stats
+    }
+}
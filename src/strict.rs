@@ -0,0 +1,87 @@
+//! Invariant checks run by the structural operations when the `strict-checks` feature is enabled
+//! in a debug build. Every check panics with a description of the violated invariant. The checks
+//! run after the operations have verified that their entities are alive, so that dead entities
+//! are still reported as [HierarchyError::NoSuchEntity](crate::HierarchyError::NoSuchEntity).
+
+use moss_hecs::{Component, Entity, Frame};
+
+use crate::{Child, Hierarchy, Parent};
+
+/// Panics if attaching `child` to `parent` would create a cycle.
+pub(crate) fn check_acyclic<T: Component>(frame: &Frame, op: &str, child: Entity, parent: Entity) {
+    assert!(
+        child != parent,
+        "{}: entity {:?} can not be attached to itself",
+        op,
+        child
+    );
+
+    assert!(
+        !frame
+            .ancestors::<T>(parent)
+            .any(|ancestor| ancestor == child),
+        "{}: attaching {:?} to its descendant {:?} would create a cycle",
+        op,
+        child,
+        parent
+    );
+}
+
+/// Panics if the sibling ring of `parent` is inconsistent with its child count, contains an entity
+/// twice or links to entities which are not children of `parent`.
+pub(crate) fn check_ring<T: Component>(frame: &Frame, op: &str, parent: Entity) {
+    let (num_children, last_child) = match frame.get::<&Parent<T>>(parent) {
+        Ok(data) => (data.num_children, data.last_child),
+        Err(_) => return,
+    };
+
+    let mut cur = last_child;
+    for i in 0..num_children {
+        let data = frame.get::<&Child<T>>(cur).unwrap_or_else(|_| {
+            panic!(
+                "{}: sibling {:?} in the children of {:?} is not a child",
+                op, cur, parent
+            )
+        });
+
+        assert!(
+            data.parent == parent,
+            "{}: child {:?} is linked into the children of {:?}, but has parent {:?}",
+            op,
+            cur,
+            parent,
+            data.parent
+        );
+
+        let next = data.next;
+        drop(data);
+
+        let prev = frame.get::<&Child<T>>(next).map(|next| next.prev).ok();
+        assert!(
+            prev == Some(cur),
+            "{}: next sibling {:?} of child {:?} does not link back",
+            op,
+            next,
+            cur
+        );
+
+        assert!(
+            next != last_child || i + 1 == num_children,
+            "{}: {:?} has {} children, but its sibling ring closes after {}",
+            op,
+            parent,
+            num_children,
+            i + 1
+        );
+
+        cur = next;
+    }
+
+    assert!(
+        cur == last_child,
+        "{}: {:?} has {} children, but its sibling ring does not close",
+        op,
+        parent,
+        num_children
+    );
+}
@@ -0,0 +1,88 @@
+use std::marker::PhantomData;
+
+use moss_hecs::{Component, Entity, Frame};
+
+use crate::{hierarchy::attach_at, HierarchyMut, HierarchyResult};
+
+/// A subtree of hierarchy `T` which has been cut from its parent by
+/// [HierarchyMut::detach_subtree]. Remembers the slot it was cut from so that it can be pasted
+/// back, either where it was or under a different parent.
+///
+/// The subtree itself remains intact in the world; dropping the handle leaves it as a tree of its
+/// own.
+pub struct DetachedSubtree<T> {
+    root: Entity,
+    parent: Option<Entity>,
+    index: usize,
+    marker: PhantomData<T>,
+}
+
+impl<T: Component> DetachedSubtree<T> {
+    pub(crate) fn new(root: Entity, parent: Option<Entity>, index: usize) -> Self {
+        Self {
+            root,
+            parent,
+            index,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the root of the subtree.
+    pub fn root(&self) -> Entity {
+        self.root
+    }
+
+    /// Returns the parent the subtree was detached from, or `None` if it was not attached.
+    pub fn previous_parent(&self) -> Option<Entity> {
+        self.parent
+    }
+
+    /// Returns the index of the subtree among the children of its previous parent.
+    pub fn previous_index(&self) -> usize {
+        self.index
+    }
+
+    /// Attach the subtree as the last child of `parent`. Returns the root of the subtree.
+    pub fn reattach(self, frame: &mut Frame, parent: Entity) -> HierarchyResult<Entity> {
+        frame.attach::<T>(self.root, parent)
+    }
+
+    /// Attach the subtree to `parent` so that it becomes the child at `index`, or the last child
+    /// if `parent` has fewer children. Returns the root of the subtree.
+    pub fn reattach_at(
+        self,
+        frame: &mut Frame,
+        parent: Entity,
+        index: usize,
+    ) -> HierarchyResult<Entity> {
+        attach_at::<T>(frame, self.root, parent, index)?;
+        Ok(self.root)
+    }
+
+    /// Attach the subtree back into the slot it was detached from. Does nothing if it was not
+    /// attached. Returns the root of the subtree.
+    pub fn restore(self, frame: &mut Frame) -> HierarchyResult<Entity> {
+        match self.parent {
+            Some(parent) => {
+                let index = self.index;
+                self.reattach_at(frame, parent, index)
+            }
+            None => Ok(self.root),
+        }
+    }
+
+    /// Despawn the subtree. Returns the number of despawned entities.
+    pub fn despawn(self, frame: &mut Frame) -> HierarchyResult<usize> {
+        frame.despawn_all::<T>(self.root)
+    }
+}
+
+impl<T> std::fmt::Debug for DetachedSubtree<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DetachedSubtree")
+            .field("root", &self.root)
+            .field("parent", &self.parent)
+            .field("index", &self.index)
+            .finish()
+    }
+}
@@ -0,0 +1,143 @@
+use std::{any::TypeId, collections::HashMap};
+
+use moss_hecs::{Component, Entity, EntityBuilder, Frame};
+
+use crate::{hierarchy::ensure_alive, Hierarchy, HierarchyMut, HierarchyResult, Role};
+
+struct Transfer {
+    type_id: TypeId,
+    copy: fn(&Frame, Entity, &mut EntityBuilder),
+}
+
+/// The component types which [transplant] and [transplant_copy] carry over to the destination
+/// frame. Components of other types, including the `Parent` and `Child` components of other
+/// hierarchies, are left behind.
+#[derive(Default)]
+pub struct TransplantRegistry {
+    components: Vec<Transfer>,
+}
+
+impl TransplantRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers component `C` to be carried over. Registering the same component again does
+    /// nothing.
+    pub fn register<C: Component + Clone>(&mut self) -> &mut Self {
+        let type_id = TypeId::of::<C>();
+        if !self.components.iter().any(|c| c.type_id == type_id) {
+            self.components.push(Transfer {
+                type_id,
+                copy: copy_component::<C>,
+            });
+        }
+
+        self
+    }
+}
+
+impl std::fmt::Debug for TransplantRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TransplantRegistry")
+            .field("components", &self.components.len())
+            .finish()
+    }
+}
+
+fn copy_component<C: Component + Clone>(
+    frame: &Frame,
+    entity: Entity,
+    builder: &mut EntityBuilder,
+) {
+    if let Ok(component) = frame.get::<&C>(entity) {
+        builder.add((*component).clone());
+    }
+}
+
+/// Moves the tree below `root` in hierarchy `T` from `src` into `dst`, carrying over the
+/// components registered in `registry`. The tree is spawned in `dst` with the same structure and
+/// despawned from `src`, detaching it from its parent first. Returns the new root in `dst`.
+///
+/// The components are copied into `dst` and only dropped from `src` once the tree has been
+/// spawned, so `src` is left untouched if spawning fails.
+pub fn transplant<T: Component>(
+    src: &mut Frame,
+    root: Entity,
+    dst: &mut Frame,
+    registry: &TransplantRegistry,
+) -> HierarchyResult<Entity> {
+    ensure_alive(src, root, Role::Parent)?;
+
+    let tree = capture::<T>(src, root);
+    let new_root = spawn_tree::<T>(dst, &tree, |entity, builder| {
+        for component in &registry.components {
+            (component.copy)(src, entity, builder);
+        }
+    })?;
+
+    if src.parent::<T>(root).is_ok() {
+        src.detach::<T>(root)?;
+    }
+    src.despawn_all::<T>(root)?;
+
+    Ok(new_root)
+}
+
+/// Same as [transplant], but copies the tree, leaving `src` untouched.
+pub fn transplant_copy<T: Component>(
+    src: &Frame,
+    root: Entity,
+    dst: &mut Frame,
+    registry: &TransplantRegistry,
+) -> HierarchyResult<Entity> {
+    ensure_alive(src, root, Role::Parent)?;
+
+    let tree = capture::<T>(src, root);
+    spawn_tree::<T>(dst, &tree, |entity, builder| {
+        for component in &registry.components {
+            (component.copy)(src, entity, builder);
+        }
+    })
+}
+
+/// Returns every entity of the tree below `root` in depth first order along with its children.
+fn capture<T: Component>(frame: &Frame, root: Entity) -> Vec<(Entity, Vec<Entity>)> {
+    std::iter::once(root)
+        .chain(frame.descendants_depth_first::<T>(root))
+        .map(|entity| (entity, frame.children_vec::<T>(entity)))
+        .collect()
+}
+
+/// Spawns an entity in `dst` for every entity of `tree`, built by `build`, and links them with
+/// the structure of `tree`. Returns the entity spawned for the root. If linking fails, the spawned
+/// entities are despawned again.
+fn spawn_tree<T: Component>(
+    dst: &mut Frame,
+    tree: &[(Entity, Vec<Entity>)],
+    mut build: impl FnMut(Entity, &mut EntityBuilder),
+) -> HierarchyResult<Entity> {
+    let mut builder = EntityBuilder::new();
+    let map = tree
+        .iter()
+        .map(|&(entity, _)| {
+            build(entity, &mut builder);
+            (entity, dst.spawn(builder.build()))
+        })
+        .collect::<HashMap<_, _>>();
+
+    for (entity, children) in tree {
+        if !children.is_empty() {
+            let children = children.iter().map(|child| map[child]).collect::<Vec<_>>();
+            if let Err(err) = dst.set_children::<T>(map[entity], &children) {
+                for &spawned in map.values() {
+                    let _ = dst.despawn(spawned);
+                }
+                return Err(err);
+            }
+        }
+    }
+
+    Ok(map[&tree[0].0])
+}
@@ -0,0 +1,360 @@
+use std::collections::{HashMap, HashSet};
+
+use moss_hecs::{Component, Entity, Frame};
+use moss_hecs_schedule::GenericWorld;
+
+use crate::{
+    hierarchy::{clear_child, relink_children},
+    Child, Parent,
+};
+
+/// A violated invariant of hierarchy `T`, as reported by [validate].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntegrityError {
+    /// The parent of `child` does not exist or has no `Parent` component.
+    DanglingParent {
+        /// The child referring to the parent
+        child: Entity,
+        /// The missing parent
+        parent: Entity,
+    },
+    /// A sibling link of `child` points to an entity which does not exist or has no `Child`
+    /// component.
+    DanglingSibling {
+        /// The child holding the link
+        child: Entity,
+        /// The missing sibling
+        sibling: Entity,
+    },
+    /// The next sibling of `child` does not link back to it.
+    BrokenLink {
+        /// The child holding the link
+        child: Entity,
+        /// The next sibling, whose previous sibling is not `child`
+        next: Entity,
+    },
+    /// The last child of `parent` is not one of its children, which means neither is the first.
+    InvalidLastChild {
+        /// The parent
+        parent: Entity,
+        /// The entity stored as last child
+        last_child: Entity,
+    },
+    /// Following the sibling links from the first child of `parent` leaves its children or never
+    /// returns to the first child.
+    OpenRing {
+        /// The parent
+        parent: Entity,
+    },
+    /// The child count of `parent` does not match the number of children in its sibling ring.
+    CountMismatch {
+        /// The parent
+        parent: Entity,
+        /// The stored child count
+        expected: usize,
+        /// The number of children in the sibling ring
+        actual: usize,
+    },
+    /// `child` refers to `parent`, but is not part of its sibling ring.
+    NotInRing {
+        /// The child
+        child: Entity,
+        /// The parent it refers to
+        parent: Entity,
+    },
+    /// `entity` is its own ancestor.
+    Cycle {
+        /// An entity on the cycle
+        entity: Entity,
+    },
+}
+
+struct Links {
+    parent: Entity,
+    next: Entity,
+    prev: Entity,
+}
+
+/// Checks the invariants of hierarchy `T` in `frame`: child counts match, sibling rings are
+/// closed, the first and last child of every parent are its children, children refer to live
+/// parents and there are no cycles. Returns every violation found.
+///
+/// The check visits every `Parent` and `Child` of `T` once, and is intended for asserting sanity
+/// in tests and debug builds.
+pub fn validate<T: Component, W: GenericWorld>(frame: &W) -> Result<(), Vec<IntegrityError>> {
+    let children = frame
+        .try_query::<&Child<T>>()
+        .map(|mut query| {
+            query
+                .iter()
+                .map(|(entity, child)| {
+                    let links = Links {
+                        parent: child.parent,
+                        next: child.next,
+                        prev: child.prev,
+                    };
+                    (entity, links)
+                })
+                .collect::<HashMap<_, _>>()
+        })
+        .unwrap_or_default();
+
+    let parents = frame
+        .try_query::<&Parent<T>>()
+        .map(|mut query| {
+            query
+                .iter()
+                .map(|(entity, parent)| (entity, (parent.num_children, parent.last_child)))
+                .collect::<HashMap<_, _>>()
+        })
+        .unwrap_or_default();
+
+    let mut errors = Vec::new();
+    let mut in_ring = HashSet::new();
+
+    for (&parent, &(num_children, last_child)) in &parents {
+        if num_children == 0 {
+            continue;
+        }
+
+        let first = match children.get(&last_child) {
+            Some(links) if links.parent == parent => links.next,
+            _ => {
+                errors.push(IntegrityError::InvalidLastChild { parent, last_child });
+                continue;
+            }
+        };
+
+        let mut cur = first;
+        let mut count = 0;
+        loop {
+            match children.get(&cur) {
+                Some(links) if links.parent == parent && count <= children.len() => {
+                    in_ring.insert(cur);
+                    count += 1;
+                    cur = links.next;
+                }
+                _ => {
+                    errors.push(IntegrityError::OpenRing { parent });
+                    break;
+                }
+            }
+
+            if cur == first {
+                if count != num_children {
+                    errors.push(IntegrityError::CountMismatch {
+                        parent,
+                        expected: num_children,
+                        actual: count,
+                    });
+                }
+                break;
+            }
+        }
+    }
+
+    for (&child, links) in &children {
+        if !parents.contains_key(&links.parent) {
+            errors.push(IntegrityError::DanglingParent {
+                child,
+                parent: links.parent,
+            });
+        } else if !in_ring.contains(&child) {
+            errors.push(IntegrityError::NotInRing {
+                child,
+                parent: links.parent,
+            });
+        }
+
+        for &sibling in &[links.prev, links.next] {
+            if !children.contains_key(&sibling) {
+                errors.push(IntegrityError::DanglingSibling { child, sibling });
+            }
+        }
+
+        if let Some(next) = children.get(&links.next) {
+            if next.prev != child {
+                errors.push(IntegrityError::BrokenLink {
+                    child,
+                    next: links.next,
+                });
+            }
+        }
+    }
+
+    // Walk up from every child, stopping at entities already walked from an earlier child
+    let mut visited = HashSet::new();
+    for &start in children.keys() {
+        let mut on_path = HashSet::new();
+        let mut cur = start;
+
+        while let Some(links) = children.get(&cur) {
+            if visited.contains(&cur) {
+                break;
+            }
+
+            if !on_path.insert(cur) {
+                errors.push(IntegrityError::Cycle { entity: cur });
+                break;
+            }
+
+            cur = links.parent;
+        }
+
+        visited.extend(on_path);
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Repairs the recoverable inconsistencies of hierarchy `T` reported by [validate], making a best
+/// effort to keep as much of the hierarchy as possible. Returns the violations which were found,
+/// which is empty if the hierarchy was already consistent.
+///
+/// - Children of despawned parents are detached, becoming roots along with their descendants.
+/// - Cycles are broken by detaching one entity of each cycle.
+/// - The sibling ring and child count of every parent are rebuilt from the children referring to
+///   it. The order of the intact part of the old ring is kept, and the remaining children follow
+///   in the order of their ids.
+pub fn repair<T: Component>(frame: &mut Frame) -> Vec<IntegrityError> {
+    let errors = match validate::<T, _>(frame) {
+        Ok(()) => return Vec::new(),
+        Err(errors) => errors,
+    };
+
+    let mut children = frame
+        .query::<&Child<T>>()
+        .iter()
+        .map(|(entity, child)| {
+            let links = Links {
+                parent: child.parent,
+                next: child.next,
+                prev: child.prev,
+            };
+            (entity, links)
+        })
+        .collect::<HashMap<_, _>>();
+
+    let parents = frame
+        .query::<&Parent<T>>()
+        .iter()
+        .map(|(entity, parent)| (entity, parent.last_child))
+        .collect::<Vec<_>>();
+
+    let mut detached = children
+        .iter()
+        .filter(|(_, links)| !frame.contains(links.parent))
+        .map(|(&child, _)| child)
+        .collect::<Vec<_>>();
+
+    for child in &detached {
+        children.remove(child);
+    }
+
+    let mut visited = HashSet::new();
+    let starts = children.keys().copied().collect::<Vec<_>>();
+    for start in starts {
+        let mut on_path = HashSet::new();
+        let mut cur = start;
+
+        while let Some(links) = children.get(&cur) {
+            if visited.contains(&cur) {
+                break;
+            }
+
+            if !on_path.insert(cur) {
+                children.remove(&cur);
+                detached.push(cur);
+                break;
+            }
+
+            cur = links.parent;
+        }
+
+        visited.extend(on_path);
+    }
+
+    // Keep the intact part of every ring, starting from the first child
+    let mut rings = HashMap::new();
+    let mut placed = HashSet::new();
+    for (parent, last_child) in parents {
+        let mut ring = Vec::new();
+        let mut cur = children
+            .get(&last_child)
+            .filter(|links| links.parent == parent)
+            .map(|links| links.next);
+
+        while let Some(entity) = cur {
+            match children.get(&entity) {
+                Some(links) if links.parent == parent && placed.insert(entity) => {
+                    ring.push(entity);
+                    cur = Some(links.next);
+                }
+                _ => break,
+            }
+        }
+
+        rings.insert(parent, ring);
+    }
+
+    let mut rest = children
+        .iter()
+        .filter(|(child, _)| !placed.contains(*child))
+        .map(|(&child, links)| (links.parent, child))
+        .collect::<Vec<_>>();
+    rest.sort();
+
+    for (parent, child) in rest {
+        rings.entry(parent).or_insert_with(Vec::new).push(child);
+    }
+
+    for child in detached {
+        let _ = clear_child::<T>(frame, child);
+    }
+
+    for (parent, ring) in rings {
+        let _ = relink_children::<T>(frame, parent, &ring);
+    }
+
+    errors
+}
+
+/// Detects the entities of hierarchy `T` which have been despawned directly through the frame
+/// rather than through [HierarchyMut](crate::HierarchyMut), and repairs the hierarchy around them
+/// using [repair]. The sibling rings and child counts of their parents are rebuilt, and their
+/// children are detached, becoming roots. Returns the despawned entities which were still
+/// referenced by the hierarchy, in the order of their ids.
+///
+/// This is cheap when nothing has been despawned, and can be called once per frame when mixing
+/// plain `Frame::despawn` with the hierarchy.
+pub fn sync_despawns<T: Component>(frame: &mut Frame) -> Vec<Entity> {
+    let mut despawned = HashSet::new();
+
+    for (_, child) in frame.query::<&Child<T>>().iter() {
+        for &entity in &[child.parent, child.next, child.prev] {
+            if !frame.contains(entity) {
+                despawned.insert(entity);
+            }
+        }
+    }
+
+    for (_, parent) in frame.query::<&Parent<T>>().iter() {
+        if !frame.contains(parent.last_child) {
+            despawned.insert(parent.last_child);
+        }
+    }
+
+    if despawned.is_empty() {
+        return Vec::new();
+    }
+
+    repair::<T>(frame);
+
+    let mut despawned = despawned.into_iter().collect::<Vec<_>>();
+    despawned.sort();
+    despawned
+}
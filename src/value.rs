@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use moss_hecs::{Component, EntityBuilderClone};
+use serde::de::DeserializeOwned;
+use serde_value::{DeserializerError, Value};
+
+use crate::{
+    builder_clone::{remover, track, Remover},
+    TreeBuilderClone,
+};
+
+type DeserializeFn =
+    fn(Value, &mut EntityBuilderClone, &mut Vec<Remover>) -> Result<(), DeserializerError>;
+
+/// Maps component names to deserializers, which allows [TreeBuilderClone::from_value] to build
+/// trees described in data files.
+#[derive(Default)]
+pub struct ComponentRegistry {
+    deserializers: HashMap<String, DeserializeFn>,
+}
+
+impl ComponentRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers component `C` under `name`, replacing the component previously registered under
+    /// the same name.
+    pub fn register<C: Component + Clone + DeserializeOwned>(
+        &mut self,
+        name: impl Into<String>,
+    ) -> &mut Self {
+        self.deserializers.insert(name.into(), add_component::<C>);
+        self
+    }
+
+    /// Returns true if a component is registered under `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.deserializers.contains_key(name)
+    }
+}
+
+impl std::fmt::Debug for ComponentRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.deserializers.keys()).finish()
+    }
+}
+
+fn add_component<C: Component + Clone + DeserializeOwned>(
+    value: Value,
+    builder: &mut EntityBuilderClone,
+    removers: &mut Vec<Remover>,
+) -> Result<(), DeserializerError> {
+    builder.add(C::deserialize(value)?);
+    track(removers, remover::<C>());
+    Ok(())
+}
+
+/// Errors of building a tree from a value, as returned by [TreeBuilderClone::from_value].
+#[derive(Debug)]
+pub enum TreeValueError {
+    /// A node names a component which is not registered.
+    UnknownComponent(String),
+    /// The value of a registered component could not be deserialized.
+    Component {
+        /// The name of the component
+        name: String,
+        /// The error of its deserializer
+        error: DeserializerError,
+    },
+    /// The value does not describe a tree.
+    Malformed(String),
+}
+
+impl std::fmt::Display for TreeValueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TreeValueError::UnknownComponent(name) => {
+                write!(f, "No component is registered as {:?}", name)
+            }
+            TreeValueError::Component { name, error } => {
+                write!(f, "Invalid value for component {:?}: {}", name, error)
+            }
+            TreeValueError::Malformed(msg) => write!(f, "Malformed tree: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for TreeValueError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TreeValueError::Component { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+fn expect_string(value: Value, what: &str) -> Result<String, TreeValueError> {
+    match value {
+        Value::String(s) => Ok(s),
+        other => Err(TreeValueError::Malformed(format!(
+            "expected {} to be a string, found {:?}",
+            what, other
+        ))),
+    }
+}
+
+impl<T: Component> TreeBuilderClone<T> {
+    /// Builds a tree from a deserialized value, looking up the components of every node by name
+    /// in `registry`. A node is a map with the following optional entries:
+    ///
+    /// - `label`: a string which the node is [labeled](Self::label) with
+    /// - `components`: a map from registered component names to component values
+    /// - `children`: a sequence of nodes
+    ///
+    /// ```ignore
+    /// {
+    ///     "label": "root",
+    ///     "components": { "name": "Root" },
+    ///     "children": [{ "components": { "name": "Child" } }]
+    /// }
+    /// ```
+    pub fn from_value(registry: &ComponentRegistry, value: Value) -> Result<Self, TreeValueError> {
+        let entries = match value {
+            Value::Map(entries) => entries,
+            other => {
+                return Err(TreeValueError::Malformed(format!(
+                    "expected a node to be a map, found {:?}",
+                    other
+                )))
+            }
+        };
+
+        let mut node = Self::new();
+        for (key, value) in entries {
+            match &*expect_string(key, "a node key")? {
+                "label" => {
+                    node.label(expect_string(value, "a label")?);
+                }
+                "components" => {
+                    let components = match value {
+                        Value::Map(components) => components,
+                        other => {
+                            return Err(TreeValueError::Malformed(format!(
+                                "expected components to be a map, found {:?}",
+                                other
+                            )))
+                        }
+                    };
+
+                    for (name, value) in components {
+                        let name = expect_string(name, "a component name")?;
+                        let deserialize = match registry.deserializers.get(&name) {
+                            Some(deserialize) => deserialize,
+                            None => return Err(TreeValueError::UnknownComponent(name)),
+                        };
+
+                        deserialize(value, &mut node.builder, &mut node.components)
+                            .map_err(|error| TreeValueError::Component { name, error })?;
+                    }
+                }
+                "children" => {
+                    let children = match value {
+                        Value::Seq(children) => children,
+                        other => {
+                            return Err(TreeValueError::Malformed(format!(
+                                "expected children to be a sequence, found {:?}",
+                                other
+                            )))
+                        }
+                    };
+
+                    for child in children {
+                        node.attach_tree(Self::from_value(registry, child)?);
+                    }
+                }
+                other => {
+                    return Err(TreeValueError::Malformed(format!(
+                        "unknown node key {:?}",
+                        other
+                    )))
+                }
+            }
+        }
+
+        Ok(node)
+    }
+}
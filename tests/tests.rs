@@ -1,8 +1,19 @@
-use std::collections::HashSet;
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet},
+    hash::{Hash, Hasher},
+};
 
 use moss_hecs::{Entity, Frame};
 use moss_hecs_hierarchy::{
-    Child, Hierarchy, HierarchyMut, HierarchyQuery, TreeBuilder, TreeBuilderClone,
+    aggregate, apply_deltas, derive_down, despawn_all_deferred, diff, flush_despawns, format_tree,
+    from_outline, layout, maintain_hierarchy, propagate_inherited, remap_edge_data, remap_entities,
+    to_outline, transplant, transplant_copy, Child, ChildList, ChildStorage, DepthBuckets,
+    DespawnRecursive, Dirty, DynMarker, EdgeData, Forest, Hierarchy, HierarchyError,
+    HierarchyEvent, HierarchyId, HierarchyIds, HierarchyJournal, HierarchyMut, HierarchyPolicy,
+    HierarchyQuery, HierarchyQueryFull, HierarchyRegistry, HierarchySnapshot, Inherited,
+    IntegrityError, OutlineError, Parent, Removal, ReplicationError, Replicator, Role,
+    SortedChildren, StructuralDelta, TransplantRegistry, TraversalCache, TreeBuilder,
+    TreeBuilderClone, TreeDisplay, TreeEdit,
 };
 use moss_hecs_schedule::{CommandBuffer, GenericWorld, SubWorldRef};
 
@@ -187,7 +198,7 @@ fn despawn() {
     let child4 = frame.attach_new::<Tree, _>(root, ("Child4",)).unwrap();
     let child5 = frame.attach_new::<Tree, _>(root, ("Child5",)).unwrap();
 
-    frame.despawn_all::<Tree>(child3);
+    assert_eq!(frame.despawn_all::<Tree>(child3).unwrap(), 1);
 
     assert_eq!(
         frame
@@ -460,3 +471,3861 @@ fn reserve() {
         assert_eq!(*frame.get::<&&str>(a).unwrap(), b)
     }
 }
+
+#[test]
+fn builder_walk_mut() {
+    let mut frame = Frame::default();
+    let mut builder = TreeBuilder::<Tree>::from(("root",));
+    builder
+        .attach(("child 1",))
+        .attach_tree(TreeBuilder::from(("child 2",)).attach_move(("child 2.1",)));
+
+    builder.walk_mut(|path, node| {
+        node.add(path.len());
+    });
+
+    builder.walk_mut(|path, node| {
+        if path == [1, 0] {
+            node.add("patched");
+        }
+    });
+
+    let root = builder.spawn(&mut frame);
+
+    assert_eq!(*frame.get::<&usize>(root).unwrap(), 0);
+
+    let expected = [("child 1", 1), ("child 2", 1), ("patched", 2)];
+
+    assert_eq!(
+        frame
+            .descendants_depth_first::<Tree>(root)
+            .map(|e| (
+                *frame.get::<&&str>(e).unwrap(),
+                *frame.get::<&usize>(e).unwrap()
+            ))
+            .collect::<Vec<_>>(),
+        expected
+    );
+}
+
+#[test]
+fn builder_clone_walk_mut() {
+    let mut frame = Frame::default();
+    let mut builder = TreeBuilderClone::<Tree>::from(("root",));
+    builder.attach(("child 1",)).attach(("child 2",));
+
+    builder.walk_mut(|path, node| {
+        node.add(path.len());
+    });
+
+    let root = builder.clone().spawn(&mut frame);
+
+    assert_eq!(*frame.get::<&usize>(root).unwrap(), 0);
+    assert!(frame
+        .children::<Tree>(root)
+        .all(|e| *frame.get::<&usize>(e).unwrap() == 1));
+}
+
+#[test]
+fn builder_clone_spawn_n() {
+    let mut frame = Frame::default();
+    let mut cmd = CommandBuffer::new();
+
+    let mut builder = TreeBuilderClone::<Tree>::from(("root",));
+    builder
+        .attach(("child 1",))
+        .attach(TreeBuilderClone::from(("child 2",)).attach_move(("child 2.1",)));
+
+    let mut roots = builder.spawn_n(&mut frame, 10);
+    // Deferred copies only need to reserve entities, which a subworld can do
+    roots.extend({
+        let subframe = SubWorldRef::<HierarchyQuery<Tree>>::new(&frame);
+        builder.spawn_n_deferred(&subframe, &mut cmd, 5)
+    });
+    cmd.execute(&mut frame);
+
+    assert_eq!(roots.len(), 15);
+    assert_eq!(roots.iter().collect::<HashSet<_>>().len(), 15);
+
+    for root in roots {
+        assert_eq!(*frame.get::<&&str>(root).unwrap(), "root");
+        assert_eq!(
+            frame
+                .descendants_depth_first::<Tree>(root)
+                .map(|e| *frame.get::<&&str>(e).unwrap())
+                .collect::<Vec<_>>(),
+            ["child 1", "child 2", "child 2.1"]
+        );
+    }
+}
+
+#[test]
+fn builder_clone_reconcile() {
+    let mut frame = Frame::default();
+
+    let root = TreeBuilderClone::<Tree>::from(("root",))
+        .attach_move(("a",))
+        .attach_move(TreeBuilderClone::from(("b",)).attach_move(("b.1",)))
+        .attach_move(("c",))
+        .spawn(&mut frame);
+
+    let before = frame.children::<Tree>(root).collect::<Vec<_>>();
+
+    TreeBuilderClone::<Tree>::from(("root",))
+        .attach_move(("a",))
+        .attach_move(("B",))
+        .reconcile(&mut frame, root)
+        .unwrap();
+
+    let after = frame.children::<Tree>(root).collect::<Vec<_>>();
+    assert_eq!(after, before[..2]);
+    assert_eq!(*frame.get::<&&str>(after[1]).unwrap(), "B");
+    assert_eq!(frame.children::<Tree>(after[1]).count(), 0);
+    assert!(!frame.contains(before[2]));
+
+    TreeBuilderClone::<Tree>::from(("root",))
+        .attach_move(("a",))
+        .attach_move(("B",))
+        .attach_move(("d",))
+        .reconcile(&mut frame, root)
+        .unwrap();
+
+    assert_eq!(
+        frame
+            .descendants_depth_first::<Tree>(root)
+            .map(|e| *frame.get::<&&str>(e).unwrap())
+            .collect::<Vec<_>>(),
+        ["a", "B", "d"]
+    );
+}
+
+#[test]
+fn builder_clone_reconcile_labels() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("root",));
+
+    TreeBuilderClone::<Tree>::from(("root",))
+        .attach_move(
+            TreeBuilderClone::from(("left",))
+                .label_move("left")
+                .attach_move(
+                    TreeBuilderClone::from(("button",))
+                        .add_tracked_bundle_move((1_u32,))
+                        .label_move("button"),
+                ),
+        )
+        .attach_move(TreeBuilderClone::from(("right",)).label_move("right"))
+        .reconcile(&mut frame, root)
+        .unwrap();
+
+    let children = frame.children_vec::<Tree>(root);
+    assert_eq!(children.len(), 2);
+    let (left, right) = (children[0], children[1]);
+    let button = frame.children_vec::<Tree>(left)[0];
+
+    // Matched by label, the panels are swapped and the button moves without its `u32`
+    TreeBuilderClone::<Tree>::from(("root",))
+        .attach_move(
+            TreeBuilderClone::from(("right",))
+                .label_move("right")
+                .attach_move(TreeBuilderClone::from(("button",)).label_move("button")),
+        )
+        .attach_move(TreeBuilderClone::from(("left",)).label_move("left"))
+        .reconcile(&mut frame, root)
+        .unwrap();
+
+    assert_eq!(frame.children_vec::<Tree>(root), [right, left]);
+    assert_eq!(frame.children_vec::<Tree>(right), [button]);
+    assert!(frame.children_vec::<Tree>(left).is_empty());
+    assert!(frame.get::<&u32>(button).is_err());
+
+    let tree = TreeBuilderClone::<Tree>::from(("root",)).attach_move(
+        TreeBuilderClone::from(("button",))
+            .label_move("button")
+            .revision_move(1),
+    );
+
+    tree.reconcile(&mut frame, root).unwrap();
+    assert_eq!(frame.children_vec::<Tree>(root), [button]);
+    assert!(!frame.contains(left));
+    assert!(!frame.contains(right));
+
+    // The revision is unchanged, so the node is not applied again
+    frame.insert_one(button, "edited").unwrap();
+    tree.reconcile(&mut frame, root).unwrap();
+    assert_eq!(*frame.get::<&&str>(button).unwrap(), "edited");
+}
+
+#[test]
+fn builder_labels() {
+    let mut frame = Frame::default();
+    let mut cmd = CommandBuffer::new();
+
+    let prefab = TreeBuilderClone::<Tree>::from(("window",))
+        .label_move("window")
+        .attach_move(
+            TreeBuilderClone::from(("title bar",))
+                .attach_move(TreeBuilderClone::from(("close",)).label_move("close_button")),
+        );
+
+    let spawned = prefab.clone().spawn_labeled(&mut frame);
+    assert_eq!(spawned.get("window"), Some(spawned.root()));
+
+    let close = spawned.get("close_button").unwrap();
+    assert_eq!(*frame.get::<&&str>(close).unwrap(), "close");
+    assert_eq!(spawned.get("missing"), None);
+
+    let mut tree: TreeBuilder<Tree> = prefab.into();
+    let deferred = tree.spawn_labeled_deferred(&frame, &mut cmd);
+    cmd.execute(&mut frame);
+
+    let close = deferred.get("close_button").unwrap();
+    assert_eq!(*frame.get::<&&str>(close).unwrap(), "close");
+    assert_eq!(frame.root::<Tree>(close).unwrap(), deferred.root());
+}
+
+#[test]
+fn node() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(root, ("Child2",)).unwrap();
+    let child3 = frame.attach_new::<Tree, _>(child2, ("Child3",)).unwrap();
+
+    let node = frame.node::<Tree>(child3);
+
+    assert_eq!(node.entity(), child3);
+    assert_eq!(node.depth(), 2);
+    assert!(!node.is_root());
+    assert_eq!(node.parent().unwrap().entity(), child2);
+    assert_eq!(node.root().unwrap().entity(), root);
+    assert_eq!(node.ancestors().collect::<Vec<_>>(), [child2, root]);
+
+    let root_node = node.root().unwrap();
+    assert!(root_node.is_root());
+    assert_eq!(root_node.depth(), 0);
+    assert_eq!(root_node.num_children(), 2);
+    assert_eq!(root_node.children().collect::<Vec<_>>(), [child1, child2]);
+    assert_eq!(
+        root_node.descendants().collect::<Vec<_>>(),
+        [child1, child2, child3]
+    );
+    assert!(root_node.parent().is_err());
+}
+
+#[test]
+fn descendants_with() {
+    // Root ---- Child 1 (1)
+    //      ---- Child 2
+    //           ------- Child 3 (3)
+
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root", 0_u32));
+    let child1 = frame
+        .attach_new::<Tree, _>(root, ("Child1", 1_u32))
+        .unwrap();
+    let child2 = frame.attach_new::<Tree, _>(root, ("Child2",)).unwrap();
+    let child3 = frame
+        .attach_new::<Tree, _>(child2, ("Child3", 3_u32))
+        .unwrap();
+
+    let mut query = frame.query::<&u32>();
+    let view = query.view();
+
+    assert_eq!(
+        frame
+            .descendants_with::<Tree, _>(root, &view)
+            .map(|(e, value)| (e, *value))
+            .collect::<Vec<_>>(),
+        [(child1, 1), (child3, 3)]
+    );
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct OrderKey(i32);
+
+struct Layers;
+
+impl SortedChildren for Layers {
+    type Key = OrderKey;
+}
+
+#[test]
+fn attach_sorted() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+
+    let a = frame.spawn((OrderKey(3),));
+    let b = frame.spawn((OrderKey(1),));
+    let c = frame.spawn((OrderKey(2),));
+    let d = frame.spawn((OrderKey(1),));
+    let unkeyed = frame.spawn(("Unkeyed",));
+
+    for child in [a, b, unkeyed, c, d] {
+        frame.attach_sorted::<Layers>(child, root).unwrap();
+    }
+
+    assert_eq!(
+        frame.children::<Layers>(root).collect::<Vec<_>>(),
+        [b, d, c, a, unkeyed]
+    );
+}
+
+#[test]
+fn sort_children_by_key() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+
+    let a = frame.attach_new::<Tree, _>(root, (OrderKey(3),)).unwrap();
+    let b = frame.attach_new::<Tree, _>(root, ("Unkeyed",)).unwrap();
+    let c = frame.attach_new::<Tree, _>(root, (OrderKey(1),)).unwrap();
+    let d = frame.attach_new::<Tree, _>(root, (OrderKey(2),)).unwrap();
+    let e = frame.attach_new::<Tree, _>(root, (OrderKey(1),)).unwrap();
+
+    frame.sort_children_by_key::<Tree, OrderKey>(root).unwrap();
+
+    assert_eq!(
+        frame.children::<Tree>(root).collect::<Vec<_>>(),
+        [c, e, d, a, b]
+    );
+
+    // Attaching after sorting appends to the sorted ring
+    let f = frame.attach_new::<Tree, _>(root, ("Last",)).unwrap();
+    assert_eq!(
+        frame.children::<Tree>(root).collect::<Vec<_>>(),
+        [c, e, d, a, b, f]
+    );
+
+    frame.detach::<Tree>(a).unwrap();
+    assert_eq!(
+        frame.children::<Tree>(root).collect::<Vec<_>>(),
+        [c, e, d, b, f]
+    );
+}
+
+#[cfg(feature = "child-index")]
+#[test]
+fn child_index() {
+    use moss_hecs_hierarchy::ChildIndex;
+
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+
+    let children = (0..4)
+        .map(|i| frame.attach_new::<Tree, _>(root, (OrderKey(-i),)).unwrap())
+        .collect::<Vec<_>>();
+
+    let indices = |frame: &Frame| {
+        frame
+            .children::<Tree>(root)
+            .map(|e| frame.get::<&ChildIndex<Tree>>(e).unwrap().index())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(indices(&frame), [0, 1, 2, 3]);
+
+    frame.detach::<Tree>(children[1]).unwrap();
+    assert_eq!(indices(&frame), [0, 1, 2]);
+    assert!(frame.get::<&ChildIndex<Tree>>(children[1]).is_err());
+
+    frame.sort_children_by_key::<Tree, OrderKey>(root).unwrap();
+    assert_eq!(indices(&frame), [0, 1, 2]);
+    assert_eq!(
+        frame.get::<&ChildIndex<Tree>>(children[3]).unwrap().index(),
+        0
+    );
+}
+
+#[test]
+fn ancestors_from_root() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+
+    let mut chain = vec![root];
+    for i in 1..10 {
+        let child = frame
+            .attach_new::<Tree, _>(chain[i - 1], (format!("Child {}", i),))
+            .unwrap();
+        chain.push(child);
+    }
+
+    let leaf = chain.pop().unwrap();
+
+    assert_eq!(
+        frame.ancestors_from_root::<Tree>(leaf).collect::<Vec<_>>(),
+        chain
+    );
+    assert_eq!(frame.ancestors_from_root::<Tree>(root).count(), 0);
+}
+
+#[test]
+fn edges() {
+    let mut frame = Frame::default();
+    let root1 = frame.spawn(("Root1",));
+    let root2 = frame.spawn(("Root2",));
+    let child1 = frame.attach_new::<Tree, _>(root1, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(child1, ("Child2",)).unwrap();
+    let child3 = frame.attach_new::<Tree, _>(root2, ("Child3",)).unwrap();
+
+    let mut edges = frame.edges::<Tree>().unwrap().iter().collect::<Vec<_>>();
+    edges.sort();
+
+    let mut expected = vec![(root1, child1), (child1, child2), (root2, child3)];
+    expected.sort();
+
+    assert_eq!(edges, expected);
+}
+
+#[test]
+fn members() {
+    let mut frame = Frame::default();
+    let root1 = frame.spawn(("Root1",));
+    let root2 = frame.spawn(("Root2",));
+    let _loose = frame.spawn(("Loose",));
+    let child1 = frame.attach_new::<Tree, _>(root1, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(child1, ("Child2",)).unwrap();
+    let child3 = frame.attach_new::<Tree, _>(root2, ("Child3",)).unwrap();
+
+    let mut members = frame.members::<Tree>().unwrap().iter().collect::<Vec<_>>();
+    members.sort();
+
+    let mut expected = vec![root1, root2, child1, child2, child3];
+    expected.sort();
+
+    assert_eq!(members, expected);
+}
+
+#[test]
+fn parents_iter() {
+    let mut frame = Frame::default();
+    let root1 = frame.spawn(("Root1",));
+    let root2 = frame.spawn(("Root2",));
+    let _loose = frame.spawn(("Loose",));
+    let child1 = frame.attach_new::<Tree, _>(root1, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(child1, ("Child2",)).unwrap();
+    let child3 = frame.attach_new::<Tree, _>(root2, ("Child3",)).unwrap();
+
+    let mut pairs = frame
+        .parents_iter::<Tree>()
+        .unwrap()
+        .iter()
+        .collect::<Vec<_>>();
+    pairs.sort();
+
+    let mut expected = vec![
+        (root1, None),
+        (root2, None),
+        (child1, Some(root1)),
+        (child2, Some(child1)),
+        (child3, Some(root2)),
+    ];
+    expected.sort();
+
+    assert_eq!(pairs, expected);
+}
+
+#[test]
+fn forest() {
+    let mut frame = Frame::default();
+    let root1 = frame.spawn(("Root1",));
+    let root2 = frame.spawn(("Root2",));
+    let loose = frame.spawn(("Loose",));
+    let child1 = frame.attach_new::<Tree, _>(root1, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(child1, ("Child2",)).unwrap();
+    let child3 = frame.attach_new::<Tree, _>(root2, ("Child3",)).unwrap();
+
+    let mut forest = Forest::<Tree>::new(&frame).unwrap();
+    assert_eq!(forest.len(), 2);
+    assert_eq!(forest.count_nodes(&frame), 5);
+
+    let mut expected = vec![vec![root1, child1, child2], vec![root2, child3]];
+    if forest.roots()[0] != root1 {
+        expected.reverse();
+    }
+
+    assert_eq!(
+        forest.iter_all_depth_first(&frame).collect::<Vec<_>>(),
+        expected.concat()
+    );
+    assert_eq!(
+        forest.tree_sizes(&frame),
+        expected.iter().map(|tree| tree.len()).collect::<Vec<_>>()
+    );
+
+    let root3 = frame.spawn(("Root3",));
+    frame.attach_new::<Tree, _>(root3, ("Child4",)).unwrap();
+    forest.refresh(&frame).unwrap();
+    assert_eq!(forest.len(), 3);
+
+    assert_eq!(forest.despawn(&mut frame).unwrap(), 7);
+    assert_eq!(
+        frame.iter().map(|e| e.entity()).collect::<Vec<_>>(),
+        [loose]
+    );
+}
+
+#[test]
+fn root_registry() {
+    let mut frame = Frame::default();
+    let root1 = frame.spawn(("Root1",));
+    let child1 = frame.attach_new::<Tree, _>(root1, ("Child1",)).unwrap();
+
+    assert_eq!(frame.registered_roots::<Tree>(), None);
+    frame.enable_root_registry::<Tree>().unwrap();
+    frame.enable_root_registry::<Tree>().unwrap();
+
+    let registered = |frame: &Frame| {
+        frame
+            .registered_roots::<Tree>()
+            .unwrap()
+            .into_iter()
+            .collect::<HashSet<_>>()
+    };
+    let scanned = |frame: &Frame| {
+        frame
+            .roots::<Tree>()
+            .unwrap()
+            .iter()
+            .map(|(e, _)| e)
+            .collect::<HashSet<_>>()
+    };
+
+    assert_eq!(registered(&frame), [root1].iter().copied().collect());
+
+    let root2 = frame.spawn(("Root2",));
+    let child2 = frame.attach_new::<Tree, _>(root2, ("Child2",)).unwrap();
+    frame.attach_new::<Tree, _>(child1, ("Child3",)).unwrap();
+    assert_eq!(registered(&frame), [root1, root2].iter().copied().collect());
+
+    // Detaching a subtree makes it a root of its own
+    frame.detach::<Tree>(child1).unwrap();
+    assert!(frame.get::<&Child<Tree>>(child1).is_err());
+    assert_eq!(
+        registered(&frame),
+        [child1, root2].iter().copied().collect()
+    );
+    assert_eq!(registered(&frame), scanned(&frame));
+
+    // Reattaching moves the child to its new parent
+    frame.attach::<Tree>(child2, child1).unwrap();
+    assert_eq!(frame.parent::<Tree>(child2).unwrap(), child1);
+    assert_eq!(frame.children::<Tree>(root2).count(), 0);
+    assert_eq!(registered(&frame), [child1].iter().copied().collect());
+    assert_eq!(registered(&frame), scanned(&frame));
+
+    frame.despawn_all::<Tree>(child1).unwrap();
+    assert!(registered(&frame).is_empty());
+    assert_eq!(registered(&frame), scanned(&frame));
+}
+
+#[test]
+fn despawn_all_collect() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(child1, ("Child2",)).unwrap();
+    let child3 = frame.attach_new::<Tree, _>(child1, ("Child3",)).unwrap();
+    let child4 = frame.attach_new::<Tree, _>(root, ("Child4",)).unwrap();
+
+    assert_eq!(
+        frame.despawn_all_collect::<Tree>(child1).unwrap(),
+        [child1, child2, child3]
+    );
+    assert_eq!(frame.children::<Tree>(root).collect::<Vec<_>>(), [child4]);
+
+    assert!(frame.despawn_all::<Tree>(child1).is_err());
+
+    frame.despawn_children::<Tree>(root).unwrap();
+    assert!(!frame.contains(child4));
+    assert_eq!(frame.children::<Tree>(root).count(), 0);
+    assert_eq!(frame.despawn_all::<Tree>(root).unwrap(), 1);
+}
+
+#[test]
+fn despawn_all_dangling() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(root, ("Child2",)).unwrap();
+    let grandchild = frame
+        .attach_new::<Tree, _>(child2, ("Grandchild",))
+        .unwrap();
+    let child3 = frame.attach_new::<Tree, _>(root, ("Child3",)).unwrap();
+
+    // Despawned through the frame without detaching
+    frame.despawn(child1).unwrap();
+    assert_eq!(
+        frame.descendants_depth_first::<Tree>(root).count(),
+        0,
+        "traversal ends at the despawned first child rather than panicking"
+    );
+
+    assert_eq!(
+        frame.despawn_all_collect::<Tree>(root).unwrap(),
+        [root, child2, grandchild, child3]
+    );
+    assert!(!frame.contains(grandchild));
+    assert!(!frame.contains(child3));
+
+    let root = frame.spawn(("Root",));
+    let child = frame.attach_new::<Tree, _>(root, ("Child",)).unwrap();
+    frame.despawn(child).unwrap();
+    assert_eq!(frame.despawn_all::<Tree>(root).unwrap(), 1);
+}
+
+#[test]
+fn detach_returns_parent() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(child1, ("Child2",)).unwrap();
+
+    let parent = frame.detach::<Tree>(child2).unwrap();
+    assert_eq!(parent, child1);
+
+    // Move back
+    frame.attach::<Tree>(child2, parent).unwrap();
+    assert_eq!(frame.parent::<Tree>(child2).unwrap(), child1);
+
+    assert_eq!(frame.detach::<Tree>(child1).unwrap(), root);
+    assert!(frame.detach::<Tree>(child1).is_err());
+}
+
+#[test]
+fn swap_subtrees() {
+    // Root ---- Child 1
+    //           ------- Child 2
+    //      ---- Child 3
+    //      ---- Child 4
+    //           ------- Child 5
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(child1, ("Child2",)).unwrap();
+    let child3 = frame.attach_new::<Tree, _>(root, ("Child3",)).unwrap();
+    let child4 = frame.attach_new::<Tree, _>(root, ("Child4",)).unwrap();
+    let child5 = frame.attach_new::<Tree, _>(child4, ("Child5",)).unwrap();
+
+    // Siblings
+    frame.swap_subtrees::<Tree>(child1, child4).unwrap();
+    assert_eq!(
+        frame
+            .descendants_depth_first::<Tree>(root)
+            .collect::<Vec<_>>(),
+        [child4, child5, child3, child1, child2]
+    );
+
+    // Different parents
+    frame.swap_subtrees::<Tree>(child5, child3).unwrap();
+    assert_eq!(
+        frame
+            .descendants_depth_first::<Tree>(root)
+            .collect::<Vec<_>>(),
+        [child4, child3, child5, child1, child2]
+    );
+    assert_eq!(frame.parent::<Tree>(child5).unwrap(), root);
+
+    // With a root
+    let other = frame.spawn(("Other",));
+    let leaf = frame.attach_new::<Tree, _>(other, ("Leaf",)).unwrap();
+    frame.swap_subtrees::<Tree>(child2, other).unwrap();
+    assert_eq!(frame.parent::<Tree>(other).unwrap(), child1);
+    assert!(frame.parent::<Tree>(child2).is_err());
+    assert_eq!(
+        frame
+            .descendants_depth_first::<Tree>(child1)
+            .collect::<Vec<_>>(),
+        [other, leaf]
+    );
+
+    assert!(matches!(
+        frame.swap_subtrees::<Tree>(root, leaf),
+        Err(HierarchyError::Cycle { ancestor, descendant }) if ancestor == root && descendant == leaf
+    ));
+}
+
+#[test]
+fn replace_child() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(root, ("Child2",)).unwrap();
+    let child3 = frame.attach_new::<Tree, _>(child2, ("Child3",)).unwrap();
+    let child4 = frame.attach_new::<Tree, _>(root, ("Child4",)).unwrap();
+
+    let new1 = frame.spawn(("New1",));
+    frame
+        .replace_child::<Tree>(child2, new1, Removal::Detach)
+        .unwrap();
+    assert_eq!(
+        frame.children::<Tree>(root).collect::<Vec<_>>(),
+        [child1, new1, child4]
+    );
+    assert!(frame.parent::<Tree>(child2).is_err());
+    assert_eq!(frame.children::<Tree>(child2).collect::<Vec<_>>(), [child3]);
+
+    let new2 = frame.spawn(("New2",));
+    frame
+        .replace_child::<Tree>(child4, new2, Removal::Despawn)
+        .unwrap();
+    assert_eq!(
+        frame.children::<Tree>(root).collect::<Vec<_>>(),
+        [child1, new1, new2]
+    );
+    assert!(!frame.contains(child4));
+
+    assert!(matches!(
+        frame.replace_child::<Tree>(child1, root, Removal::Detach),
+        Err(HierarchyError::Cycle { .. })
+    ));
+}
+
+#[test]
+fn truncate_depth() {
+    // Root ---- Child 1
+    //           ------- Child 2
+    //                   ------- Child 3
+    //      ---- Child 4
+    //           ------- Child 5
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(child1, ("Child2",)).unwrap();
+    let child3 = frame.attach_new::<Tree, _>(child2, ("Child3",)).unwrap();
+    let child4 = frame.attach_new::<Tree, _>(root, ("Child4",)).unwrap();
+    let child5 = frame.attach_new::<Tree, _>(child4, ("Child5",)).unwrap();
+
+    let removed = frame
+        .truncate_depth::<Tree>(root, 2, Removal::Detach)
+        .unwrap();
+    assert_eq!(removed, [child3]);
+    assert!(frame.parent::<Tree>(child3).is_err());
+
+    let removed = frame
+        .truncate_depth::<Tree>(root, 1, Removal::Despawn)
+        .unwrap();
+    assert_eq!(removed, [child2, child5]);
+    assert!(!frame.contains(child2) && !frame.contains(child5));
+    assert!(frame.contains(child3));
+
+    assert_eq!(
+        frame
+            .descendants_depth_first::<Tree>(root)
+            .collect::<Vec<_>>(),
+        [child1, child4]
+    );
+}
+
+#[test]
+fn prune() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame
+        .attach_new::<Tree, _>(child1, ("Child2", 1_u32))
+        .unwrap();
+    let child3 = frame
+        .attach_new::<Tree, _>(child2, ("Child3", 1_u32))
+        .unwrap();
+    let child4 = frame
+        .attach_new::<Tree, _>(root, ("Child4", 2_u32))
+        .unwrap();
+    let child5 = frame.attach_new::<Tree, _>(root, ("Child5",)).unwrap();
+
+    let mut visited = Vec::new();
+    let removed = frame
+        .prune::<Tree, _>(root, Removal::Detach, |frame, entity| {
+            visited.push(entity);
+            frame.get::<&u32>(entity).is_ok()
+        })
+        .unwrap();
+
+    assert_eq!(removed, [child2, child4]);
+    assert_eq!(visited, [child1, child2, child4, child5]);
+    assert_eq!(
+        frame
+            .descendants_depth_first::<Tree>(root)
+            .collect::<Vec<_>>(),
+        [child1, child5]
+    );
+    assert_eq!(frame.children::<Tree>(child2).collect::<Vec<_>>(), [child3]);
+
+    frame.attach::<Tree>(child2, root).unwrap();
+    let removed = frame
+        .prune::<Tree, _>(root, Removal::Despawn, |frame, entity| {
+            frame.get::<&u32>(entity).is_ok()
+        })
+        .unwrap();
+    assert_eq!(removed, [child2]);
+    assert!(!frame.contains(child2) && !frame.contains(child3));
+}
+
+#[test]
+fn merge_into() {
+    let mut frame = Frame::default();
+    let root1 = frame.spawn(("Root1",));
+    let child1 = frame.attach_new::<Tree, _>(root1, ("Child1",)).unwrap();
+    let root2 = frame.spawn(("Root2",));
+    let root3 = frame.spawn(("Root3",));
+
+    let group = frame
+        .merge_into::<Tree, _>(&[root2, root1, root3], ("Group",))
+        .unwrap();
+
+    assert_eq!(*frame.get::<&&str>(group).unwrap(), "Group");
+    assert_eq!(
+        frame
+            .descendants_depth_first::<Tree>(group)
+            .collect::<Vec<_>>(),
+        [root2, root1, child1, root3]
+    );
+
+    let dead = frame.spawn(("Dead",));
+    frame.despawn(dead).unwrap();
+    let count = frame.iter().count();
+    assert!(frame
+        .merge_into::<Tree, _>(&[root2, dead], ("Group",))
+        .is_err());
+    assert_eq!(frame.iter().count(), count);
+}
+
+#[test]
+fn detach_subtree() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(root, ("Child2",)).unwrap();
+    let child3 = frame.attach_new::<Tree, _>(child2, ("Child3",)).unwrap();
+    let child4 = frame.attach_new::<Tree, _>(root, ("Child4",)).unwrap();
+
+    let subtree = frame.detach_subtree::<Tree>(child2).unwrap();
+    assert_eq!(subtree.root(), child2);
+    assert_eq!(subtree.previous_parent(), Some(root));
+    assert_eq!(subtree.previous_index(), 1);
+    assert_eq!(
+        frame.children::<Tree>(root).collect::<Vec<_>>(),
+        [child1, child4]
+    );
+
+    subtree.restore(&mut frame).unwrap();
+    assert_eq!(
+        frame
+            .descendants_depth_first::<Tree>(root)
+            .collect::<Vec<_>>(),
+        [child1, child2, child3, child4]
+    );
+
+    // Cut and paste
+    let subtree = frame.detach_subtree::<Tree>(child2).unwrap();
+    subtree.reattach_at(&mut frame, root, 0).unwrap();
+    assert_eq!(
+        frame.children::<Tree>(root).collect::<Vec<_>>(),
+        [child2, child1, child4]
+    );
+
+    let subtree = frame.detach_subtree::<Tree>(child2).unwrap();
+    assert_eq!(subtree.despawn(&mut frame).unwrap(), 2);
+    assert!(!frame.contains(child3));
+}
+
+#[test]
+fn builder_graft() {
+    let mut frame = Frame::default();
+    let container = frame.spawn(("Container",));
+    let existing1 = frame
+        .attach_new::<Tree, _>(container, ("Existing1",))
+        .unwrap();
+    let existing2 = frame
+        .attach_new::<Tree, _>(container, ("Existing2",))
+        .unwrap();
+
+    let mut builder = TreeBuilder::<Tree>::from((5_u32,));
+    builder.attach(TreeBuilder::<Tree>::from(("Child1",)).label_move("child1"));
+    builder.attach_tree(
+        TreeBuilder::from(("Child2",))
+            .attach_move(TreeBuilder::<Tree>::from(("Child3",)).label_move("child3")),
+    );
+
+    let spawned = builder.graft_at(&mut frame, container, 1).unwrap();
+    assert_eq!(spawned.root(), container);
+    assert_eq!(*frame.get::<&u32>(container).unwrap(), 5);
+
+    let child1 = spawned.get("child1").unwrap();
+    let child3 = spawned.get("child3").unwrap();
+    let child2 = frame.parent::<Tree>(child3).unwrap();
+    assert_eq!(
+        frame.children::<Tree>(container).collect::<Vec<_>>(),
+        [existing1, child1, child2, existing2]
+    );
+
+    let mut builder = TreeBuilder::<Tree>::new();
+    builder.attach(("Child4",));
+    builder.graft(&mut frame, existing1).unwrap();
+    let names = frame
+        .children::<Tree>(existing1)
+        .map(|child| *frame.get::<&&str>(child).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["Child4"]);
+}
+
+#[test]
+fn reparent_children() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let group = frame.attach_new::<Tree, _>(root, ("Group",)).unwrap();
+    let child1 = frame.attach_new::<Tree, _>(group, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(group, ("Child2",)).unwrap();
+    let child3 = frame.attach_new::<Tree, _>(child2, ("Child3",)).unwrap();
+    let child4 = frame.attach_new::<Tree, _>(root, ("Child4",)).unwrap();
+
+    assert!(matches!(
+        frame.reparent_children::<Tree>(group, child2),
+        Err(HierarchyError::Cycle { .. })
+    ));
+
+    let moved = frame.reparent_children::<Tree>(group, root).unwrap();
+    assert_eq!(moved, [child1, child2]);
+    assert_eq!(frame.children::<Tree>(group).count(), 0);
+    assert_eq!(
+        frame
+            .descendants_depth_first::<Tree>(root)
+            .collect::<Vec<_>>(),
+        [group, child4, child1, child2, child3]
+    );
+    assert_eq!(frame.parent::<Tree>(child2).unwrap(), root);
+}
+
+#[test]
+fn collapse() {
+    // Root ---- Child 1
+    //      ---- Group
+    //           ------- Child 2
+    //           ------- Child 3
+    //                   ------- Child 4
+    //      ---- Child 5
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let group = frame.attach_new::<Tree, _>(root, ("Group",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(group, ("Child2",)).unwrap();
+    let child3 = frame.attach_new::<Tree, _>(group, ("Child3",)).unwrap();
+    let child4 = frame.attach_new::<Tree, _>(child3, ("Child4",)).unwrap();
+    let child5 = frame.attach_new::<Tree, _>(root, ("Child5",)).unwrap();
+
+    assert_eq!(frame.collapse::<Tree>(group).unwrap(), [child2, child3]);
+    assert_eq!(
+        frame.children::<Tree>(root).collect::<Vec<_>>(),
+        [child1, child2, child3, child5]
+    );
+    assert_eq!(frame.children::<Tree>(child3).collect::<Vec<_>>(), [child4]);
+    assert!(frame.contains(group));
+    assert!(frame.parent::<Tree>(group).is_err());
+    assert_eq!(frame.children::<Tree>(group).count(), 0);
+
+    assert!(frame.collapse::<Tree>(root).is_err());
+}
+
+#[test]
+fn validate() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(root, ("Child2",)).unwrap();
+    let child3 = frame.attach_new::<Tree, _>(root, ("Child3",)).unwrap();
+    frame.attach_new::<Tree, _>(child2, ("Child4",)).unwrap();
+
+    assert_eq!(moss_hecs_hierarchy::validate::<Tree, _>(&frame), Ok(()));
+
+    // Despawning without going through the hierarchy leaves dangling links
+    frame.despawn(child2).unwrap();
+
+    let errors = moss_hecs_hierarchy::validate::<Tree, _>(&frame).unwrap_err();
+    assert!(errors.contains(&IntegrityError::OpenRing { parent: root }));
+    assert!(errors.contains(&IntegrityError::DanglingSibling {
+        child: child1,
+        sibling: child2
+    }));
+    assert!(errors.contains(&IntegrityError::DanglingSibling {
+        child: child3,
+        sibling: child2
+    }));
+    assert!(errors.iter().any(
+        |err| matches!(err, IntegrityError::DanglingParent { parent, .. } if *parent == child2)
+    ));
+}
+
+#[test]
+fn repair() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(root, ("Child2",)).unwrap();
+    let child3 = frame.attach_new::<Tree, _>(root, ("Child3",)).unwrap();
+    let child4 = frame.attach_new::<Tree, _>(child2, ("Child4",)).unwrap();
+    let child5 = frame.attach_new::<Tree, _>(child4, ("Child5",)).unwrap();
+
+    assert!(moss_hecs_hierarchy::repair::<Tree>(&mut frame).is_empty());
+
+    frame.despawn(child2).unwrap();
+    assert!(!moss_hecs_hierarchy::repair::<Tree>(&mut frame).is_empty());
+    assert_eq!(moss_hecs_hierarchy::validate::<Tree, _>(&frame), Ok(()));
+
+    assert_eq!(
+        frame.children::<Tree>(root).collect::<Vec<_>>(),
+        [child1, child3]
+    );
+
+    // The orphaned subtree is kept as a tree of its own
+    assert!(frame.parent::<Tree>(child4).is_err());
+    assert_eq!(frame.children::<Tree>(child4).collect::<Vec<_>>(), [child5]);
+}
+
+#[cfg(all(feature = "strict-checks", debug_assertions))]
+#[test]
+#[should_panic(expected = "would create a cycle")]
+fn strict_checks_cycle() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child = frame.attach_new::<Tree, _>(root, ("Child",)).unwrap();
+
+    frame.attach::<Tree>(root, child).unwrap();
+}
+
+#[cfg(all(feature = "strict-checks", debug_assertions))]
+#[test]
+#[should_panic(expected = "attach_front: attaching")]
+fn strict_checks_cycle_front() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child = frame.attach_new::<Tree, _>(root, ("Child",)).unwrap();
+
+    frame.attach_front::<Tree>(root, child).unwrap();
+}
+
+#[cfg(all(feature = "strict-checks", debug_assertions))]
+#[test]
+fn strict_checks_dead_entity() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let dead = frame.spawn(("Dead",));
+    frame.despawn(dead).unwrap();
+
+    // Liveness is reported as an error rather than a panic
+    assert!(matches!(
+        frame.attach::<Tree>(dead, root),
+        Err(HierarchyError::NoSuchEntity { entity, which: Role::Child }) if entity == dead
+    ));
+    assert!(matches!(
+        frame.detach::<Tree>(dead),
+        Err(HierarchyError::NoSuchEntity { .. })
+    ));
+}
+
+#[test]
+fn adopt_orphans() {
+    use std::sync::{Arc, Mutex};
+
+    let mut frame = Frame::default();
+    frame.enable_root_registry::<Tree>().unwrap();
+    let root = frame.spawn(("Root",));
+    let group = frame.attach_new::<Tree, _>(root, ("Group",)).unwrap();
+    let child1 = frame.attach_new::<Tree, _>(group, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(group, ("Child2",)).unwrap();
+    let child3 = frame.attach_new::<Tree, _>(child2, ("Child3",)).unwrap();
+
+    let lost_and_found = frame.spawn(("Lost & Found",));
+    // Despawn the group, and repair the ring of root
+    frame.detach::<Tree>(group).unwrap();
+    frame.despawn(group).unwrap();
+
+    assert!(matches!(
+        frame.adopt_orphans::<Tree>(child3),
+        Err(HierarchyError::Cycle { .. })
+    ));
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let recorded = events.clone();
+    frame.observe::<Tree, _>(move |event| recorded.lock().unwrap().push(format!("{:?}", event)));
+
+    let adopted = frame.adopt_orphans::<Tree>(lost_and_found).unwrap();
+    assert_eq!(adopted, [child1, child2]);
+    let expected = [child1, child2]
+        .iter()
+        .map(|&child| {
+            format!(
+                "{:?}",
+                HierarchyEvent::Attached {
+                    child,
+                    parent: lost_and_found
+                }
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(*events.lock().unwrap(), expected);
+
+    assert_eq!(frame.registered_roots::<Tree>().unwrap(), [lost_and_found]);
+    assert_eq!(
+        frame
+            .descendants_depth_first::<Tree>(lost_and_found)
+            .collect::<Vec<_>>(),
+        [child1, child2, child3]
+    );
+    assert_eq!(moss_hecs_hierarchy::validate::<Tree, _>(&frame), Ok(()));
+}
+
+#[test]
+fn sync_despawns() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(root, ("Child2",)).unwrap();
+    let child3 = frame.attach_new::<Tree, _>(root, ("Child3",)).unwrap();
+    let child4 = frame.attach_new::<Tree, _>(child3, ("Child4",)).unwrap();
+
+    assert!(moss_hecs_hierarchy::sync_despawns::<Tree>(&mut frame).is_empty());
+
+    frame.despawn(child2).unwrap();
+    frame.despawn(child3).unwrap();
+
+    assert_eq!(
+        moss_hecs_hierarchy::sync_despawns::<Tree>(&mut frame),
+        [child2, child3]
+    );
+    assert_eq!(moss_hecs_hierarchy::validate::<Tree, _>(&frame), Ok(()));
+    assert_eq!(frame.children::<Tree>(root).collect::<Vec<_>>(), [child1]);
+    assert!(frame.parent::<Tree>(child4).is_err());
+}
+
+#[test]
+fn children_alive() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let children = (0..6)
+        .map(|i| frame.attach_new::<Tree, _>(root, (i,)).unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        frame.children_alive::<Tree>(root).collect::<Vec<_>>(),
+        children
+    );
+
+    frame.despawn(children[1]).unwrap();
+    frame.despawn(children[4]).unwrap();
+
+    assert_eq!(
+        frame.children_alive::<Tree>(root).collect::<Vec<_>>(),
+        [children[0], children[2], children[3], children[5]]
+    );
+
+    frame.despawn(children[5]).unwrap();
+    assert_eq!(
+        frame.children_alive::<Tree>(root).collect::<Vec<_>>(),
+        [children[0], children[2], children[3]]
+    );
+
+    // Sibling order is kept when the last child is despawned, even against the order of the ids
+    let root = frame.spawn(("Root",));
+    let children = (0..6)
+        .map(|i| {
+            let child = frame.spawn((i,));
+            frame.attach_front::<Tree>(child, root).unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    frame.despawn(children[0]).unwrap();
+    assert_eq!(
+        frame.children_alive::<Tree>(root).collect::<Vec<_>>(),
+        [
+            children[5],
+            children[4],
+            children[3],
+            children[2],
+            children[1]
+        ]
+    );
+
+    frame.despawn(children[3]).unwrap();
+    frame.despawn(children[2]).unwrap();
+    assert_eq!(
+        frame.children_alive::<Tree>(root).collect::<Vec<_>>(),
+        [children[5], children[4], children[1]]
+    );
+}
+
+#[test]
+fn path_between() {
+    // Root ---- Child 1
+    //           ------- Child 2
+    //                   ------- Child 3
+    //      ---- Child 4
+    //           ------- Child 5
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(child1, ("Child2",)).unwrap();
+    let child3 = frame.attach_new::<Tree, _>(child2, ("Child3",)).unwrap();
+    let child4 = frame.attach_new::<Tree, _>(root, ("Child4",)).unwrap();
+    let child5 = frame.attach_new::<Tree, _>(child4, ("Child5",)).unwrap();
+    let other = frame.spawn(("Other",));
+
+    assert_eq!(
+        frame.path_between::<Tree>(child3, child5),
+        Some(vec![child3, child2, child1, root, child4, child5])
+    );
+    assert_eq!(
+        frame.path_between::<Tree>(child1, child3),
+        Some(vec![child1, child2, child3])
+    );
+    assert_eq!(
+        frame.path_between::<Tree>(child3, child1),
+        Some(vec![child3, child2, child1])
+    );
+    assert_eq!(frame.path_between::<Tree>(root, root), Some(vec![root]));
+    assert_eq!(frame.path_between::<Tree>(child3, other), None);
+}
+
+#[test]
+fn no_such_entity() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child = frame.spawn(("Child",));
+    let dead = frame.spawn(("Dead",));
+    frame.despawn(dead).unwrap();
+
+    assert!(matches!(
+        frame.attach::<Tree>(child, dead),
+        Err(HierarchyError::NoSuchEntity { entity, which: Role::Parent }) if entity == dead
+    ));
+    assert!(matches!(
+        frame.attach::<Tree>(dead, root),
+        Err(HierarchyError::NoSuchEntity { entity, which: Role::Child }) if entity == dead
+    ));
+    assert!(matches!(
+        frame.detach::<Tree>(dead),
+        Err(HierarchyError::NoSuchEntity {
+            which: Role::Child,
+            ..
+        })
+    ));
+
+    // Nothing is spawned when the parent is dead
+    let count = frame.iter().count();
+    assert!(frame.attach_new::<Tree, _>(dead, ("New",)).is_err());
+    assert_eq!(frame.iter().count(), count);
+
+    // A live but detached child is not a liveness error
+    assert!(matches!(
+        frame.detach::<Tree>(child),
+        Err(HierarchyError::World(_))
+    ));
+}
+
+#[test]
+fn attach_strict() {
+    let mut frame = Frame::default();
+    let root1 = frame.spawn(("Root1",));
+    let root2 = frame.spawn(("Root2",));
+    let child = frame.spawn(("Child",));
+
+    frame.attach_strict::<Tree>(child, root1).unwrap();
+
+    assert!(matches!(
+        frame.attach_strict::<Tree>(child, root2),
+        Err(HierarchyError::AlreadyAttached { child: c, parent }) if c == child && parent == root1
+    ));
+    assert_eq!(frame.parent::<Tree>(child).unwrap(), root1);
+
+    // attach keeps moving the child
+    frame.attach::<Tree>(child, root2).unwrap();
+    assert_eq!(frame.parent::<Tree>(child).unwrap(), root2);
+}
+
+#[test]
+fn attach_front() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.spawn(("Child1",));
+    let child2 = frame.spawn(("Child2",));
+    let child3 = frame.spawn(("Child3",));
+
+    frame.attach_front::<Tree>(child1, root).unwrap();
+    frame.attach_front::<Tree>(child2, root).unwrap();
+    frame.attach::<Tree>(child3, root).unwrap();
+    assert_eq!(
+        frame.children::<Tree>(root).collect::<Vec<_>>(),
+        [child2, child1, child3]
+    );
+
+    // Moving the last child to the front
+    frame.attach_front::<Tree>(child3, root).unwrap();
+    assert_eq!(
+        frame.children::<Tree>(root).collect::<Vec<_>>(),
+        [child3, child2, child1]
+    );
+    assert_eq!(
+        frame.get::<&Parent<Tree>>(root).unwrap().last_child(),
+        child1
+    );
+}
+
+#[test]
+fn attach_before_after() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(root, ("Child2",)).unwrap();
+    let new1 = frame.spawn(("New1",));
+    let new2 = frame.spawn(("New2",));
+    let new3 = frame.spawn(("New3",));
+
+    frame.attach_before::<Tree>(new1, child1).unwrap();
+    frame.attach_after::<Tree>(new2, child2).unwrap();
+    frame.attach_before::<Tree>(new3, child2).unwrap();
+    assert_eq!(
+        frame.children::<Tree>(root).collect::<Vec<_>>(),
+        [new1, child1, new3, child2, new2]
+    );
+
+    // Moving among siblings
+    frame.attach_after::<Tree>(new1, new2).unwrap();
+    frame.attach_before::<Tree>(child2, child1).unwrap();
+    assert_eq!(
+        frame.children::<Tree>(root).collect::<Vec<_>>(),
+        [child2, child1, new3, new2, new1]
+    );
+    assert_eq!(frame.get::<&Parent<Tree>>(root).unwrap().last_child(), new1);
+
+    assert!(frame.attach_after::<Tree>(new1, root).is_err());
+    assert!(matches!(
+        frame.attach_after::<Tree>(root, child1),
+        Err(HierarchyError::Cycle { .. })
+    ));
+}
+
+#[test]
+fn set_children() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let other = frame.spawn(("Other",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(root, ("Child2",)).unwrap();
+    let child3 = frame.attach_new::<Tree, _>(root, ("Child3",)).unwrap();
+    let moved = frame.attach_new::<Tree, _>(other, ("Moved",)).unwrap();
+    let new = frame.spawn(("New",));
+
+    let detached = frame
+        .set_children::<Tree>(root, &[child3, new, moved, child1, new])
+        .unwrap();
+
+    assert_eq!(detached, [child2]);
+    assert_eq!(
+        frame.children::<Tree>(root).collect::<Vec<_>>(),
+        [child3, new, moved, child1]
+    );
+    assert!(frame.get::<&Child<Tree>>(child2).is_err());
+    assert!(frame.get::<&Parent<Tree>>(other).is_err());
+    assert_eq!(moss_hecs_hierarchy::validate::<Tree, _>(&frame), Ok(()));
+
+    assert!(matches!(
+        frame.set_children::<Tree>(child1, &[root]),
+        Err(HierarchyError::Cycle { .. })
+    ));
+
+    assert_eq!(
+        frame.set_children::<Tree>(root, &[]).unwrap(),
+        [child3, new, moved, child1]
+    );
+    assert!(frame.get::<&Parent<Tree>>(root).is_err());
+}
+
+#[test]
+fn children_vec() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(root, ("Child2",)).unwrap();
+    let child3 = frame.attach_new::<Tree, _>(root, ("Child3",)).unwrap();
+
+    assert_eq!(frame.children_vec::<Tree>(root), [child1, child2, child3]);
+    assert_eq!(
+        frame.children_vec::<Tree>(root),
+        frame.children::<Tree>(root).collect::<Vec<_>>()
+    );
+    assert!(frame.children_vec::<Tree>(child1).is_empty());
+
+    // Mutate while walking the snapshot
+    for child in frame.children_vec::<Tree>(root) {
+        frame.detach::<Tree>(child).unwrap();
+    }
+    assert!(frame.children_vec::<Tree>(root).is_empty());
+}
+
+#[test]
+fn children_exact_size() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    frame.attach_new::<Tree, _>(root, ("Child2",)).unwrap();
+    frame.attach_new::<Tree, _>(root, ("Child3",)).unwrap();
+
+    let mut children = frame.children::<Tree>(root);
+    assert_eq!(children.len(), 3);
+    children.next();
+    assert_eq!(children.len(), 2);
+    assert_eq!(children.size_hint(), (2, Some(2)));
+    children.next();
+    children.next();
+    assert_eq!(children.len(), 0);
+    assert_eq!(children.next(), None);
+
+    let empty = frame.spawn(("Empty",));
+    assert_eq!(frame.children::<Tree>(empty).len(), 0);
+}
+
+#[test]
+fn iterator_size_hints() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(root, ("Child2",)).unwrap();
+    let grandchild = frame
+        .attach_new::<Tree, _>(child1, ("Grandchild",))
+        .unwrap();
+
+    let mut dfs = frame.descendants_depth_first::<Tree>(root);
+    assert_eq!(dfs.size_hint(), (2, None));
+    assert_eq!(dfs.next(), Some(child1));
+    assert_eq!(dfs.size_hint(), (2, None));
+    assert_eq!(dfs.by_ref().count(), 2);
+    assert_eq!(dfs.size_hint(), (0, Some(0)));
+    assert_eq!(dfs.next(), None);
+
+    let mut bfs = frame.descendants_breadth_first::<Tree>(root);
+    assert_eq!(bfs.size_hint(), (2, None));
+    assert_eq!(
+        bfs.by_ref().collect::<Vec<_>>(),
+        [child1, child2, grandchild]
+    );
+    assert_eq!(bfs.size_hint(), (0, Some(0)));
+    assert_eq!(bfs.next(), None);
+
+    let mut ancestors = frame.ancestors::<Tree>(grandchild);
+    assert_eq!(ancestors.by_ref().count(), 2);
+    assert_eq!(ancestors.next(), None);
+
+    assert_eq!(frame.ancestors_from_root::<Tree>(grandchild).len(), 2);
+}
+
+#[test]
+fn traversal_cache() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(root, ("Child2",)).unwrap();
+    let grandchild = frame
+        .attach_new::<Tree, _>(child1, ("Grandchild",))
+        .unwrap();
+
+    let mut cache = TraversalCache::new();
+    for _ in 0..2 {
+        assert_eq!(
+            frame
+                .descendants_depth_first_in::<Tree>(root, &mut cache)
+                .collect::<Vec<_>>(),
+            frame
+                .descendants_depth_first::<Tree>(root)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            frame
+                .descendants_breadth_first_in::<Tree>(root, &mut cache)
+                .collect::<Vec<_>>(),
+            [child1, child2, grandchild]
+        );
+    }
+
+    // An abandoned traversal does not leak into the next one
+    frame
+        .descendants_depth_first_in::<Tree>(root, &mut cache)
+        .next();
+    assert_eq!(
+        frame
+            .descendants_depth_first_in::<Tree>(child1, &mut cache)
+            .collect::<Vec<_>>(),
+        [grandchild]
+    );
+}
+
+#[test]
+fn breadth_first_large() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+
+    // Three levels of ten children exceed the inline capacity of the frontier
+    let mut level = vec![root];
+    let mut expected = Vec::new();
+    for _ in 0..3 {
+        let mut next = Vec::new();
+        for &parent in &level {
+            for _ in 0..10 {
+                next.push(frame.attach_new::<Tree, _>(parent, ("Node",)).unwrap());
+            }
+        }
+        expected.extend(&next);
+        level = next;
+    }
+
+    assert_eq!(
+        frame
+            .descendants_breadth_first::<Tree>(root)
+            .collect::<Vec<_>>(),
+        expected
+    );
+
+    let mut cache = TraversalCache::new();
+    assert_eq!(
+        frame
+            .descendants_breadth_first_in::<Tree>(root, &mut cache)
+            .collect::<Vec<_>>(),
+        expected
+    );
+}
+
+#[test]
+fn despawn_deep_chain() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+
+    let mut tail = root;
+    for _ in 0..100_000 {
+        tail = frame.attach_new::<Tree, _>(tail, ("Link",)).unwrap();
+    }
+
+    assert_eq!(frame.despawn_all::<Tree>(root).unwrap(), 100_001);
+    assert!(!frame.contains(root));
+    assert!(!frame.contains(tail));
+}
+
+#[test]
+fn child_lists() {
+    struct Listed;
+
+    impl ChildStorage for Listed {
+        const CHILD_LISTS: bool = true;
+    }
+
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Listed, _>(root, ("Child1",)).unwrap();
+
+    frame.enable_child_storage::<Listed>().unwrap();
+    let list = |frame: &Frame, parent: Entity| {
+        frame
+            .get::<&ChildList<Listed>>(parent)
+            .map(|list| list.as_slice().to_vec())
+            .ok()
+    };
+    assert_eq!(list(&frame, root), Some(vec![child1]));
+
+    let child2 = frame.attach_new::<Listed, _>(root, ("Child2",)).unwrap();
+    let child3 = frame.spawn(("Child3",));
+    frame.attach_front::<Listed>(child3, root).unwrap();
+    let grandchild = frame
+        .attach_new::<Listed, _>(child1, ("Grandchild",))
+        .unwrap();
+    assert_eq!(list(&frame, root), Some(vec![child3, child1, child2]));
+    assert_eq!(list(&frame, child1), Some(vec![grandchild]));
+
+    frame.detach::<Listed>(child1).unwrap();
+    frame.sort_children_by_key::<Listed, &str>(root).unwrap();
+    assert_eq!(
+        list(&frame, root),
+        Some(frame.children::<Listed>(root).collect())
+    );
+
+    frame.detach::<Listed>(grandchild).unwrap();
+    assert_eq!(list(&frame, child1), None);
+
+    frame.despawn_children::<Listed>(root).unwrap();
+    assert_eq!(list(&frame, root), None);
+}
+
+#[test]
+fn children_with() {
+    struct Visible;
+
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame
+        .attach_new::<Tree, _>(root, ("Child1", Visible))
+        .unwrap();
+    frame.attach_new::<Tree, _>(root, ("Child2",)).unwrap();
+    let child3 = frame
+        .attach_new::<Tree, _>(root, ("Child3", Visible))
+        .unwrap();
+    frame
+        .attach_new::<Tree, _>(child1, ("Grandchild", Visible))
+        .unwrap();
+
+    assert_eq!(
+        frame
+            .children_with::<Tree, Visible>(root)
+            .collect::<Vec<_>>(),
+        [child1, child3]
+    );
+    assert_eq!(frame.children_with::<Tree, Visible>(child3).count(), 0);
+}
+
+#[test]
+fn ancestors_with() {
+    struct Theme(&'static str);
+
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root", Theme("Dark")));
+    let panel = frame.attach_new::<Tree, _>(root, ("Panel",)).unwrap();
+    let group = frame
+        .attach_new::<Tree, _>(panel, ("Group", Theme("Light")))
+        .unwrap();
+    let button = frame.attach_new::<Tree, _>(group, ("Button",)).unwrap();
+
+    let themes = frame
+        .ancestors_with::<Tree, Theme>(button)
+        .map(|(entity, theme)| (entity, theme.0))
+        .collect::<Vec<_>>();
+    assert_eq!(themes, [(group, "Light"), (root, "Dark")]);
+
+    let nearest = frame
+        .ancestors_with::<Tree, Theme>(panel)
+        .next()
+        .map(|(entity, _)| entity);
+    assert_eq!(nearest, Some(root));
+    assert!(frame.ancestors_with::<Tree, Theme>(root).next().is_none());
+}
+
+#[test]
+fn find_descendant_ancestor() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(root, ("Target",)).unwrap();
+    let grandchild = frame.attach_new::<Tree, _>(child1, ("Target",)).unwrap();
+
+    let is_target = |frame: &Frame, entity: Entity| {
+        frame
+            .get::<&&str>(entity)
+            .map(|name| *name == "Target")
+            .unwrap_or(false)
+    };
+
+    // Depth first reaches the grandchild before the second child
+    assert_eq!(
+        frame.find_descendant::<Tree, _>(root, is_target),
+        Some(grandchild)
+    );
+    assert_eq!(frame.find_descendant::<Tree, _>(child2, is_target), None);
+
+    let mut visited = 0;
+    frame.find_descendant::<Tree, _>(root, |_, _| {
+        visited += 1;
+        true
+    });
+    assert_eq!(visited, 1);
+
+    assert_eq!(
+        frame.find_ancestor::<Tree, _>(grandchild, |_, entity| entity != child1),
+        Some(root)
+    );
+    assert_eq!(frame.find_ancestor::<Tree, _>(grandchild, is_target), None);
+}
+
+#[test]
+fn first_last_child() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+
+    assert_eq!(frame.first_child::<Tree>(root).unwrap(), child1);
+    assert_eq!(frame.last_child::<Tree>(root).unwrap(), child1);
+
+    let child2 = frame.attach_new::<Tree, _>(root, ("Child2",)).unwrap();
+    let child3 = frame.spawn(("Child3",));
+    frame.attach_front::<Tree>(child3, root).unwrap();
+
+    assert_eq!(frame.first_child::<Tree>(root).unwrap(), child3);
+    assert_eq!(frame.last_child::<Tree>(root).unwrap(), child2);
+
+    assert!(frame.first_child::<Tree>(child1).is_err());
+    assert!(frame.last_child::<Tree>(child1).is_err());
+}
+
+#[test]
+fn layers() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(root, ("Child2",)).unwrap();
+    let grandchild1 = frame
+        .attach_new::<Tree, _>(child1, ("Grandchild1",))
+        .unwrap();
+    let grandchild2 = frame
+        .attach_new::<Tree, _>(child2, ("Grandchild2",))
+        .unwrap();
+    let leaf = frame.attach_new::<Tree, _>(grandchild2, ("Leaf",)).unwrap();
+
+    assert_eq!(
+        frame.layers::<Tree>(root).collect::<Vec<_>>(),
+        [
+            vec![child1, child2],
+            vec![grandchild1, grandchild2],
+            vec![leaf]
+        ]
+    );
+    assert_eq!(frame.layers::<Tree>(leaf).count(), 0);
+}
+
+#[test]
+fn sorted_depth_first() {
+    #[derive(PartialEq, Eq, PartialOrd, Ord)]
+    struct Order(i32);
+
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let a = frame.spawn(("A", Order(2)));
+    let b = frame.spawn(("B", Order(1)));
+    let c = frame.spawn(("C",));
+    let d = frame.spawn(("D", Order(0)));
+
+    // Attached in an order unrelated to both the ids and the keys
+    frame.attach::<Tree>(c, root).unwrap();
+    frame.attach::<Tree>(a, root).unwrap();
+    frame.attach::<Tree>(d, c).unwrap();
+    frame.attach::<Tree>(b, root).unwrap();
+
+    assert_eq!(
+        frame
+            .descendants_depth_first_sorted::<Tree>(root)
+            .collect::<Vec<_>>(),
+        [a, b, c, d]
+    );
+    assert_eq!(
+        frame
+            .descendants_depth_first_sorted_by_key::<Tree, Order>(root)
+            .collect::<Vec<_>>(),
+        [b, a, c, d]
+    );
+}
+
+#[test]
+fn descendants_excluding() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(root, ("Child2",)).unwrap();
+    frame
+        .attach_new::<Tree, _>(child1, ("Grandchild1",))
+        .unwrap();
+    let grandchild2 = frame
+        .attach_new::<Tree, _>(child2, ("Grandchild2",))
+        .unwrap();
+
+    assert_eq!(
+        frame
+            .descendants_excluding::<Tree>(root, child1)
+            .collect::<Vec<_>>(),
+        [child2, grandchild2]
+    );
+    let unrelated = frame.spawn(("Unrelated",));
+    assert_eq!(
+        frame.descendants_excluding::<Tree>(root, unrelated).count(),
+        4
+    );
+    assert_eq!(frame.descendants_excluding::<Tree>(root, root).count(), 0);
+}
+
+#[test]
+fn cross_hierarchy_membership() {
+    struct Focus;
+
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(root, ("Child2",)).unwrap();
+    let grandchild = frame
+        .attach_new::<Tree, _>(child1, ("Grandchild",))
+        .unwrap();
+
+    // A focus tree spanning entities of the scene tree
+    frame.attach::<Focus>(grandchild, child2).unwrap();
+
+    assert_eq!(
+        frame
+            .descendants_in_both::<Tree, Focus>(root)
+            .collect::<Vec<_>>(),
+        [grandchild, child2]
+    );
+    assert_eq!(
+        frame
+            .descendants_not_in::<Tree, Focus>(root)
+            .collect::<Vec<_>>(),
+        [child1]
+    );
+}
+
+#[test]
+fn dynamic_hierarchies() {
+    let mut frame = Frame::default();
+    let mut ids = HierarchyIds::new();
+    let document1 = ids.allocate().unwrap();
+    let document2 = ids.allocate().unwrap();
+    assert_ne!(document1, document2);
+
+    let root = frame.spawn(("Root",));
+    let child1 = frame.spawn(("Child1",));
+    let child2 = frame.spawn(("Child2",));
+
+    document1.attach(&mut frame, child1, root).unwrap();
+    document1.attach(&mut frame, child2, child1).unwrap();
+    document2.attach(&mut frame, child2, root).unwrap();
+
+    assert_eq!(
+        document1.descendants_depth_first(&frame, root),
+        [child1, child2]
+    );
+    assert_eq!(document2.children(&frame, root), [child2]);
+    assert_eq!(document1.parent(&frame, child2).unwrap(), child1);
+    assert_eq!(document2.parent(&frame, child2).unwrap(), root);
+    assert_eq!(
+        document1.descendants_breadth_first(&frame, root),
+        [child1, child2]
+    );
+    assert_eq!(document1.ancestors(&frame, child2), [child1, root]);
+    assert_eq!(document2.roots(&frame).unwrap(), [root]);
+
+    // The generic API sees the same hierarchy
+    let id = HierarchyId::new(document1.index()).unwrap();
+    assert_eq!(id, document1);
+    assert_eq!(document1.index(), 0);
+    assert_eq!(frame.parent::<DynMarker<0>>(child1).unwrap(), root);
+
+    document1.detach(&mut frame, child2).unwrap();
+    assert!(document1.children(&frame, child1).is_empty());
+
+    ids.release(document1);
+    assert!(!ids.is_allocated(document1));
+    assert_eq!(ids.allocate().unwrap(), document1);
+    assert!(HierarchyId::new(moss_hecs_hierarchy::MAX_DYN_HIERARCHIES).is_none());
+
+    // Allocation fails once every id is in use
+    while ids.allocate().is_ok() {}
+    assert!(matches!(
+        ids.allocate(),
+        Err(HierarchyError::IdsExhausted {
+            max: moss_hecs_hierarchy::MAX_DYN_HIERARCHIES
+        })
+    ));
+}
+
+#[test]
+fn mirror() {
+    struct Layout;
+
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(root, ("Child2",)).unwrap();
+    let grandchild = frame
+        .attach_new::<Tree, _>(child2, ("Grandchild",))
+        .unwrap();
+    let stale = frame.spawn(("Stale",));
+    frame.attach::<Layout>(stale, child1).unwrap();
+
+    frame.mirror::<Tree, Layout>(root).unwrap();
+    assert_eq!(
+        frame
+            .descendants_depth_first::<Layout>(root)
+            .collect::<Vec<_>>(),
+        [child1, child2, grandchild]
+    );
+    assert!(frame.get::<&Child<Layout>>(stale).is_err());
+
+    // The snapshot diverges from the live tree
+    frame.detach::<Tree>(child2).unwrap();
+    assert_eq!(frame.parent::<Layout>(child2).unwrap(), root);
+
+    frame.detach::<Layout>(grandchild).unwrap();
+    frame.attach::<Layout>(root, grandchild).unwrap();
+    frame.attach::<Tree>(child2, root).unwrap();
+    assert!(matches!(
+        frame.mirror::<Tree, Layout>(root),
+        Err(HierarchyError::Cycle { .. })
+    ));
+}
+
+#[test]
+fn attach_multi() {
+    struct Focus;
+    struct Layout;
+
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let old = frame.spawn(("Old",));
+    let sibling = frame.attach_new::<Tree, _>(old, ("Sibling",)).unwrap();
+    let child = frame.spawn(("Child",));
+    frame.attach::<Tree>(child, old).unwrap();
+    let last = frame.attach_new::<Tree, _>(old, ("Last",)).unwrap();
+
+    frame.attach_multi::<(Focus, Layout)>(child, root).unwrap();
+    assert_eq!(frame.parent::<Focus>(child).unwrap(), root);
+    assert_eq!(frame.parent::<Layout>(child).unwrap(), root);
+
+    // Attaching root below child creates a cycle in Layout, which leaves Tree and Focus untouched
+    frame.detach::<Focus>(child).unwrap();
+    let result = frame.attach_multi::<(Tree, Focus, Layout)>(root, child);
+    assert!(matches!(result, Err(HierarchyError::Cycle { .. })));
+
+    assert!(frame.get::<&Child<Tree>>(root).is_err());
+    assert!(frame.get::<&Child<Focus>>(root).is_err());
+    assert_eq!(frame.parent::<Tree>(child).unwrap(), old);
+    assert_eq!(frame.children_vec::<Tree>(old), [sibling, child, last]);
+}
+
+#[test]
+fn attach_multi_policy() {
+    struct Single;
+    struct Focus;
+
+    impl HierarchyPolicy for Single {
+        const MAX_CHILDREN: Option<usize> = Some(1);
+    }
+
+    let mut frame = Frame::default();
+    frame.enable_policy::<Single>().unwrap();
+
+    let root = frame.spawn(("Root",));
+    let first = frame.spawn(("First",));
+    let second = frame.spawn(("Second",));
+    frame.attach_multi::<(Focus, Single)>(first, root).unwrap();
+
+    // The policy of the last marker rejects the attachment before Focus is touched
+    assert!(matches!(
+        frame.attach_multi::<(Focus, Single)>(second, root),
+        Err(HierarchyError::TooManyChildren { max: 1, .. })
+    ));
+    assert!(frame.parent::<Focus>(second).is_err());
+    assert!(frame.parent::<Single>(second).is_err());
+    assert_eq!(frame.children_vec::<Focus>(root), [first]);
+}
+
+#[test]
+fn attach_multi_observers() {
+    use std::sync::{Arc, Mutex};
+
+    struct Focus;
+    struct Layout;
+
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child = frame.spawn(("Child",));
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let recorded = events.clone();
+    frame.observe::<Focus, _>(move |event| recorded.lock().unwrap().push(format!("{:?}", event)));
+    let recorded = events.clone();
+    frame.observe::<Layout, _>(move |event| recorded.lock().unwrap().push(format!("{:?}", event)));
+
+    // A failed attachment notifies no one
+    assert!(frame.attach_multi::<(Focus, Layout)>(root, root).is_err());
+    assert!(events.lock().unwrap().is_empty());
+
+    frame.attach_multi::<(Focus, Layout)>(child, root).unwrap();
+    let attached = format!(
+        "{:?}",
+        HierarchyEvent::Attached {
+            child,
+            parent: root
+        }
+    );
+    assert_eq!(*events.lock().unwrap(), [attached.clone(), attached]);
+}
+
+#[test]
+fn hierarchies_of() {
+    struct Focus;
+    struct Unused;
+
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(root, ("Child2",)).unwrap();
+    frame.attach::<Focus>(child2, child1).unwrap();
+    let loose = frame.spawn(("Loose",));
+
+    let mut registry = HierarchyRegistry::new();
+    registry
+        .register::<Tree>("tree")
+        .register::<Focus>("focus")
+        .register_type::<Unused>();
+
+    let memberships = registry.hierarchies_of(&frame, child1);
+    assert_eq!(
+        memberships
+            .iter()
+            .map(|m| (m.name, m.parent, m.num_children))
+            .collect::<Vec<_>>(),
+        [("tree", Some(root), 0), ("focus", None, 1)]
+    );
+    assert_eq!(memberships[1].type_id, std::any::TypeId::of::<Focus>());
+
+    assert_eq!(registry.hierarchies_of(&frame, root)[0].num_children, 2);
+    assert!(registry.hierarchies_of(&frame, loose).is_empty());
+    assert_eq!(registry.names().count(), 3);
+}
+
+#[test]
+fn stats() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    frame.attach_new::<Tree, _>(root, ("Child2",)).unwrap();
+    frame.attach_new::<Tree, _>(root, ("Child3",)).unwrap();
+    let grandchild = frame
+        .attach_new::<Tree, _>(child1, ("Grandchild",))
+        .unwrap();
+
+    let stats = frame.stats::<Tree>(root);
+    assert_eq!(stats.node_count, 5);
+    assert_eq!(stats.leaf_count, 3);
+    assert_eq!(stats.max_depth, 2);
+    assert_eq!(stats.max_children, 3);
+    assert_eq!(stats.average_branching_factor, 2.0);
+    assert_eq!(stats.depth_histogram, [1, 3, 1]);
+
+    let leaf = frame.stats::<Tree>(grandchild);
+    assert_eq!(leaf.node_count, 1);
+    assert_eq!(leaf.max_depth, 0);
+    assert_eq!(leaf.average_branching_factor, 0.0);
+}
+
+#[test]
+fn tree_formatting() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(root, ("Child2",)).unwrap();
+    frame
+        .attach_new::<Tree, _>(child2, ("Grandchild",))
+        .unwrap();
+
+    let label = |frame: &Frame, entity: Entity| frame.get::<&&str>(entity).unwrap().to_string();
+    assert_eq!(
+        format_tree::<Tree, _, _>(&frame, root, label),
+        "Root\n|-------- Child1\n|-------- Child2\n          |-------- Grandchild\n"
+    );
+    assert_eq!(
+        TreeDisplay::<Tree, _, _>::new(&frame, child2, label)
+            .indent(2)
+            .branch("- ")
+            .to_string(),
+        "Child2\n- Grandchild\n"
+    );
+}
+
+#[test]
+#[cfg(not(all(feature = "strict-checks", debug_assertions)))]
+fn tree_formatting_cycle() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child = frame.attach_new::<Tree, _>(root, ("Child",)).unwrap();
+    frame.attach_new::<Tree, _>(root, ("Sibling",)).unwrap();
+    frame.attach::<Tree>(root, child).unwrap();
+
+    // The entity closing the cycle is printed once more without descending into it
+    let label = |frame: &Frame, entity: Entity| frame.get::<&&str>(entity).unwrap().to_string();
+    assert_eq!(
+        format_tree::<Tree, _, _>(&frame, child, label),
+        "Child\n|-------- Root\n          |-------- Child\n          |-------- Sibling\n"
+    );
+}
+
+#[test]
+fn tree_diff() {
+    let mut frame = Frame::default();
+    let spawn = |frame: &mut Frame, parent, key: &'static str| {
+        frame.attach_new::<Tree, _>(parent, (key,)).unwrap()
+    };
+
+    let old = frame.spawn(("A",));
+    let b = spawn(&mut frame, old, "B");
+    let c = spawn(&mut frame, old, "C");
+    spawn(&mut frame, b, "F");
+    spawn(&mut frame, c, "D");
+
+    let new = frame.spawn(("A",));
+    let c = spawn(&mut frame, new, "C");
+    spawn(&mut frame, new, "E");
+    spawn(&mut frame, c, "D");
+    spawn(&mut frame, c, "B");
+
+    assert_eq!(
+        diff::<Tree, &str, _>(&frame, old, new),
+        [
+            TreeEdit::Removed { key: "F" },
+            TreeEdit::Moved {
+                key: "B",
+                parent: Some("C"),
+                index: 1
+            },
+            TreeEdit::Added {
+                key: "E",
+                parent: Some("A"),
+                index: 1
+            },
+        ]
+    );
+    assert!(diff::<Tree, &str, _>(&frame, new, new).is_empty());
+}
+
+#[test]
+fn tree_hash() {
+    let mut frame = Frame::default();
+    let spawn_tree = |frame: &mut Frame| {
+        let root = frame.spawn(("Root",));
+        let a = frame.attach_new::<Tree, _>(root, ("A",)).unwrap();
+        let b = frame.attach_new::<Tree, _>(root, ("B",)).unwrap();
+        let leaf = frame.attach_new::<Tree, _>(a, ("Leaf",)).unwrap();
+        (root, a, b, leaf)
+    };
+
+    let (first, _, _, _) = spawn_tree(&mut frame);
+    let (second, a, b, leaf) = spawn_tree(&mut frame);
+
+    let label = |frame: &Frame, entity: Entity| {
+        let mut hasher = DefaultHasher::new();
+        (*frame.get::<&&str>(entity).unwrap()).hash(&mut hasher);
+        hasher.finish()
+    };
+    let hash = |frame: &Frame, root| frame.tree_hash::<Tree, DefaultHasher, _>(root, label);
+
+    assert_eq!(hash(&frame, first), hash(&frame, second));
+
+    // Moving the leaf changes the structure, but not the set of labels
+    let before = hash(&frame, second);
+    frame.attach::<Tree>(leaf, b).unwrap();
+    assert_ne!(hash(&frame, second), before);
+    frame.attach::<Tree>(leaf, a).unwrap();
+    assert_eq!(hash(&frame, second), before);
+
+    // Reordering the children changes the hash
+    frame.attach_front::<Tree>(b, second).unwrap();
+    assert_ne!(hash(&frame, second), before);
+    frame.attach::<Tree>(b, second).unwrap();
+    assert_eq!(hash(&frame, second), hash(&frame, first));
+
+    *frame.get::<&mut &str>(leaf).unwrap() = "Changed";
+    assert_ne!(hash(&frame, second), hash(&frame, first));
+}
+
+#[test]
+fn snapshot_restore() {
+    let mut frame = Frame::default();
+    let parent = frame.spawn(("Parent",));
+    let first = frame.attach_new::<Tree, _>(parent, ("First",)).unwrap();
+    let root = frame.attach_new::<Tree, _>(parent, ("Root",)).unwrap();
+    let a = frame.attach_new::<Tree, _>(root, ("A",)).unwrap();
+    let b = frame.attach_new::<Tree, _>(root, ("B",)).unwrap();
+    let c = frame.attach_new::<Tree, _>(a, ("C",)).unwrap();
+
+    let snapshot = HierarchySnapshot::<Tree>::capture(&frame, root);
+    assert_eq!(snapshot.root(), root);
+
+    frame.attach::<Tree>(c, b).unwrap();
+    frame.attach_front::<Tree>(b, root).unwrap();
+    let added = frame.attach_new::<Tree, _>(a, ("Added",)).unwrap();
+    frame.attach_front::<Tree>(root, parent).unwrap();
+
+    assert_eq!(snapshot.restore(&mut frame).unwrap(), [added]);
+    assert_eq!(frame.children_vec::<Tree>(parent), [first, root]);
+    assert_eq!(frame.children_vec::<Tree>(root), [a, b]);
+    assert_eq!(frame.children_vec::<Tree>(a), [c]);
+    assert!(frame.children_vec::<Tree>(b).is_empty());
+    assert!(frame.parent::<Tree>(added).is_err());
+
+    // Restoring again is a no-op
+    assert!(snapshot.restore(&mut frame).unwrap().is_empty());
+    assert_eq!(frame.children_vec::<Tree>(root), [a, b]);
+
+    frame.despawn_all::<Tree>(c).unwrap();
+    frame.attach::<Tree>(b, a).unwrap();
+    assert!(matches!(
+        snapshot.restore(&mut frame),
+        Err(HierarchyError::NoSuchEntity { entity, which: Role::Child }) if entity == c
+    ));
+    assert_eq!(frame.parent::<Tree>(b).unwrap(), a);
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn tree_from_value() {
+    use moss_hecs_hierarchy::{ComponentRegistry, TreeValueError};
+    use serde_value::Value;
+
+    let string = |s: &str| Value::String(s.to_string());
+    let map = |entries: Vec<(&str, Value)>| {
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(key, value)| (Value::String(key.to_string()), value))
+                .collect(),
+        )
+    };
+
+    let node = |name: &str, children: Vec<Value>| {
+        map(vec![
+            ("label", string(name)),
+            ("components", map(vec![("name", string(name))])),
+            ("children", Value::Seq(children)),
+        ])
+    };
+
+    let mut registry = ComponentRegistry::new();
+    registry
+        .register::<String>("name")
+        .register::<i32>("health");
+
+    let value = node(
+        "Root",
+        vec![
+            node("Child", vec![]),
+            node("Other", vec![node("Leaf", vec![])]),
+        ],
+    );
+
+    let mut frame = Frame::default();
+    let tree = TreeBuilderClone::<Tree>::from_value(&registry, value)
+        .unwrap()
+        .spawn_labeled(&mut frame);
+
+    let root = tree.root();
+    let names = frame
+        .descendants_depth_first::<Tree>(root)
+        .map(|entity| (*frame.get::<&String>(entity).unwrap()).clone())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["Child", "Other", "Leaf"]);
+    assert_eq!(
+        tree.get("Leaf"),
+        frame.children::<Tree>(tree.get("Other").unwrap()).next()
+    );
+
+    let unknown = map(vec![("components", map(vec![("speed", Value::I32(3))]))]);
+    assert!(matches!(
+        TreeBuilderClone::<Tree>::from_value(&registry, unknown),
+        Err(TreeValueError::UnknownComponent(name)) if name == "speed"
+    ));
+
+    let invalid = map(vec![("components", map(vec![("health", string("full"))]))]);
+    assert!(matches!(
+        TreeBuilderClone::<Tree>::from_value(&registry, invalid),
+        Err(TreeValueError::Component { .. })
+    ));
+}
+
+#[test]
+#[cfg(feature = "scene")]
+fn scene_loading() {
+    use moss_hecs_hierarchy::{scene, ComponentRegistry};
+
+    let mut registry = ComponentRegistry::new();
+    registry.register::<String>("name");
+
+    let document = r#"{
+        "components": { "name": "Root" },
+        "children": [
+            { "label": "first", "components": { "name": "First" } },
+            { "components": { "name": "Second" }, "children": [{ "label": "leaf" }] }
+        ]
+    }"#;
+
+    let mut frame = Frame::default();
+    let mut deserializer = serde_json::Deserializer::from_str(document);
+    let tree = scene::spawn::<Tree, _>(&mut frame, &registry, &mut deserializer).unwrap();
+
+    let root = tree.root();
+    assert_eq!(*frame.get::<&String>(root).unwrap(), "Root");
+    assert_eq!(
+        frame.parent::<Tree>(tree.get("first").unwrap()).unwrap(),
+        root
+    );
+    assert_eq!(frame.descendants_depth_first::<Tree>(root).count(), 3);
+
+    let mut deserializer = serde_json::Deserializer::from_str("{ \"children\": ");
+    assert!(matches!(
+        scene::load::<Tree, _>(&registry, &mut deserializer),
+        Err(scene::SceneError::Parse(_))
+    ));
+}
+
+#[test]
+fn outline() {
+    let outline = "Root\n  Child 1\n    Grandchild\n  Child 2\n";
+
+    let mut frame = Frame::default();
+    let tree = from_outline::<Tree>(outline)
+        .unwrap()
+        .spawn_labeled(&mut frame);
+    let grandchild = tree.get("Grandchild").unwrap();
+    assert_eq!(
+        frame.parent::<Tree>(grandchild).unwrap(),
+        tree.get("Child 1").unwrap()
+    );
+
+    let label = |frame: &Frame, entity: Entity| (*frame.get::<&String>(entity).unwrap()).clone();
+    assert_eq!(
+        to_outline::<Tree, _, _>(&frame, tree.root(), label),
+        outline
+    );
+
+    assert_eq!(
+        from_outline::<Tree>("\n  \n").err(),
+        Some(OutlineError::Empty)
+    );
+    assert_eq!(
+        from_outline::<Tree>("Root\n    Deep").err(),
+        Some(OutlineError::InvalidIndent { line: 2 })
+    );
+    assert_eq!(
+        from_outline::<Tree>("Root\n Odd").err(),
+        Some(OutlineError::InvalidIndent { line: 2 })
+    );
+    assert_eq!(
+        from_outline::<Tree>("Root\n  Child\nOther").err(),
+        Some(OutlineError::MultipleRoots { line: 3 })
+    );
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn serialize_components() {
+    use std::collections::HashMap;
+
+    let mut source = Frame::default();
+    let root = source.spawn(("Root",));
+    source.attach_new::<Tree, _>(root, ("Child 1",)).unwrap();
+    let child2 = source.attach_new::<Tree, _>(root, ("Child 2",)).unwrap();
+    source
+        .attach_new::<Tree, _>(child2, ("Grandchild",))
+        .unwrap();
+
+    let rows = std::iter::once(root)
+        .chain(source.descendants_depth_first::<Tree>(root))
+        .map(|entity| {
+            let label = *source.get::<&&str>(entity).unwrap();
+            let parent = source
+                .get::<&Parent<Tree>>(entity)
+                .ok()
+                .map(|parent| serde_json::to_string(&*parent).unwrap());
+            let child = source
+                .get::<&Child<Tree>>(entity)
+                .ok()
+                .map(|child| serde_json::to_string(&*child).unwrap());
+            (entity, label, parent, child)
+        })
+        .collect::<Vec<_>>();
+
+    // Shift the ids of the destination frame
+    let mut frame = Frame::default();
+    for _ in 0..3 {
+        frame.spawn(("Other",));
+    }
+
+    let map = rows
+        .iter()
+        .map(|&(entity, label, _, _)| (entity, frame.spawn((label,))))
+        .collect::<HashMap<_, _>>();
+
+    for (entity, _, parent, child) in rows {
+        let entity = map[&entity];
+        if let Some(parent) = parent {
+            let mut parent = serde_json::from_str::<Parent<Tree>>(&parent).unwrap();
+            parent.remap_entities(|e| map[&e]);
+            frame.insert_one(entity, parent).unwrap();
+        }
+
+        if let Some(child) = child {
+            let mut child = serde_json::from_str::<Child<Tree>>(&child).unwrap();
+            child.remap_entities(|e| map[&e]);
+            frame.insert_one(entity, child).unwrap();
+        }
+    }
+
+    assert_eq!(moss_hecs_hierarchy::validate::<Tree, _>(&frame), Ok(()));
+
+    let label = |frame: &Frame, entity: Entity| frame.get::<&&str>(entity).unwrap().to_string();
+    assert_eq!(
+        format_tree::<Tree, _, _>(&frame, map[&root], label),
+        format_tree::<Tree, _, _>(&source, root, label)
+    );
+}
+
+#[test]
+fn transplanting() {
+    let mut src = Frame::default();
+    let parent = src.spawn(("Parent",));
+    let root = src.attach_new::<Tree, _>(parent, ("Root", 1)).unwrap();
+    src.attach_new::<Tree, _>(root, ("Child 1", 2)).unwrap();
+    let child2 = src.attach_new::<Tree, _>(root, ("Child 2", 3)).unwrap();
+    src.attach_new::<Tree, _>(child2, ("Grandchild", 4))
+        .unwrap();
+
+    let mut registry = TransplantRegistry::new();
+    registry.register::<&str>();
+
+    let label = |frame: &Frame, entity: Entity| frame.get::<&&str>(entity).unwrap().to_string();
+    let expected = format_tree::<Tree, _, _>(&src, root, label);
+
+    let mut dst = Frame::default();
+    dst.spawn(("Other",));
+
+    let copy = transplant_copy::<Tree>(&src, root, &mut dst, &registry).unwrap();
+    assert_eq!(format_tree::<Tree, _, _>(&dst, copy, label), expected);
+    assert_eq!(format_tree::<Tree, _, _>(&src, root, label), expected);
+
+    let moved = transplant::<Tree>(&mut src, root, &mut dst, &registry).unwrap();
+    assert_eq!(format_tree::<Tree, _, _>(&dst, moved, label), expected);
+    assert!(!src.contains(root));
+    assert!(!src.contains(child2));
+    assert_eq!(src.children::<Tree>(parent).count(), 0);
+
+    // Unregistered components are left behind
+    assert!(dst.get::<&i32>(moved).is_err());
+    assert_eq!(moss_hecs_hierarchy::validate::<Tree, _>(&dst), Ok(()));
+}
+
+#[test]
+fn remapping() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    frame.attach_new::<Tree, _>(root, ("Child 1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(root, ("Child 2",)).unwrap();
+    frame
+        .attach_new::<Tree, _>(child2, ("Grandchild",))
+        .unwrap();
+    frame.set_edge_data::<Tree, _>(child2, 2_u32).unwrap();
+
+    let label = |frame: &Frame, entity: Entity| frame.get::<&&str>(entity).unwrap().to_string();
+    let expected = format_tree::<Tree, _, _>(&frame, root, label);
+
+    let old = std::iter::once(root)
+        .chain(frame.descendants_depth_first::<Tree>(root))
+        .collect::<Vec<_>>();
+
+    // Move every entity of the tree to a new entity
+    let mut map = std::collections::HashMap::new();
+    for entity in old {
+        let label = *frame.get::<&&str>(entity).unwrap();
+        let new = frame.spawn((label,));
+        if let Ok(parent) = frame.remove_one::<Parent<Tree>>(entity) {
+            frame.insert_one(new, parent).unwrap();
+        }
+        if let Ok(child) = frame.remove_one::<Child<Tree>>(entity) {
+            frame.insert_one(new, child).unwrap();
+        }
+        if let Ok(edge) = frame.remove_one::<EdgeData<Tree, u32>>(entity) {
+            frame.insert_one(new, edge).unwrap();
+        }
+        frame.despawn(entity).unwrap();
+        map.insert(entity, new);
+    }
+
+    remap_entities::<Tree>(&mut frame, &map);
+    remap_edge_data::<Tree, u32>(&mut frame, &map);
+
+    assert_eq!(moss_hecs_hierarchy::validate::<Tree, _>(&frame), Ok(()));
+    assert_eq!(
+        format_tree::<Tree, _, _>(&frame, map[&root], label),
+        expected
+    );
+    assert_eq!(
+        frame
+            .children_with_edge_data::<Tree, u32>(map[&root])
+            .map(|(child, data)| (child, **data))
+            .collect::<Vec<_>>(),
+        [(map[&child2], 2)]
+    );
+}
+
+#[test]
+fn replication() {
+    use std::collections::HashMap;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    struct NetId(u32);
+
+    let spawn_peer = || {
+        let mut frame = Frame::default();
+        let entities = (0..4)
+            .map(|id| (NetId(id), frame.spawn((NetId(id),))))
+            .collect::<HashMap<_, _>>();
+        (frame, entities)
+    };
+
+    let (mut sender, local) = spawn_peer();
+    let (mut receiver, remote) = spawn_peer();
+
+    let mut replicator = Replicator::<Tree, NetId>::new();
+    replicator
+        .attach(&mut sender, local[&NetId(1)], local[&NetId(0)])
+        .unwrap();
+    replicator
+        .attach(&mut sender, local[&NetId(2)], local[&NetId(0)])
+        .unwrap();
+    replicator
+        .attach(&mut sender, local[&NetId(3)], local[&NetId(2)])
+        .unwrap();
+    replicator.detach(&mut sender, local[&NetId(1)]).unwrap();
+    replicator
+        .despawn_all(&mut sender, local[&NetId(3)])
+        .unwrap();
+
+    // Failed operations are not recorded
+    assert!(replicator.detach(&mut sender, local[&NetId(1)]).is_err());
+
+    let deltas = replicator.drain();
+    assert_eq!(deltas.len(), 5);
+    assert_eq!(deltas[3], StructuralDelta::Detach { child: NetId(1) });
+    assert!(replicator.deltas().is_empty());
+
+    apply_deltas::<Tree, _, _>(&mut receiver, &deltas, |id| remote.get(id).copied()).unwrap();
+
+    let root = remote[&NetId(0)];
+    assert_eq!(receiver.children_vec::<Tree>(root), [remote[&NetId(2)]]);
+    assert!(receiver.parent::<Tree>(remote[&NetId(1)]).is_err());
+    assert!(!receiver.contains(remote[&NetId(3)]));
+
+    let unknown = [StructuralDelta::Detach { child: NetId(7) }];
+    assert!(matches!(
+        apply_deltas::<Tree, _, _>(&mut receiver, &unknown, |id| remote.get(id).copied()),
+        Err(ReplicationError::UnknownKey(NetId(7)))
+    ));
+}
+
+#[test]
+fn journal_undo_redo() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let a = frame.attach_new::<Tree, _>(root, ("A",)).unwrap();
+    let b = frame.attach_new::<Tree, _>(root, ("B",)).unwrap();
+    let c = frame.attach_new::<Tree, _>(root, ("C",)).unwrap();
+    let loose = frame.spawn(("Loose",));
+
+    let mut journal = HierarchyJournal::<Tree>::new();
+    assert!(!journal.undo(&mut frame).unwrap());
+
+    journal.attach_front(&mut frame, c, root).unwrap();
+    journal.attach(&mut frame, b, a).unwrap();
+    journal.attach_after(&mut frame, loose, c).unwrap();
+    journal.detach(&mut frame, a).unwrap();
+
+    // A failed operation is not recorded
+    let unattached = frame.spawn(());
+    assert!(journal.detach(&mut frame, unattached).is_err());
+
+    let done = |frame: &Frame| {
+        assert_eq!(frame.children_vec::<Tree>(root), [c, loose]);
+        assert_eq!(frame.children_vec::<Tree>(a), [b]);
+        assert!(frame.parent::<Tree>(a).is_err());
+    };
+    done(&frame);
+
+    while journal.undo(&mut frame).unwrap() {}
+    assert_eq!(frame.children_vec::<Tree>(root), [a, b, c]);
+    assert!(frame.parent::<Tree>(loose).is_err());
+    assert!(!journal.can_undo());
+
+    while journal.redo(&mut frame).unwrap() {}
+    done(&frame);
+
+    journal.undo(&mut frame).unwrap();
+    journal.undo(&mut frame).unwrap();
+    assert_eq!(frame.children_vec::<Tree>(root), [c, a]);
+
+    // A new operation discards what could have been redone
+    journal.detach(&mut frame, c).unwrap();
+    assert!(!journal.can_redo());
+    journal.undo(&mut frame).unwrap();
+    assert_eq!(frame.children_vec::<Tree>(root), [c, a]);
+}
+
+#[test]
+fn hierarchy_transaction() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let a = frame.attach_new::<Tree, _>(root, ("A",)).unwrap();
+    let b = frame.attach_new::<Tree, _>(root, ("B",)).unwrap();
+    let loose = frame.spawn(("Loose",));
+
+    let result = frame.hierarchy_transaction::<Tree, _, _>(|tx| {
+        tx.attach_front(b, root)?;
+        tx.attach(loose, a)?;
+        tx.detach(a)?;
+        assert!(tx.frame().parent::<Tree>(a).is_err());
+        tx.detach(root)
+    });
+
+    assert!(result.is_err());
+    assert_eq!(frame.children_vec::<Tree>(root), [a, b]);
+    assert!(frame.children_vec::<Tree>(a).is_empty());
+    assert!(frame.parent::<Tree>(loose).is_err());
+
+    let result = frame.hierarchy_transaction::<Tree, _, _>(|tx| {
+        tx.attach(loose, b)?;
+        tx.attach_before(b, a)
+    });
+
+    assert_eq!(result.unwrap(), b);
+    assert_eq!(frame.children_vec::<Tree>(root), [b, a]);
+    assert_eq!(frame.parent::<Tree>(loose).unwrap(), b);
+}
+
+#[test]
+fn observers() {
+    use std::sync::{Arc, Mutex};
+
+    struct Other;
+
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let a = frame.spawn(("A",));
+    let b = frame.spawn(("B",));
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let recorded = events.clone();
+    frame.observe::<Tree, _>(move |event| recorded.lock().unwrap().push(format!("{:?}", event)));
+
+    // Other hierarchies are not observed
+    frame.attach::<Other>(a, root).unwrap();
+
+    frame.attach::<Tree>(a, root).unwrap();
+    frame.attach_front::<Tree>(b, root).unwrap();
+    frame.detach::<Tree>(a).unwrap();
+    frame.attach::<Tree>(a, b).unwrap();
+    let despawned = frame.despawn_all_collect::<Tree>(b).unwrap();
+
+    let expected = vec![
+        HierarchyEvent::Attached {
+            child: a,
+            parent: root,
+        },
+        HierarchyEvent::Attached {
+            child: b,
+            parent: root,
+        },
+        HierarchyEvent::Detached {
+            child: a,
+            parent: root,
+        },
+        HierarchyEvent::Attached {
+            child: a,
+            parent: b,
+        },
+        HierarchyEvent::Despawned {
+            root: b,
+            entities: &despawned,
+        },
+    ]
+    .iter()
+    .map(|event| format!("{:?}", event))
+    .collect::<Vec<_>>();
+
+    assert_eq!(*events.lock().unwrap(), expected);
+}
+
+#[test]
+fn observers_at_index() {
+    use std::sync::{Arc, Mutex};
+
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let a = frame.attach_new::<Tree, _>(root, ("A",)).unwrap();
+    let b = frame.attach_new::<Tree, _>(root, ("B",)).unwrap();
+    let subtree = frame.detach_subtree::<Tree>(a).unwrap();
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let recorded = events.clone();
+    frame.observe::<Tree, _>(move |event| recorded.lock().unwrap().push(format!("{:?}", event)));
+    let recorded = events.clone();
+    frame.observe::<Layers, _>(move |event| recorded.lock().unwrap().push(format!("{:?}", event)));
+
+    subtree.reattach_at(&mut frame, root, 0).unwrap();
+
+    let mut journal = HierarchyJournal::<Tree>::new();
+    journal.attach(&mut frame, b, a).unwrap();
+    journal.undo(&mut frame).unwrap();
+    journal.redo(&mut frame).unwrap();
+
+    // Moving a sorted child among its siblings is a single attachment
+    let low = frame.spawn((OrderKey(0),));
+    let high = frame.spawn((OrderKey(1),));
+    frame.attach_sorted::<Layers>(low, root).unwrap();
+    frame.attach_sorted::<Layers>(high, root).unwrap();
+    *frame.get::<&mut OrderKey>(low).unwrap() = OrderKey(2);
+    frame.attach_sorted::<Layers>(low, root).unwrap();
+    assert_eq!(frame.children_vec::<Layers>(root), [high, low]);
+
+    let expected = [
+        (a, root),
+        (b, a),
+        (b, root),
+        (b, a),
+        (low, root),
+        (high, root),
+        (low, root),
+    ]
+    .iter()
+    .map(|&(child, parent)| format!("{:?}", HierarchyEvent::Attached { child, parent }))
+    .collect::<Vec<_>>();
+
+    assert_eq!(*events.lock().unwrap(), expected);
+}
+
+#[test]
+fn maintenance() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let a = frame.attach_new::<Tree, _>(root, ("A",)).unwrap();
+    let b = frame.attach_new::<Tree, _>(root, ("B",)).unwrap();
+    let c = frame.attach_new::<Tree, _>(a, ("C",)).unwrap();
+    let other = frame.spawn(("Other",));
+    frame.attach_new::<Tree, _>(other, ("D",)).unwrap();
+    frame.enable_root_registry::<Tree>().unwrap();
+
+    assert!(maintain_hierarchy::<Tree>(&mut frame).is_empty());
+
+    frame.despawn(a).unwrap();
+    frame.despawn(other).unwrap();
+
+    let mut expected = vec![a, other];
+    expected.sort();
+    assert_eq!(maintain_hierarchy::<Tree>(&mut frame), expected);
+
+    assert_eq!(moss_hecs_hierarchy::validate::<Tree, _>(&frame), Ok(()));
+    assert_eq!(frame.children_vec::<Tree>(root), [b]);
+    assert!(frame.parent::<Tree>(c).is_err());
+
+    let roots = frame.registered_roots::<Tree>().unwrap();
+    assert!(roots.contains(&root));
+    assert!(!roots.contains(&other));
+}
+
+#[test]
+fn despawn_recursive_tag() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let a = frame.attach_new::<Tree, _>(root, ("A",)).unwrap();
+    let b = frame.attach_new::<Tree, _>(root, ("B",)).unwrap();
+    let c = frame.attach_new::<Tree, _>(a, ("C",)).unwrap();
+    frame.attach_new::<Tree, _>(c, ("D",)).unwrap();
+
+    // Tag while iterating, which could not despawn right away
+    let mut cmd = CommandBuffer::new();
+    for entity in frame.descendants_depth_first::<Tree>(root) {
+        if entity == a || entity == c {
+            cmd.insert(entity, (DespawnRecursive::<Tree>::new(),));
+        }
+    }
+    cmd.execute(&mut frame);
+
+    assert_eq!(flush_despawns::<Tree>(&mut frame), 3);
+    assert!(!frame.contains(a));
+    assert!(!frame.contains(c));
+    assert_eq!(frame.children_vec::<Tree>(root), [b]);
+    assert_eq!(flush_despawns::<Tree>(&mut frame), 0);
+}
+
+#[test]
+fn maintenance_deferred_attach() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let a = frame.attach_new::<Tree, _>(root, ("A",)).unwrap();
+    let b = frame.attach_new::<Tree, _>(root, ("B",)).unwrap();
+    let c = frame.attach_new::<Tree, _>(a, ("C",)).unwrap();
+    let loose = frame.spawn(("Loose",));
+    frame.enable_root_registry::<Tree>().unwrap();
+
+    // Queue attaches alongside the tag while iterating: `c` is rescued from the tagged `a`,
+    // while `loose` is attached below it and goes along with it
+    let mut cmd = CommandBuffer::new();
+    for entity in frame.descendants_depth_first::<Tree>(root) {
+        if entity == a {
+            cmd.insert(entity, (DespawnRecursive::<Tree>::new(),));
+            cmd.write(move |w: &mut Frame| {
+                w.attach::<Tree>(c, b).unwrap();
+                w.attach::<Tree>(loose, a).unwrap();
+            });
+        }
+    }
+    cmd.execute(&mut frame);
+
+    assert_eq!(frame.children_vec::<Tree>(a), [loose]);
+    assert!(maintain_hierarchy::<Tree>(&mut frame).is_empty());
+
+    assert!(!frame.contains(a));
+    assert!(!frame.contains(loose));
+    assert_eq!(frame.children_vec::<Tree>(root), [b]);
+    assert_eq!(frame.children_vec::<Tree>(b), [c]);
+    assert_eq!(moss_hecs_hierarchy::validate::<Tree, _>(&frame), Ok(()));
+
+    let roots = frame.registered_roots::<Tree>().unwrap();
+    assert_eq!(roots.len(), 1);
+    assert!(roots.contains(&root));
+}
+
+#[test]
+fn despawn_deferred() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let a = frame.attach_new::<Tree, _>(root, ("A",)).unwrap();
+    let b = frame.attach_new::<Tree, _>(root, ("B",)).unwrap();
+    let c = frame.attach_new::<Tree, _>(a, ("C",)).unwrap();
+
+    let mut cmd = CommandBuffer::new();
+    {
+        // Only shared access to the frame is needed
+        let subframe = SubWorldRef::<HierarchyQuery<Tree>>::new(&frame);
+        assert_eq!(
+            despawn_all_deferred::<Tree, _>(&subframe, &mut cmd, a),
+            [a, c]
+        );
+    }
+    assert!(frame.contains(a));
+
+    // Attached after queueing, but still despawned along with `a`
+    let late = frame.attach_new::<Tree, _>(c, ("Late",)).unwrap();
+    cmd.execute(&mut frame);
+
+    assert!(!frame.contains(a));
+    assert!(!frame.contains(c));
+    assert!(!frame.contains(late));
+    assert_eq!(frame.children_vec::<Tree>(root), [b]);
+}
+
+#[test]
+fn split_subtrees() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let a = frame.attach_new::<Tree, _>(root, ("A",)).unwrap();
+    for _ in 0..4 {
+        frame.attach_new::<Tree, _>(a, ("Leaf",)).unwrap();
+    }
+    frame.attach_new::<Tree, _>(root, ("B",)).unwrap();
+    let c = frame.attach_new::<Tree, _>(root, ("C",)).unwrap();
+    frame.attach_new::<Tree, _>(c, ("Leaf",)).unwrap();
+    frame.attach_new::<Tree, _>(root, ("D",)).unwrap();
+
+    let split = frame.split_subtrees::<Tree>(root, 3);
+    assert_eq!(split.interior, [root, a]);
+    assert_eq!(split.chunks.len(), 3);
+
+    let mut covered = split.interior.iter().copied().collect::<HashSet<_>>();
+    for chunk in &split.chunks {
+        let size = chunk
+            .iter()
+            .map(|&subtree| 1 + frame.descendants_depth_first::<Tree>(subtree).count())
+            .sum::<usize>();
+        assert!(size == 2 || size == 3);
+
+        for &subtree in chunk {
+            assert!(covered.insert(subtree));
+            for entity in frame.descendants_depth_first::<Tree>(subtree) {
+                assert!(covered.insert(entity));
+            }
+        }
+    }
+    assert_eq!(covered.len(), 10);
+
+    let single = frame.split_subtrees::<Tree>(root, 1);
+    assert_eq!(single.interior, [root]);
+    assert_eq!(single.chunks, [frame.children_vec::<Tree>(root)]);
+    assert!(frame
+        .split_subtrees::<Tree>(c, 0)
+        .chunks
+        .iter()
+        .all(|chunk| chunk.len() == 1));
+}
+
+#[test]
+#[cfg(feature = "parallel")]
+fn par_children() {
+    use rayon::iter::ParallelIterator;
+
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    for i in 0..64 {
+        frame.attach_new::<Tree, _>(root, (i,)).unwrap();
+    }
+
+    let sum = frame
+        .par_children::<Tree>(root)
+        .map(|child| *frame.get::<&i32>(child).unwrap())
+        .sum::<i32>();
+    assert_eq!(sum, (0..64).sum());
+
+    let collected = frame.par_children::<Tree>(root).collect::<Vec<_>>();
+    assert_eq!(collected, frame.children_vec::<Tree>(root));
+}
+
+#[test]
+fn topological_batches() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(root, ("Child2",)).unwrap();
+    let grandchild1 = frame
+        .attach_new::<Tree, _>(child1, ("Grandchild1",))
+        .unwrap();
+    let grandchild2 = frame
+        .attach_new::<Tree, _>(child2, ("Grandchild2",))
+        .unwrap();
+    let leaf = frame.attach_new::<Tree, _>(grandchild2, ("Leaf",)).unwrap();
+
+    let batches = frame.topological_batches::<Tree>(root);
+    assert_eq!(
+        batches,
+        [
+            vec![root],
+            vec![child1, child2],
+            vec![grandchild1, grandchild2],
+            vec![leaf]
+        ]
+    );
+
+    // Every parent is processed in an earlier batch than its children
+    for (i, batch) in batches.iter().enumerate().skip(1) {
+        for &entity in batch {
+            let parent = frame.parent::<Tree>(entity).unwrap();
+            assert!(batches[..i].iter().any(|batch| batch.contains(&parent)));
+        }
+    }
+
+    assert_eq!(frame.topological_batches::<Tree>(leaf), [vec![leaf]]);
+    assert_eq!(batches, frame.entities_by_depth::<Tree>(root));
+}
+
+#[test]
+fn entities_by_depth() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let child1 = frame.attach_new::<Tree, _>(root, ("Child1",)).unwrap();
+    let child2 = frame.attach_new::<Tree, _>(root, ("Child2",)).unwrap();
+    let grandchild = frame
+        .attach_new::<Tree, _>(child1, ("Grandchild",))
+        .unwrap();
+    let leaf = frame.attach_new::<Tree, _>(grandchild, ("Leaf",)).unwrap();
+
+    let expected = [
+        vec![root],
+        vec![child1, child2],
+        vec![grandchild],
+        vec![leaf],
+    ];
+    assert_eq!(frame.entities_by_depth::<Tree>(root), expected);
+
+    let mut buckets = DepthBuckets::new();
+    assert!(buckets.as_slice().is_empty());
+    assert_eq!(
+        frame.entities_by_depth_in::<Tree>(root, &mut buckets),
+        expected
+    );
+
+    // Reusing the buckets for a shallower tree leaves no stale levels behind
+    assert_eq!(
+        frame.entities_by_depth_in::<Tree>(child1, &mut buckets),
+        [vec![child1], vec![grandchild], vec![leaf]]
+    );
+    assert_eq!(
+        frame.entities_by_depth_in::<Tree>(child2, &mut buckets),
+        [vec![child2]]
+    );
+    assert_eq!(buckets.as_slice(), [vec![child2]]);
+}
+
+#[test]
+fn inherited_components() {
+    #[derive(Debug, Clone, PartialEq)]
+    struct Tint(u32);
+
+    let mut frame = Frame::default();
+    let root = frame.spawn((Tint(1),));
+    let a = frame.attach_new::<Tree, _>(root, ("a",)).unwrap();
+    let b = frame.attach_new::<Tree, _>(a, (Tint(2),)).unwrap();
+    let c = frame.attach_new::<Tree, _>(b, ("c",)).unwrap();
+    let d = frame.attach_new::<Tree, _>(root, ("d",)).unwrap();
+
+    assert_eq!(propagate_inherited::<Tree, Tint>(&mut frame, root), 3);
+    let tint = |frame: &Frame, e| frame.get::<&Tint>(e).map(|t| t.0).ok();
+    assert_eq!(tint(&frame, a), Some(1));
+    assert_eq!(tint(&frame, b), Some(2));
+    assert_eq!(tint(&frame, c), Some(2));
+    assert_eq!(tint(&frame, d), Some(1));
+    assert!(frame.get::<&Inherited<Tint>>(a).is_ok());
+    assert!(frame.get::<&Inherited<Tint>>(b).is_err());
+
+    // Inherited values follow changes to the declared ones
+    frame.get::<&mut Tint>(root).unwrap().0 = 3;
+    propagate_inherited::<Tree, Tint>(&mut frame, root);
+    assert_eq!(tint(&frame, a), Some(3));
+    assert_eq!(tint(&frame, c), Some(2));
+
+    // Without a declaring ancestor the inherited values are removed
+    frame.remove_one::<Tint>(root).unwrap();
+    assert_eq!(propagate_inherited::<Tree, Tint>(&mut frame, root), 1);
+    assert_eq!(tint(&frame, a), None);
+    assert_eq!(tint(&frame, d), None);
+    assert!(frame.get::<&Inherited<Tint>>(a).is_err());
+    assert_eq!(tint(&frame, b), Some(2));
+    assert_eq!(tint(&frame, c), Some(2));
+}
+
+#[test]
+fn derive_down_dirty() {
+    struct Local(i32);
+    #[derive(Debug, PartialEq)]
+    struct Global(i32);
+
+    let mut frame = Frame::default();
+    let root = frame.spawn((Local(1), Dirty::<Local>::new()));
+    let a = frame
+        .attach_new::<Tree, _>(root, (Local(2), Dirty::<Local>::new()))
+        .unwrap();
+    let b = frame
+        .attach_new::<Tree, _>(a, (Local(3), Dirty::<Local>::new()))
+        .unwrap();
+    let c = frame
+        .attach_new::<Tree, _>(root, (Local(10), Dirty::<Local>::new()))
+        .unwrap();
+
+    let derive = |frame: &mut Frame| {
+        derive_down::<Tree, Local, Global, _>(frame, root, |parent, local| {
+            Global(parent.map(|parent| parent.0).unwrap_or_default() + local.0)
+        })
+    };
+    let global = |frame: &Frame, e| frame.get::<&Global>(e).map(|g| g.0).unwrap();
+
+    assert_eq!(derive(&mut frame), 4);
+    assert_eq!(global(&frame, root), 1);
+    assert_eq!(global(&frame, a), 3);
+    assert_eq!(global(&frame, b), 6);
+    assert_eq!(global(&frame, c), 11);
+    assert!(frame.get::<&Dirty<Local>>(b).is_err());
+
+    // Only the dirty subtree is recomputed
+    frame.get::<&mut Local>(a).unwrap().0 = 5;
+    frame.insert_one(a, Dirty::<Local>::new()).unwrap();
+    assert_eq!(derive(&mut frame), 2);
+    assert_eq!(global(&frame, a), 6);
+    assert_eq!(global(&frame, b), 9);
+    assert_eq!(global(&frame, c), 11);
+
+    assert_eq!(derive(&mut frame), 0);
+}
+
+#[test]
+fn aggregate_dirty() {
+    #[derive(Debug, Clone, PartialEq)]
+    struct Extent(i32);
+
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let a = frame.attach_new::<Tree, _>(root, ("a",)).unwrap();
+    let a1 = frame
+        .attach_new::<Tree, _>(a, (Extent(3), Dirty::<Extent>::new()))
+        .unwrap();
+    let a2 = frame
+        .attach_new::<Tree, _>(a, (Extent(7), Dirty::<Extent>::new()))
+        .unwrap();
+    let b = frame
+        .attach_new::<Tree, _>(root, (Extent(5), Dirty::<Extent>::new()))
+        .unwrap();
+
+    let update = |frame: &mut Frame| {
+        aggregate::<Tree, Extent, _>(frame, root, |_, _, children| {
+            Extent(
+                children
+                    .iter()
+                    .map(|extent| extent.0)
+                    .max()
+                    .unwrap_or_default(),
+            )
+        })
+    };
+    let extent = |frame: &Frame, e| frame.get::<&Extent>(e).map(|e| e.0).unwrap();
+
+    assert_eq!(update(&mut frame), 2);
+    assert_eq!(extent(&frame, a), 7);
+    assert_eq!(extent(&frame, root), 7);
+    assert!(frame.get::<&Dirty<Extent>>(a1).is_err());
+
+    // Only the ancestors of the changed leaf are recomputed
+    frame.get::<&mut Extent>(a2).unwrap().0 = 1;
+    frame.insert_one(a2, Dirty::<Extent>::new()).unwrap();
+    assert_eq!(update(&mut frame), 2);
+    assert_eq!(extent(&frame, a), 3);
+    assert_eq!(extent(&frame, root), 5);
+    assert_eq!(extent(&frame, b), 5);
+
+    assert_eq!(update(&mut frame), 0);
+}
+
+#[test]
+fn bubble_events() {
+    struct Handles;
+
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root", Handles));
+    let panel = frame
+        .attach_new::<Tree, _>(root, ("Panel", Handles))
+        .unwrap();
+    let button = frame.attach_new::<Tree, _>(panel, ("Button",)).unwrap();
+
+    let mut visited = Vec::new();
+    let handled = frame.bubble::<Tree, _, _>(button, &mut visited, |frame, entity, visited| {
+        visited.push(entity);
+        frame.get::<&Handles>(entity).is_ok()
+    });
+    assert_eq!(handled, Some(panel));
+    assert_eq!(visited, [button, panel]);
+
+    let mut visited = Vec::new();
+    let handled = frame.bubble::<Tree, _, _>(button, &mut visited, |_, entity, visited| {
+        visited.push(entity);
+        false
+    });
+    assert_eq!(handled, None);
+    assert_eq!(visited, [button, panel, root]);
+}
+
+#[test]
+fn capture_events() {
+    struct Handles;
+
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let panel = frame
+        .attach_new::<Tree, _>(root, ("Panel", Handles))
+        .unwrap();
+    let button = frame
+        .attach_new::<Tree, _>(panel, ("Button", Handles))
+        .unwrap();
+
+    let mut visited = Vec::new();
+    let handled = frame.capture::<Tree, _, _>(button, &mut visited, |frame, entity, visited| {
+        visited.push(entity);
+        frame.get::<&Handles>(entity).is_ok()
+    });
+    assert_eq!(handled, Some(panel));
+    assert_eq!(visited, [root, panel]);
+
+    let mut visited = Vec::new();
+    let handled = frame.capture::<Tree, _, _>(button, &mut visited, |_, entity, visited| {
+        visited.push(entity);
+        false
+    });
+    assert_eq!(handled, None);
+    assert_eq!(visited, [root, panel, button]);
+}
+
+#[test]
+fn hit_test() {
+    // Horizontal extent of every entity
+    struct Rect(i32, i32);
+
+    let mut frame = Frame::default();
+    let root = frame.spawn((Rect(0, 100),));
+    let back = frame.attach_new::<Tree, _>(root, (Rect(0, 60),)).unwrap();
+    let button = frame.attach_new::<Tree, _>(back, (Rect(10, 20),)).unwrap();
+    let front = frame.attach_new::<Tree, _>(root, (Rect(40, 80),)).unwrap();
+
+    let pick = |frame: &Frame, x: i32| {
+        frame.hit_test::<Tree, _>(root, |frame, entity| {
+            let rect = frame.get::<&Rect>(entity).unwrap();
+            rect.0 <= x && x < rect.1
+        })
+    };
+
+    assert_eq!(pick(&frame, 15), Some(button));
+    assert_eq!(pick(&frame, 30), Some(back));
+    // The last child is on top of its earlier siblings
+    assert_eq!(pick(&frame, 50), Some(front));
+    assert_eq!(pick(&frame, 90), Some(root));
+    assert_eq!(pick(&frame, 120), None);
+}
+
+#[test]
+fn z_ordered() {
+    struct Z(f32);
+
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let overlay = frame
+        .attach_new::<Tree, _>(root, ("Overlay", Z(2.0)))
+        .unwrap();
+    let plain = frame.attach_new::<Tree, _>(root, ("Plain",)).unwrap();
+    let background = frame
+        .attach_new::<Tree, _>(root, ("Background", Z(-1.0)))
+        .unwrap();
+    let content = frame
+        .attach_new::<Tree, _>(root, ("Content", Z(0.0)))
+        .unwrap();
+    let label = frame
+        .attach_new::<Tree, _>(content, ("Label", Z(0.0)))
+        .unwrap();
+    let icon = frame
+        .attach_new::<Tree, _>(content, ("Icon", Z(0.0)))
+        .unwrap();
+
+    assert_eq!(
+        frame
+            .descendants_z_ordered::<Tree, Z>(root)
+            .collect::<Vec<_>>(),
+        [background, content, label, icon, overlay, plain]
+    );
+
+    // The stored order is untouched
+    assert_eq!(
+        frame.children_vec::<Tree>(root),
+        [overlay, plain, background, content]
+    );
+}
+
+#[test]
+fn document_order_navigation() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let a = frame.attach_new::<Tree, _>(root, ("a",)).unwrap();
+    let a1 = frame.attach_new::<Tree, _>(a, ("a1",)).unwrap();
+    let a2 = frame.attach_new::<Tree, _>(a, ("a2",)).unwrap();
+    let a21 = frame.attach_new::<Tree, _>(a2, ("a21",)).unwrap();
+    let b = frame.attach_new::<Tree, _>(root, ("b",)).unwrap();
+
+    let order = [root, a, a1, a2, a21, b];
+    for pair in order.windows(2) {
+        assert_eq!(frame.next_in_tree::<Tree>(pair[0], false), Some(pair[1]));
+        assert_eq!(frame.prev_in_tree::<Tree>(pair[1], false), Some(pair[0]));
+    }
+
+    assert_eq!(frame.next_in_tree::<Tree>(b, false), None);
+    assert_eq!(frame.next_in_tree::<Tree>(b, true), Some(root));
+    assert_eq!(frame.prev_in_tree::<Tree>(root, false), None);
+    assert_eq!(frame.prev_in_tree::<Tree>(root, true), Some(b));
+
+    // Walking forward from any entity visits the whole tree when wrapping
+    let mut cur = a21;
+    let mut visited = Vec::new();
+    for _ in 0..order.len() {
+        cur = frame.next_in_tree::<Tree>(cur, true).unwrap();
+        visited.push(cur);
+    }
+    assert_eq!(visited, [b, root, a, a1, a2, a21]);
+
+    let lonely = frame.spawn(("Lonely",));
+    assert_eq!(frame.next_in_tree::<Tree>(lonely, true), Some(lonely));
+    assert_eq!(frame.prev_in_tree::<Tree>(lonely, false), None);
+}
+
+#[test]
+fn flatten_skipping() {
+    struct Hidden;
+
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let a = frame.attach_new::<Tree, _>(root, ("a",)).unwrap();
+    let a1 = frame.attach_new::<Tree, _>(a, ("a1",)).unwrap();
+    let hidden = frame.attach_new::<Tree, _>(root, ("b", Hidden)).unwrap();
+    frame.attach_new::<Tree, _>(hidden, ("b1",)).unwrap();
+    let c = frame.attach_new::<Tree, _>(root, ("c",)).unwrap();
+
+    let is_hidden = |frame: &Frame, entity| frame.get::<&Hidden>(entity).is_ok();
+    assert_eq!(
+        frame
+            .flatten::<Tree, _>(root, is_hidden)
+            .collect::<Vec<_>>(),
+        [root, a, a1, c]
+    );
+    assert_eq!(
+        frame.flatten::<Tree, _>(root, |_, _| false).count(),
+        frame.descendants_depth_first::<Tree>(root).count() + 1
+    );
+    assert_eq!(frame.flatten::<Tree, _>(hidden, is_hidden).count(), 0);
+}
+
+#[test]
+fn layout_pass() {
+    // A row of boxes, each as wide as its children or its own minimum width
+    struct MinWidth(i32);
+    struct Width(i32);
+    struct Offset(i32);
+
+    let mut frame = Frame::default();
+    let root = frame.spawn((MinWidth(0),));
+    let a = frame.attach_new::<Tree, _>(root, (MinWidth(0),)).unwrap();
+    let a1 = frame.attach_new::<Tree, _>(a, (MinWidth(10),)).unwrap();
+    let a2 = frame.attach_new::<Tree, _>(a, (MinWidth(20),)).unwrap();
+    let b = frame.attach_new::<Tree, _>(root, (MinWidth(5),)).unwrap();
+
+    let mut measured = Vec::new();
+    let mut arranged = Vec::new();
+    let count = layout::<Tree, _, _>(
+        &mut frame,
+        root,
+        |frame, entity| {
+            measured.push(entity);
+            let children = frame
+                .children::<Tree>(entity)
+                .map(|child| frame.get::<&Width>(child).unwrap().0)
+                .sum::<i32>();
+            let width = children.max(frame.get::<&MinWidth>(entity).unwrap().0);
+            frame.insert_one(entity, Width(width)).unwrap();
+        },
+        |frame, entity| {
+            arranged.push(entity);
+            if frame.get::<&Offset>(entity).is_err() {
+                frame.insert_one(entity, Offset(0)).unwrap();
+            }
+
+            let mut x = frame.get::<&Offset>(entity).unwrap().0;
+            for child in frame.children_vec::<Tree>(entity) {
+                let width = frame.get::<&Width>(child).unwrap().0;
+                frame.insert_one(child, Offset(x)).unwrap();
+                x += width;
+            }
+        },
+    );
+
+    assert_eq!(count, 5);
+    assert_eq!(measured, [a1, a2, a, b, root]);
+    assert_eq!(arranged, [root, a, a1, a2, b]);
+
+    let width = |e| frame.get::<&Width>(e).unwrap().0;
+    assert_eq!([width(root), width(a), width(b)], [35, 30, 5]);
+    let offset = |e| frame.get::<&Offset>(e).unwrap().0;
+    assert_eq!([offset(a1), offset(a2), offset(b)], [0, 10, 30]);
+}
+
+#[test]
+fn ancestors_until() {
+    struct ScrollContainer;
+
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root", ScrollContainer));
+    let scroll = frame
+        .attach_new::<Tree, _>(root, ("Scroll", ScrollContainer))
+        .unwrap();
+    let panel = frame.attach_new::<Tree, _>(scroll, ("Panel",)).unwrap();
+    let button = frame.attach_new::<Tree, _>(panel, ("Button",)).unwrap();
+
+    let is_container = |frame: &Frame, entity| frame.get::<&ScrollContainer>(entity).is_ok();
+    assert_eq!(
+        frame
+            .ancestors_until::<Tree, _>(button, is_container)
+            .collect::<Vec<_>>(),
+        [panel, scroll]
+    );
+    assert_eq!(
+        frame
+            .ancestors_until::<Tree, _>(button, |_, _| false)
+            .collect::<Vec<_>>(),
+        [panel, scroll, root]
+    );
+    assert_eq!(
+        frame.ancestors_until::<Tree, _>(root, is_container).count(),
+        0
+    );
+}
+
+#[test]
+fn child_accessors() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let a = frame.attach_new::<Tree, _>(root, ("a",)).unwrap();
+    let b = frame.attach_new::<Tree, _>(root, ("b",)).unwrap();
+    let c = frame.attach_new::<Tree, _>(root, ("c",)).unwrap();
+    let only = frame.attach_new::<Tree, _>(a, ("only",)).unwrap();
+
+    let mut query = frame.query::<&Child<Tree>>();
+    let view = query.view();
+
+    let child = view.get(b).unwrap();
+    assert_eq!(child.parent(), root);
+    assert_eq!(child.prev_sibling(), a);
+    assert_eq!(child.next_sibling(), c);
+
+    // The siblings wrap around
+    assert_eq!(view.get(c).unwrap().next_sibling(), a);
+    assert_eq!(view.get(a).unwrap().prev_sibling(), c);
+
+    let child = view.get(only).unwrap();
+    assert_eq!(child.parent(), a);
+    assert_eq!(child.next_sibling(), only);
+    assert_eq!(child.prev_sibling(), only);
+}
+
+#[test]
+fn parent_accessors() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let a = frame.attach_new::<Tree, _>(root, ("a",)).unwrap();
+    let b = frame.attach_new::<Tree, _>(root, ("b",)).unwrap();
+    let only = frame.attach_new::<Tree, _>(a, ("only",)).unwrap();
+
+    {
+        let mut children = frame.query::<&Child<Tree>>();
+        let children = children.view();
+
+        let mut found = frame
+            .query::<(&Parent<Tree>, &&str)>()
+            .iter()
+            .map(|(_, (parent, name))| {
+                let first = parent.view_first_child(&children).unwrap();
+                (*name, parent.num_children(), first, parent.last_child())
+            })
+            .collect::<Vec<_>>();
+        found.sort();
+        assert_eq!(found, [("Root", 2, a, b), ("a", 1, only, only)]);
+
+        let parent = frame.get::<&Parent<Tree>>(root).unwrap();
+        assert_eq!(parent.first_child(&frame).unwrap(), a);
+    }
+
+    // The component is removed along with the last child
+    frame.detach::<Tree>(only).unwrap();
+    assert!(frame.get::<&Parent<Tree>>(a).is_err());
+}
+
+#[test]
+fn edge_data() {
+    #[derive(Debug, PartialEq)]
+    struct Split(f32);
+
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let left = frame.attach_new::<Tree, _>(root, ("Left",)).unwrap();
+    let middle = frame.attach_new::<Tree, _>(root, ("Middle",)).unwrap();
+    let right = frame.attach_new::<Tree, _>(root, ("Right",)).unwrap();
+
+    frame.set_edge_data::<Tree, _>(left, Split(0.25)).unwrap();
+    frame.set_edge_data::<Tree, _>(right, Split(0.75)).unwrap();
+
+    let splits = |frame: &Frame| {
+        frame
+            .children_with_edge_data::<Tree, Split>(root)
+            .map(|(child, edge)| (child, edge.0))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(splits(&frame), [(left, 0.25), (right, 0.75)]);
+
+    // Written while dragging
+    frame.get::<&mut EdgeData<Tree, Split>>(right).unwrap().0 = 0.5;
+    frame.set_edge_data::<Tree, _>(left, Split(0.1)).unwrap();
+    assert_eq!(splits(&frame), [(left, 0.1), (right, 0.5)]);
+
+    // The data does not follow the child to another parent
+    frame.attach::<Tree>(right, middle).unwrap();
+    assert_eq!(splits(&frame), [(left, 0.1)]);
+    assert_eq!(
+        frame.children_with_edge_data::<Tree, Split>(middle).count(),
+        0
+    );
+
+    let loose = frame.spawn(("Loose",));
+    assert!(frame.set_edge_data::<Tree, _>(loose, Split(1.0)).is_err());
+}
+
+#[test]
+fn slots() {
+    #[derive(Debug, PartialEq, Eq)]
+    enum Slot {
+        Header,
+        Content,
+    }
+
+    let mut frame = Frame::default();
+    let card = frame.spawn(("Card",));
+    let title = frame.spawn(("Title",));
+    let body = frame.spawn(("Body",));
+    let other = frame.spawn(("Other",));
+
+    frame
+        .attach_slot::<Tree, _>(title, card, Slot::Header)
+        .unwrap();
+    frame
+        .attach_slot::<Tree, _>(body, card, Slot::Content)
+        .unwrap();
+    assert_eq!(
+        frame.child_in_slot::<Tree, _>(card, &Slot::Header),
+        Some(title)
+    );
+    assert_eq!(
+        frame.child_in_slot::<Tree, _>(card, &Slot::Content),
+        Some(body)
+    );
+    assert_eq!(frame.children_vec::<Tree>(card), [title, body]);
+
+    assert!(matches!(
+        frame.attach_slot::<Tree, _>(other, card, Slot::Header),
+        Err(HierarchyError::SlotOccupied { child, parent, occupant })
+            if child == other && parent == card && occupant == title
+    ));
+    assert!(frame.parent::<Tree>(other).is_err());
+
+    // Attaching the occupant into its own slot again does nothing
+    frame
+        .attach_slot::<Tree, _>(title, card, Slot::Header)
+        .unwrap();
+    assert_eq!(frame.children_vec::<Tree>(card), [title, body]);
+
+    // Detaching frees the slot
+    frame.detach::<Tree>(title).unwrap();
+    assert_eq!(frame.child_in_slot::<Tree, _>(card, &Slot::Header), None);
+    frame
+        .attach_slot::<Tree, _>(other, card, Slot::Header)
+        .unwrap();
+    assert_eq!(
+        frame.child_in_slot::<Tree, _>(card, &Slot::Header),
+        Some(other)
+    );
+}
+
+#[test]
+fn children_by_edge_key() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let low = frame.attach_new::<Tree, _>(root, ("Low",)).unwrap();
+    let none = frame.attach_new::<Tree, _>(root, ("None",)).unwrap();
+    let high = frame.attach_new::<Tree, _>(root, ("High",)).unwrap();
+    let high2 = frame.attach_new::<Tree, _>(root, ("High2",)).unwrap();
+
+    frame.set_edge_data::<Tree, _>(low, 3_u32).unwrap();
+    frame.set_edge_data::<Tree, _>(high, 1_u32).unwrap();
+    frame.set_edge_data::<Tree, _>(high2, 1_u32).unwrap();
+
+    let ordered = frame.children_by_edge_key::<Tree, u32>(root);
+    assert_eq!(ordered.len(), 4);
+    assert_eq!(ordered.collect::<Vec<_>>(), [high, high2, low, none]);
+
+    // The stored order is untouched
+    assert_eq!(frame.children_vec::<Tree>(root), [low, none, high, high2]);
+}
+
+#[test]
+fn policy_max_children() {
+    struct Split;
+
+    impl HierarchyPolicy for Split {
+        const MAX_CHILDREN: Option<usize> = Some(2);
+    }
+
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let left = frame.spawn(("Left",));
+    let right = frame.spawn(("Right",));
+    let extra = frame.spawn(("Extra",));
+
+    // Not enforced until enabled
+    frame.attach::<Split>(left, root).unwrap();
+    frame.attach::<Split>(right, root).unwrap();
+    frame.attach::<Split>(extra, root).unwrap();
+    frame.detach::<Split>(extra).unwrap();
+
+    frame.enable_policy::<Split>().unwrap();
+    frame.enable_policy::<Split>().unwrap();
+
+    assert!(matches!(
+        frame.attach::<Split>(extra, root),
+        Err(HierarchyError::TooManyChildren { parent, max: 2 }) if parent == root
+    ));
+    assert!(frame.attach_front::<Split>(extra, root).is_err());
+    assert!(frame.attach_before::<Split>(extra, left).is_err());
+    assert!(frame.parent::<Split>(extra).is_err());
+
+    let entities = frame.iter().count();
+    assert!(frame.attach_new::<Split, _>(root, ("New",)).is_err());
+    assert!(matches!(
+        frame.merge_into::<Split, _>(&[left, right, extra], ("Merged",)),
+        Err(HierarchyError::TooManyChildren { max: 2, .. })
+    ));
+    assert_eq!(frame.iter().count(), entities);
+    assert_eq!(frame.children_vec::<Split>(root), [left, right]);
+
+    // Moving a child within its parent does not add a child
+    frame.attach_before::<Split>(right, left).unwrap();
+    assert_eq!(frame.children_vec::<Split>(root), [right, left]);
+
+    // Children of other parents are not affected
+    frame.attach::<Split>(extra, left).unwrap();
+
+    frame.detach::<Split>(left).unwrap();
+    frame.attach::<Split>(extra, root).unwrap();
+    assert_eq!(frame.children_vec::<Split>(root), [right, extra]);
+}
+
+#[test]
+fn policy_at_index() {
+    struct Single;
+
+    impl HierarchyPolicy for Single {
+        const MAX_CHILDREN: Option<usize> = Some(1);
+    }
+
+    let mut frame = Frame::default();
+    frame.enable_policy::<Single>().unwrap();
+
+    let root = frame.spawn(("Root",));
+    let first = frame.attach_new::<Single, _>(root, ("First",)).unwrap();
+    let subtree = frame.detach_subtree::<Single>(first).unwrap();
+    let second = frame.attach_new::<Single, _>(root, ("Second",)).unwrap();
+
+    assert!(matches!(
+        subtree.reattach_at(&mut frame, root, 0),
+        Err(HierarchyError::TooManyChildren { max: 1, .. })
+    ));
+    assert!(frame.parent::<Single>(first).is_err());
+
+    let entities = frame.iter().count();
+    assert!(matches!(
+        TreeBuilder::<Single>::new()
+            .attach_move(TreeBuilder::<Single>::from(("Grafted",)).attach_move(("Leaf",)))
+            .graft_at(&mut frame, root, 0),
+        Err(HierarchyError::TooManyChildren { max: 1, .. })
+    ));
+    assert_eq!(frame.iter().count(), entities);
+    assert_eq!(frame.children_vec::<Single>(root), [second]);
+
+    // Undoing a detach can not move the child back into a parent which filled up meanwhile
+    let mut journal = HierarchyJournal::<Single>::new();
+    journal.detach(&mut frame, second).unwrap();
+    frame.attach::<Single>(first, root).unwrap();
+    assert!(matches!(
+        journal.undo(&mut frame),
+        Err(HierarchyError::TooManyChildren { max: 1, .. })
+    ));
+    assert_eq!(frame.children_vec::<Single>(root), [first]);
+    assert!(journal.can_undo());
+}
+
+#[test]
+fn policy_max_depth() {
+    struct Shallow;
+
+    impl HierarchyPolicy for Shallow {
+        const MAX_DEPTH: Option<usize> = Some(2);
+    }
+
+    let mut frame = Frame::default();
+    frame.enable_policy::<Shallow>().unwrap();
+
+    let root = frame.spawn(("Root",));
+    let a = frame.attach_new::<Shallow, _>(root, ("a",)).unwrap();
+    let b = frame.attach_new::<Shallow, _>(a, ("b",)).unwrap();
+    assert!(matches!(
+        frame.attach_new::<Shallow, _>(b, ("c",)),
+        Err(HierarchyError::TooDeep { max: 2, .. })
+    ));
+
+    // A subtree which would end up too deep is rejected as a whole
+    let other = frame.spawn(("Other",));
+    let leaf = frame.attach_new::<Shallow, _>(other, ("Leaf",)).unwrap();
+    assert!(matches!(
+        frame.attach::<Shallow>(other, a),
+        Err(HierarchyError::TooDeep { child, max: 2 }) if child == other
+    ));
+    assert!(frame.parent::<Shallow>(other).is_err());
+
+    frame.attach::<Shallow>(other, root).unwrap();
+    assert_eq!(frame.parent::<Shallow>(leaf).unwrap(), other);
+    assert!(frame.attach_after::<Shallow>(leaf, b).is_ok());
+}
+
+#[test]
+fn policy_shape() {
+    struct Document;
+    struct Table;
+    struct TableRow;
+
+    impl HierarchyPolicy for Document {
+        fn can_attach(frame: &Frame, child: Entity, parent: Entity) -> bool {
+            frame.get::<&TableRow>(child).is_err() || frame.get::<&Table>(parent).is_ok()
+        }
+    }
+
+    let mut frame = Frame::default();
+    frame.enable_policy::<Document>().unwrap();
+
+    let body = frame.spawn(("Body",));
+    let table = frame.attach_new::<Document, _>(body, (Table,)).unwrap();
+    let row = frame.attach_new::<Document, _>(table, (TableRow,)).unwrap();
+
+    let stray = frame.spawn((TableRow,));
+    assert!(matches!(
+        frame.attach::<Document>(stray, body),
+        Err(HierarchyError::NotAllowed { child, parent }) if child == stray && parent == body
+    ));
+    assert!(frame.attach_after::<Document>(stray, table).is_err());
+    assert!(frame.attach_before::<Document>(stray, row).is_ok());
+    assert_eq!(frame.children_vec::<Document>(table), [stray, row]);
+}
+
+#[test]
+fn policy_rollback() {
+    struct Single;
+
+    impl HierarchyPolicy for Single {
+        const MAX_CHILDREN: Option<usize> = Some(1);
+    }
+
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let a = frame.attach_new::<Single, _>(root, ("a",)).unwrap();
+    let b = frame.attach_new::<Single, _>(root, ("b",)).unwrap();
+    frame.enable_policy::<Single>().unwrap();
+
+    let result = frame.hierarchy_transaction::<Single, _, _>(|tx| {
+        tx.detach(a)?;
+        tx.attach(a, root)
+    });
+
+    // Rolling back restores the children which already violated the policy
+    assert!(matches!(
+        result,
+        Err(HierarchyError::TooManyChildren { max: 1, .. })
+    ));
+    assert_eq!(frame.children_vec::<Single>(root), [a, b]);
+}
+
+#[test]
+fn policy_set_children() {
+    struct Pair;
+
+    impl HierarchyPolicy for Pair {
+        const MAX_CHILDREN: Option<usize> = Some(2);
+    }
+
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let a = frame.attach_new::<Pair, _>(root, ("a",)).unwrap();
+    let b = frame.attach_new::<Pair, _>(root, ("b",)).unwrap();
+    let c = frame.attach_new::<Pair, _>(root, ("c",)).unwrap();
+    let snapshot = HierarchySnapshot::<Pair>::capture(&frame, root);
+
+    frame.set_children::<Pair>(root, &[b, a]).unwrap();
+    frame.enable_policy::<Pair>().unwrap();
+
+    assert!(matches!(
+        frame.set_children::<Pair>(root, &[a, b, c]),
+        Err(HierarchyError::TooManyChildren { parent, max: 2 }) if parent == root
+    ));
+    assert_eq!(frame.children_vec::<Pair>(root), [b, a]);
+
+    frame.set_children::<Pair>(root, &[c, a]).unwrap();
+    assert_eq!(frame.children_vec::<Pair>(root), [c, a]);
+
+    // Orphans are rejected as a whole
+    let dead = frame.spawn(("Dead",));
+    let x = frame.attach_new::<Pair, _>(dead, ("x",)).unwrap();
+    let y = frame.attach_new::<Pair, _>(dead, ("y",)).unwrap();
+    frame.despawn(dead).unwrap();
+
+    let adopter = frame.spawn(("Adopter",));
+    frame.attach::<Pair>(b, adopter).unwrap();
+    assert!(matches!(
+        frame.adopt_orphans::<Pair>(adopter),
+        Err(HierarchyError::TooManyChildren { max: 2, .. })
+    ));
+    assert_eq!(frame.children_vec::<Pair>(adopter), [b]);
+
+    frame.detach::<Pair>(b).unwrap();
+    assert_eq!(frame.adopt_orphans::<Pair>(adopter).unwrap(), [x, y]);
+
+    // Restoring returns to the captured state regardless of the policy
+    snapshot.restore(&mut frame).unwrap();
+    assert_eq!(frame.children_vec::<Pair>(root), [a, b, c]);
+}
+
+#[test]
+fn hierarchy_query_full() {
+    let mut frame = Frame::default();
+    frame.enable_root_registry::<Tree>().unwrap();
+
+    let root = frame.spawn(("Root",));
+    let a = frame.attach_new::<Tree, _>(root, ("A",)).unwrap();
+    let b = frame.attach_new::<Tree, _>(a, ("B",)).unwrap();
+
+    let subframe = SubWorldRef::<HierarchyQueryFull<Tree>>::new(&frame);
+    assert_eq!(subframe.parent::<Tree>(b).unwrap(), a);
+    assert_eq!(subframe.ancestors::<Tree>(b).collect::<Vec<_>>(), [a, root]);
+    assert_eq!(
+        subframe
+            .descendants_depth_first::<Tree>(root)
+            .collect::<Vec<_>>(),
+        [a, b]
+    );
+    assert_eq!(subframe.registered_roots::<Tree>().unwrap(), [root]);
+
+    let roots = subframe
+        .roots::<Tree>()
+        .unwrap()
+        .iter()
+        .map(|(e, _)| e)
+        .collect::<Vec<_>>();
+    assert_eq!(roots, [root]);
+
+    // A narrower subworld lacks the registry
+    let subframe = SubWorldRef::<HierarchyQuery<Tree>>::new(&frame);
+    assert_eq!(subframe.registered_roots::<Tree>(), None);
+}
+
+#[test]
+fn subworld_reads() {
+    let mut frame = Frame::default();
+    let root = frame.spawn(("Root",));
+    let a = frame.attach_new::<Tree, _>(root, ("A",)).unwrap();
+    let b = frame.attach_new::<Tree, _>(root, ("B",)).unwrap();
+    let c = frame.attach_new::<Tree, _>(a, ("C",)).unwrap();
+
+    let subframe = SubWorldRef::<HierarchyQuery<Tree>>::new(&frame);
+    assert_eq!(subframe.children::<Tree>(root).collect::<Vec<_>>(), [a, b]);
+    assert_eq!(subframe.ancestors::<Tree>(c).collect::<Vec<_>>(), [a, root]);
+    assert_eq!(
+        subframe
+            .descendants_depth_first::<Tree>(root)
+            .collect::<Vec<_>>(),
+        [a, c, b]
+    );
+    assert_eq!(
+        subframe
+            .descendants_breadth_first::<Tree>(root)
+            .collect::<Vec<_>>(),
+        [a, b, c]
+    );
+    assert_eq!(
+        subframe
+            .visit::<Tree, _>(root, move |_, e| e != a)
+            .collect::<Vec<_>>(),
+        [b]
+    );
+
+    let roots = subframe
+        .roots::<Tree>()
+        .unwrap()
+        .iter()
+        .map(|(e, _)| e)
+        .collect::<Vec<_>>();
+    assert_eq!(roots, [root]);
+
+    // Filtering by a component outside the subworld finds nothing
+    assert_eq!(subframe.children_with::<Tree, &str>(root).count(), 0);
+
+    // Without access to the hierarchy lookups fail instead of reaching past the subworld
+    let unrelated = SubWorldRef::<(&&'static str,)>::new(&frame);
+    assert!(unrelated.parent::<Tree>(c).is_err());
+    assert_eq!(unrelated.children::<Tree>(root).count(), 0);
+    assert_eq!(unrelated.ancestors::<Tree>(c).count(), 0);
+}